@@ -0,0 +1,41 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use microbench::{Options, bench_group, bench_main, retain};
+
+fn fibonacci_iterative(n: u64) -> u64 {
+    let (mut x, mut y, mut z) = (0, 1, 1);
+    for _ in 0..n { x = y; y = z; z = x + y; }
+    x
+}
+
+fn fibonacci_recursive(n: u64) -> u64 {
+    if n < 2 {
+        n
+    } else {
+        fibonacci_recursive(n - 2) + fibonacci_recursive(n - 1)
+    }
+}
+
+bench_group!(iterative, Options::default();
+    "fibonacci_8" => || fibonacci_iterative(retain(8)),
+    "fibonacci_16" => || fibonacci_iterative(retain(16)),
+);
+
+bench_group!(recursive, Options::default();
+    "fibonacci_8" => || fibonacci_recursive(retain(8)),
+    "fibonacci_16" => || fibonacci_recursive(retain(16)),
+);
+
+bench_main!(iterative, recursive);
@@ -0,0 +1,27 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::env;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(microbench_unoptimized)");
+
+    // Benchmarks are (almost always) compiled with the same optimization level
+    // as this crate.
+    let level = env::var("OPT_LEVEL").unwrap_or_default();
+    if level == "0" {
+        println!("cargo:rustc-cfg=microbench_unoptimized");
+    }
+    println!("cargo:rustc-env=MICROBENCH_OPT_LEVEL={}", level);
+}
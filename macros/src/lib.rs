@@ -0,0 +1,82 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Procedural macros for `microbench`.
+//!
+//! This crate should not be used directly, enable the `macros` crate feature of
+//! `microbench` instead.
+
+#![warn(missing_copy_implementations, missing_debug_implementations, missing_docs)]
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Ident, Span, TokenStream, TokenTree};
+
+/// Registers the annotated function as a benchmark.
+///
+/// See the documentation of `microbench::microbench` for more information.
+#[proc_macro_attribute]
+pub fn microbench(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    if let Some(token) = attribute.into_iter().next() {
+        return error("unexpected arguments", token.span());
+    }
+
+    let name = match function_name(item.clone()) {
+        Some(name) => name,
+        None => return error("expected a function", Span::call_site()),
+    };
+
+    let registration = format!(
+        r#"
+        const _: () = {{
+            fn run(options: &::microbench::Options, name: &str) {{
+                ::microbench::bench(options, name, {name});
+            }}
+
+            ::microbench::inventory::submit! {{
+                ::microbench::Benchmark::new("{name}", module_path!(), run)
+            }}
+        }};
+        "#,
+        name = name,
+    );
+
+    let mut output = item;
+    output.extend(registration.parse::<TokenStream>().unwrap());
+    output
+}
+
+/// Returns the name of the function in the supplied item, if any.
+fn function_name(item: TokenStream) -> Option<Ident> {
+    let mut tokens = item.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "fn" => {
+                return match tokens.next() {
+                    Some(TokenTree::Ident(name)) => Some(name),
+                    _ => None,
+                };
+            },
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => return None,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Returns a token stream that emits the supplied error message.
+fn error(message: &str, span: Span) -> TokenStream {
+    let output = format!("compile_error!({:?});", message).parse::<TokenStream>().unwrap();
+    output.into_iter().map(|mut t| { t.set_span(span); t }).collect()
+}
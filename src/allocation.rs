@@ -0,0 +1,137 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allocation counting.
+//!
+//! Allocations can only be counted when a [`CountingAllocator`] is installed
+//! as the global allocator.
+//!
+//! ```
+//! use std::alloc::{System};
+//!
+//! use microbench::allocation::{CountingAllocator};
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator<System> = CountingAllocator(System);
+//! ```
+//!
+//! [`CountingAllocator`]: struct.CountingAllocator.html
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::ops::{ControlFlow};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::{Options, Sample, bench_impl, format_quantity, note, retain_ref, sample_impl};
+use crate::statistics::{Model};
+use crate::time::{Stopwatch, TimeSource};
+
+/// The number of allocations performed by the counting allocator.
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+/// Whether the counting allocator has been used.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// An allocator that counts the allocations performed by another allocator.
+///
+/// Allocations (including reallocations) are counted for all threads.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CountingAllocator<A>(pub A);
+
+impl<A> CountingAllocator<A> {
+    /// Records an allocation.
+    fn count(&self) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        self.count();
+        self.0.realloc(ptr, layout, size)
+    }
+}
+
+/// Returns the number of allocations that have been performed by the counting
+/// allocator or `None` if the counting allocator is not installed.
+pub fn allocations() -> Option<u64> {
+    if INSTALLED.load(Ordering::Relaxed) {
+        Some(ALLOCATIONS.load(Ordering::Relaxed))
+    } else {
+        None
+    }
+}
+
+/// Benchmarks the supplied function and prints the results (including the
+/// number of allocations per iteration).
+///
+/// See [`measure_allocations`](fn.measure_allocations.html) for more
+/// information.
+pub fn bench_allocations<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
+    let mut counts = vec![];
+    bench_impl(options, name, || {
+        let (samples, retries) = measure_allocations_impl(options, f);
+        let (samples, allocations): (Vec<Sample>, Vec<u64>) = samples.into_iter().unzip();
+        counts = samples.iter().map(|s| s.iterations).zip(allocations).collect();
+        (samples, retries)
+    });
+
+    if allocations().is_none() {
+        note(options, format_args!("    warning: failed to count allocations (allocator not installed)"));
+    } else if counts.len() >= 2 {
+        let model = counts.iter().map(|c| (c.0 as f64, c.1 as f64)).collect::<Model>();
+        let allocations = format_quantity(options, model.beta.max(0.0));
+        note(options, format_args!("    {} allocs/iter", allocations));
+    }
+}
+
+/// Measures the execution time and number of allocations of the supplied
+/// function.
+///
+/// The allocations performed while executing the supplied function are counted
+/// for each sample so that they can be regressed against the number of
+/// iterations just like execution time. The counts will all be zero if the
+/// counting allocator is not installed as the global allocator.
+pub fn measure_allocations<T>(
+    options: &Options, f: impl FnMut() -> T
+) -> Vec<(Sample, u64)> {
+    measure_allocations_impl(options, f).0
+}
+
+/// Measures the execution time and number of allocations of the supplied
+/// function and also returns the number of retries.
+fn measure_allocations_impl<T>(
+    options: &Options, mut f: impl FnMut() -> T
+) -> (Vec<(Sample, u64)>, u32) {
+    sample_impl(options, options.clock.resolution(), |iterations| {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let stopwatch = Stopwatch::new(options.clock);
+        for _ in 0..iterations { retain_ref(&f()); }
+        let elapsed = stopwatch.elapsed();
+        Some((elapsed, ALLOCATIONS.load(Ordering::Relaxed) - allocations))
+    }, |_, _| ControlFlow::Continue(()))
+}
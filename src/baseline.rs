@@ -0,0 +1,484 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Baselines (saved results of previous runs).
+//!
+//! While recording (see [`start_recording`](fn.start_recording.html)), the
+//! results of every benchmark are recorded so they can be saved as a baseline.
+//! When a reference baseline is set (see
+//! [`set_reference`](fn.set_reference.html)), the results of every benchmark
+//! are printed along with the change relative to the baseline and
+//! [`regressions`](fn.regressions.html) can be used to check for benchmarks
+//! that have become significantly slower.
+
+use std::collections::{BTreeMap};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex};
+
+use crate::{Verdict, p_value, relative_difference};
+use crate::json::{Value};
+use crate::metadata::{self, Tags};
+use crate::statistics::{StudentT};
+use crate::system::{self, Info};
+use crate::time::{Nanoseconds};
+use crate::utility::{json_number, json_string};
+
+/// The baseline being recorded, if any.
+static RECORDING: Mutex<Option<Baseline>> = Mutex::new(None);
+/// The baseline results are compared to, if any.
+static REFERENCE: Mutex<Option<Baseline>> = Mutex::new(None);
+/// The changes relative to the reference baseline observed so far.
+static CHANGES: Mutex<Vec<(String, Change)>> = Mutex::new(Vec::new());
+
+/// The estimated execution time of a benchmark.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// The estimated execution time per iteration.
+    pub estimate: Nanoseconds<f64>,
+    /// The half-width of the confidence interval of the estimated execution time
+    /// per iteration (`NaN` if unknown).
+    pub interval: Nanoseconds<f64>,
+    /// The confidence level of the confidence interval (e.g., `0.95`, `NaN` if
+    /// unknown).
+    pub confidence: f64,
+    /// The standard error of the estimated execution time per iteration (`NaN`
+    /// if unknown).
+    pub error: Nanoseconds<f64>,
+    /// The degrees of freedom of the estimated execution time per iteration
+    /// (`NaN` if unknown).
+    pub dof: f64,
+}
+
+impl Estimate {
+    /// Returns the change in execution time from the supplied baseline estimate
+    /// to this estimate.
+    pub fn change(&self, baseline: &Estimate) -> Change {
+        let ratio = self.estimate.0 / baseline.estimate.0;
+        let difference = (self.estimate.0 - baseline.estimate.0).abs();
+        let noise = Some(self.interval.0 + baseline.interval.0).filter(|n| !n.is_nan()).map(|n| difference <= n);
+        let difference = self.distribution().difference(&baseline.distribution());
+        let relative = relative_difference(&difference, baseline.estimate.0);
+        Change { ratio, noise, p: p_value(&difference), relative }
+    }
+
+    /// Returns the sampling distribution of the estimated execution time per
+    /// iteration.
+    fn distribution(&self) -> StudentT {
+        StudentT { location: self.estimate.0, scale: self.error.0, dof: self.dof }
+    }
+}
+
+/// A change in the estimated execution time of a benchmark.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Change {
+    /// The ratio of the new estimate to the baseline estimate (e.g., `1.1` for
+    /// a 10% slowdown).
+    pub ratio: f64,
+    /// Whether the change is within the noise of the measurements (i.e., the
+    /// confidence intervals overlap) or `None` if unknown (e.g., if either
+    /// estimate has no interval, as for estimators other than OLS).
+    pub noise: Option<bool>,
+    /// The p-value of Welch's t-test of the change (`NaN` if unknown, e.g.,
+    /// if either estimate has no standard error).
+    pub p: f64,
+    /// The (approximate) sampling distribution of the relative change (e.g.,
+    /// with a location of `0.1` for a 10% slowdown), which has `NaN` parameters
+    /// if unknown.
+    pub relative: StudentT,
+}
+
+impl Change {
+    /// Returns the interval around the relative change (e.g., `(0.05, 0.15)`
+    /// for a slowdown of between 5% and 15%) that contains the true relative
+    /// change with the supplied probability (e.g., `0.95`).
+    ///
+    /// This is the effect size of the change. With enough samples, even tiny
+    /// changes are significant so this should be used to judge whether a
+    /// significant change is also a relevant change.
+    pub fn interval(&self, level: f64) -> (f64, f64) {
+        if self.relative.scale.is_finite() && self.relative.dof.is_finite() {
+            self.relative.interval(level)
+        } else {
+            (f64::NAN, f64::NAN)
+        }
+    }
+
+    /// Returns the verdict for this change at the supplied confidence level
+    /// (e.g., `0.95`).
+    ///
+    /// If the p-value of this change is unknown, the change is significant if
+    /// it is known to not be within the noise of the measurements.
+    pub fn verdict(&self, level: f64) -> Verdict {
+        if !self.p.is_nan() {
+            Verdict::new(self.ratio, self.p, level)
+        } else if self.noise != Some(false) {
+            Verdict::NoDifference
+        } else if self.ratio < 1.0 {
+            Verdict::Faster
+        } else {
+            Verdict::Slower
+        }
+    }
+}
+
+/// The saved results of a run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Baseline {
+    estimates: BTreeMap<String, Estimate>,
+    system: Option<Info>,
+    tags: BTreeMap<String, Tags>,
+}
+
+impl Baseline {
+    /// Constructs a new empty `Baseline`.
+    pub fn new() -> Self {
+        Baseline::default()
+    }
+
+    /// Returns the path of the baseline file for the baseline with the supplied
+    /// name (`<target>/microbench/<name>.json`).
+    ///
+    /// The target directory is `CARGO_TARGET_DIR` if set or `target` otherwise.
+    pub fn path(name: &str) -> PathBuf {
+        let target = env::var_os("CARGO_TARGET_DIR").unwrap_or_else(|| "target".into());
+        Path::new(&target).join("microbench").join(format!("{}.json", name))
+    }
+
+    /// Loads a baseline from the supplied file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut baseline = Baseline::new();
+        for line in fs::read_to_string(path)?.lines().filter(|l| !l.trim().is_empty()) {
+            let value = Value::parse(line).map_err(invalid)?;
+            let field = |key| value.get(key).and_then(Value::as_f64).map(Nanoseconds);
+            let name = value.get("name").and_then(Value::as_str);
+            match (name, field("estimate"), field("interval")) {
+                (Some(name), Some(estimate), Some(interval)) => {
+                    // Baselines saved before standard errors were recorded.
+                    let error = field("error").unwrap_or(Nanoseconds(f64::NAN));
+                    let dof = field("dof").map_or(f64::NAN, |d| d.0);
+                    let confidence = field("confidence").map_or(f64::NAN, |c| c.0);
+                    baseline.insert(name, Estimate { estimate, interval, confidence, error, dof });
+                    baseline.set_tags(name, metadata::from_json(value.get("tags")));
+                    if baseline.system.is_none() {
+                        baseline.system = value.get("system").and_then(Info::from_json);
+                    }
+                },
+                _ => return Err(invalid("missing or invalid fields".into())),
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// Saves this baseline to the supplied file (one JSON object per line per
+    /// benchmark, each including the system information if known), creating
+    /// the parent directories of the file if necessary.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        let system = self.system.as_ref().map(|s| format!(r#","system":{}"#, s.to_json()));
+        for (name, estimate) in &self.estimates {
+            writeln!(
+                file,
+                concat!(
+                    r#"{{"name":{},"estimate":{},"interval":{},"confidence":{},"error":{},"dof":{},"#,
+                    r#""tags":{}{}}}"#,
+                ),
+                json_string(name),
+                json_number(estimate.estimate.0),
+                json_number(estimate.interval.0),
+                json_number(estimate.confidence),
+                json_number(estimate.error.0),
+                json_number(estimate.dof),
+                metadata::to_json(self.tags(name)),
+                system.as_deref().unwrap_or_default(),
+            )?;
+        }
+        file.flush()
+    }
+
+    /// Returns the estimate for the benchmark with the supplied name, if any.
+    pub fn get(&self, name: &str) -> Option<Estimate> {
+        self.estimates.get(name).cloned()
+    }
+
+    /// Sets the estimate for the benchmark with the supplied name.
+    pub fn insert(&mut self, name: impl Into<String>, estimate: Estimate) {
+        self.estimates.insert(name.into(), estimate);
+    }
+
+    /// Returns the tags attached to the benchmark with the supplied name.
+    pub fn tags(&self, name: &str) -> &[(String, String)] {
+        self.tags.get(name).map_or(&[], |t| &t[..])
+    }
+
+    /// Sets the tags attached to the benchmark with the supplied name.
+    pub fn set_tags(&mut self, name: impl Into<String>, tags: Tags) {
+        self.tags.insert(name.into(), tags);
+    }
+
+    /// Returns information about the system this baseline was recorded on, if
+    /// known.
+    pub fn system(&self) -> Option<&Info> {
+        self.system.as_ref()
+    }
+
+    /// Sets the information about the system this baseline was recorded on.
+    pub fn set_system(&mut self, system: impl Into<Option<Info>>) {
+        self.system = system.into();
+    }
+
+    /// Sets the estimates and tags for the benchmarks in the supplied baseline
+    /// (replacing any existing estimates and tags for those benchmarks).
+    ///
+    /// The system information of the supplied baseline is used if this
+    /// baseline has none.
+    pub fn merge(&mut self, other: &Baseline) {
+        if self.system.is_none() {
+            self.system = other.system.clone();
+        }
+
+        for (name, estimate) in other.iter() {
+            self.insert(name, estimate);
+            self.set_tags(name, other.tags(name).to_vec());
+        }
+    }
+
+    /// Returns this baseline in the `customSmallerIsBetter` JSON format used by
+    /// the `github-action-benchmark` GitHub Action.
+    ///
+    /// Each benchmark is an entry with the estimated execution time per
+    /// iteration as the value (in nanoseconds), the interval around the
+    /// estimate as the range (if known), and the tags as extra information.
+    pub fn to_github_benchmark(&self) -> String {
+        let entries = self.iter().map(|(name, estimate)| {
+            let range = if estimate.interval.0.is_finite() {
+                format!(r#","range":{}"#, json_string(&format!("± {:.3}", estimate.interval.0)))
+            } else {
+                String::new()
+            };
+            let tags = self.tags(name).iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>();
+            let extra = if tags.is_empty() {
+                String::new()
+            } else {
+                format!(r#","extra":{}"#, json_string(&tags.join("\n")))
+            };
+            format!(
+                r#"{{"name":{},"unit":"ns/iter","value":{}{}{}}}"#,
+                json_string(name), json_number(estimate.estimate.0), range, extra,
+            )
+        });
+        format!("[{}]", entries.collect::<Vec<_>>().join(","))
+    }
+
+    /// Returns the names and estimates of the benchmarks in this baseline.
+    pub fn iter(&self) -> impl Iterator<Item=(&str, Estimate)> {
+        self.estimates.iter().map(|(n, e)| (&n[..], *e))
+    }
+}
+
+/// Starts recording the results of benchmarks (discarding any previously
+/// recorded results).
+pub fn start_recording() {
+    let mut baseline = Baseline::new();
+    baseline.set_system(system::info().clone());
+    *RECORDING.lock().unwrap() = Some(baseline);
+}
+
+/// Stops recording the results of benchmarks and returns the recorded results.
+pub fn stop_recording() -> Option<Baseline> {
+    RECORDING.lock().unwrap().take()
+}
+
+/// Sets the baseline the results of benchmarks are compared to (discarding any
+/// previously observed changes).
+pub fn set_reference(baseline: Option<Baseline>) {
+    *REFERENCE.lock().unwrap() = baseline;
+    CHANGES.lock().unwrap().clear();
+}
+
+/// Returns the names and changes of the benchmarks that have been compared to
+/// the reference baseline and that are slower than the reference baseline by
+/// more than the supplied threshold (e.g., `0.05` for 5%).
+///
+/// Changes that are within the noise of the measurements are never considered
+/// regressions regardless of the threshold (changes for which the noise is
+/// unknown are only compared to the threshold).
+pub fn regressions(threshold: f64) -> Vec<(String, Change)> {
+    CHANGES.lock().unwrap().iter()
+        .filter(|(_, c)| c.noise != Some(true) && c.ratio > 1.0 + threshold)
+        .cloned()
+        .collect()
+}
+
+/// Records the supplied estimate (if recording) and returns the estimate for
+/// the benchmark in the reference baseline (if any).
+pub(crate) fn observe(name: &str, estimate: Estimate) -> Option<Estimate> {
+    if let Some(recording) = &mut *RECORDING.lock().unwrap() {
+        recording.insert(name, estimate);
+        recording.set_tags(name, metadata::current());
+    }
+
+    let reference = REFERENCE.lock().unwrap().as_ref().and_then(|b| b.get(name));
+    if let Some(reference) = reference {
+        CHANGES.lock().unwrap().push((name.into(), estimate.change(&reference)));
+    }
+    reference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    fn estimate(estimate: f64, interval: f64, error: f64) -> Estimate {
+        Estimate {
+            estimate: Nanoseconds(estimate),
+            interval: Nanoseconds(interval),
+            confidence: 0.95,
+            error: Nanoseconds(error),
+            dof: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = env::temp_dir().join(format!("microbench-baseline-{}.json", process::id()));
+
+        let mut baseline = Baseline::new();
+        baseline.insert("a", estimate(100.0, 5.0, 2.0));
+        baseline.insert("b \"quoted\"", estimate(2.5, 0.125, 0.0625));
+        baseline.set_tags("a", vec![("size".into(), "16".into())]);
+        baseline.set_tags("b \"quoted\"", vec![]);
+        baseline.set_system(system::info().clone());
+        baseline.save(&path).unwrap();
+        let loaded = Baseline::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, baseline);
+        assert_eq!(loaded.tags("a"), &[("size".into(), "16".into())]);
+        assert_eq!(loaded.tags("b \"quoted\""), &[]);
+        assert_eq!(loaded.system(), Some(system::info()));
+    }
+
+    #[test]
+    fn test_load_legacy() {
+        let path = env::temp_dir().join(format!("microbench-baseline-legacy-{}.json", process::id()));
+
+        fs::write(&path, "{\"name\":\"a\",\"estimate\":100.0,\"interval\":5.0}\n\n").unwrap();
+        let loaded = Baseline::load(&path);
+        fs::write(&path, "{\"name\":\"a\",\"estimate\":100.0}\n").unwrap();
+        let invalid = Baseline::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        let estimate = loaded.get("a").unwrap();
+        assert_eq!(estimate.estimate, Nanoseconds(100.0));
+        assert_eq!(estimate.interval, Nanoseconds(5.0));
+        assert!(estimate.confidence.is_nan());
+        assert!(estimate.error.0.is_nan());
+        assert!(estimate.dof.is_nan());
+        assert_eq!(loaded.tags("a"), &[]);
+        assert_eq!(loaded.system(), None);
+
+        assert_eq!(invalid.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut baseline = Baseline::new();
+        baseline.insert("a", estimate(100.0, 5.0, 2.0));
+        baseline.insert("b", estimate(200.0, 5.0, 2.0));
+        baseline.set_tags("b", vec![("size".into(), "16".into())]);
+
+        let mut other = Baseline::new();
+        other.insert("b", estimate(300.0, 5.0, 2.0));
+        other.insert("c", estimate(400.0, 5.0, 2.0));
+        other.set_tags("c", vec![("size".into(), "32".into())]);
+        other.set_system(system::info().clone());
+
+        baseline.merge(&other);
+        let estimates = baseline.iter().map(|(n, e)| (n, e.estimate.0)).collect::<Vec<_>>();
+        assert_eq!(estimates, [("a", 100.0), ("b", 300.0), ("c", 400.0)]);
+        assert_eq!(baseline.tags("b"), &[]);
+        assert_eq!(baseline.tags("c"), &[("size".into(), "32".into())]);
+        assert_eq!(baseline.system(), Some(system::info()));
+
+        let mut info = system::info().clone();
+        info.os = "other".into();
+        let mut merged = Baseline::new();
+        merged.set_system(info.clone());
+        merged.merge(&other);
+        assert_eq!(merged.system(), Some(&info));
+    }
+
+    #[test]
+    fn test_change() {
+        let baseline = estimate(100.0, 5.0, 2.0);
+
+        let change = estimate(110.0, 4.0, 2.0).change(&baseline);
+        assert!((change.ratio - 1.1).abs() < 1e-12);
+        assert_eq!(change.noise, Some(false));
+        assert!(change.p < 0.01);
+        assert!((change.relative.location - 0.1).abs() < 1e-12);
+        assert_eq!(change.verdict(0.95), Verdict::Slower);
+
+        let change = estimate(95.0, 5.0, 2.0).change(&baseline);
+        assert_eq!(change.noise, Some(true));
+        assert!(change.p > 0.05);
+        assert_eq!(change.verdict(0.95), Verdict::NoDifference);
+
+        let change = estimate(90.0, f64::NAN, f64::NAN).change(&baseline);
+        assert!((change.ratio - 0.9).abs() < 1e-12);
+        assert_eq!(change.noise, None);
+        assert!(change.p.is_nan());
+        assert!(change.interval(0.95).0.is_nan());
+        assert_eq!(change.verdict(0.95), Verdict::NoDifference);
+    }
+
+    #[test]
+    fn test_regressions() {
+        let mut reference = Baseline::new();
+        for name in &["noise", "slower", "faster", "unknown"] {
+            reference.insert(format!("regressions-{}", name), estimate(100.0, 5.0, 2.0));
+        }
+        reference.insert("regressions-small", estimate(100.0, 1.0, 0.5));
+        set_reference(Some(reference));
+
+        assert!(observe("regressions-noise", estimate(108.0, 5.0, 2.0)).is_some());
+        assert!(observe("regressions-slower", estimate(120.0, 5.0, 2.0)).is_some());
+        assert!(observe("regressions-faster", estimate(80.0, 5.0, 2.0)).is_some());
+        assert!(observe("regressions-unknown", estimate(110.0, f64::NAN, f64::NAN)).is_some());
+        assert!(observe("regressions-small", estimate(103.0, 1.0, 0.5)).is_some());
+        assert!(observe("regressions-missing", estimate(200.0, 5.0, 2.0)).is_none());
+
+        let names = |threshold| regressions(threshold).into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        assert_eq!(names(0.0), ["regressions-slower", "regressions-unknown", "regressions-small"]);
+        assert_eq!(names(0.05), ["regressions-slower", "regressions-unknown"]);
+        assert_eq!(names(0.15), ["regressions-slower"]);
+        assert_eq!(names(0.25), Vec::<String>::new());
+
+        set_reference(None);
+        assert!(regressions(0.0).is_empty());
+    }
+}
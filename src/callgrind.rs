@@ -0,0 +1,248 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Instruction counts using Callgrind.
+//!
+//! This module is only available when the `callgrind` crate feature is enabled
+//! and requires Valgrind to be installed. Instead of measuring execution time,
+//! a single iteration of a benchmark is executed under Callgrind (with cache
+//! simulation) and the instructions executed and memory accesses made by that
+//! iteration are counted. These counts are deterministic which makes them
+//! useful for detecting changes in noisy environments (e.g., CI) even though
+//! they are only loosely related to execution time.
+//!
+//! The counts are collected by executing the current benchmark binary again
+//! under Callgrind (with the same arguments). In that process, benchmarks run
+//! before the benchmark being measured are dry runs (see
+//! [`Options::dry_run`](../struct.Options.html#method.dry_run)) and the process
+//! exits as soon as the benchmark has been measured. The dry runs are requested
+//! with the `MICROBENCH_DRY_RUN` environment variable (see
+//! [`Options`](../struct.Options.html)).
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process::{self, Command, Stdio};
+
+use crate::{Format, Options, dry_run_impl, label, note, retain_ref};
+use crate::utility::{json_string};
+
+/// The environment variable that contains the name of the benchmark to be
+/// measured by a process executed under Callgrind.
+const BENCHMARK: &str = "MICROBENCH_CALLGRIND";
+
+/// The event counts for an iteration of a benchmark.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Events {
+    /// The number of instructions executed (`Ir`).
+    pub instructions: u64,
+    /// The number of memory reads (`Dr`).
+    pub reads: u64,
+    /// The number of memory writes (`Dw`).
+    pub writes: u64,
+    /// The number of L1 instruction cache misses (`I1mr`).
+    pub l1_instruction_misses: u64,
+    /// The number of L1 data cache read misses (`D1mr`).
+    pub l1_read_misses: u64,
+    /// The number of L1 data cache write misses (`D1mw`).
+    pub l1_write_misses: u64,
+    /// The number of last-level instruction cache misses (`ILmr`).
+    pub ll_instruction_misses: u64,
+    /// The number of last-level data cache read misses (`DLmr`).
+    pub ll_read_misses: u64,
+    /// The number of last-level data cache write misses (`DLmw`).
+    pub ll_write_misses: u64,
+}
+
+impl Events {
+    /// Returns the number of accesses (instruction fetches, reads, and writes)
+    /// that hit the L1 caches.
+    pub fn l1_hits(&self) -> u64 {
+        let accesses = self.instructions + self.reads + self.writes;
+        accesses.saturating_sub(self.l1_misses())
+    }
+
+    /// Returns the number of accesses that missed the L1 caches but hit the
+    /// last-level cache.
+    pub fn ll_hits(&self) -> u64 {
+        self.l1_misses().saturating_sub(self.ram_hits())
+    }
+
+    /// Returns the number of accesses that missed every cache.
+    pub fn ram_hits(&self) -> u64 {
+        self.ll_instruction_misses + self.ll_read_misses + self.ll_write_misses
+    }
+
+    /// Returns an estimate of the number of cycles taken by the accesses
+    /// (`L1 hits + 5 × LL hits + 35 × RAM hits`).
+    pub fn estimated_cycles(&self) -> u64 {
+        self.l1_hits() + (5 * self.ll_hits()) + (35 * self.ram_hits())
+    }
+
+    /// Returns the number of accesses that missed the L1 caches.
+    fn l1_misses(&self) -> u64 {
+        self.l1_instruction_misses + self.l1_read_misses + self.l1_write_misses
+    }
+
+    /// Parses the supplied Callgrind output file contents.
+    fn parse(output: &str) -> Option<Self> {
+        let mut names = None;
+        let mut totals = None;
+        for line in output.lines() {
+            if let Some(events) = line.strip_prefix("events:") {
+                names = Some(events.split_whitespace().collect::<Vec<_>>());
+            } else if let Some(values) = line.strip_prefix("totals:").or(line.strip_prefix("summary:")) {
+                totals = Some(values.split_whitespace().map(str::parse).collect::<Result<Vec<u64>, _>>().ok()?);
+            }
+        }
+
+        let (names, totals) = (names?, totals?);
+        let event = |name| names.iter().position(|n| *n == name).and_then(|i| totals.get(i).cloned());
+        Some(Events {
+            instructions: event("Ir")?,
+            reads: event("Dr").unwrap_or(0),
+            writes: event("Dw").unwrap_or(0),
+            l1_instruction_misses: event("I1mr").unwrap_or(0),
+            l1_read_misses: event("D1mr").unwrap_or(0),
+            l1_write_misses: event("D1mw").unwrap_or(0),
+            ll_instruction_misses: event("ILmr").unwrap_or(0),
+            ll_read_misses: event("DLmr").unwrap_or(0),
+            ll_write_misses: event("DLmw").unwrap_or(0),
+        })
+    }
+}
+
+/// Benchmarks the supplied function using Callgrind and prints the results.
+///
+/// See [`measure_instructions`](fn.measure_instructions.html) for more
+/// information.
+pub fn bench_instructions<T>(options: &Options, name: &str, f: impl FnOnce() -> T) {
+    let events = match measure_instructions(options, name, f) {
+        Ok(Some(events)) => events,
+        Ok(None) if options.dry_run => return dry_run_impl(options, name),
+        Ok(None) => return,
+        Err(error) => return note(options, format_args!(
+            "{} ... warning: failed to run Callgrind ({})", label(options, name), error,
+        )),
+    };
+
+    if options.format == Format::Json {
+        println!(
+            concat!(
+                r#"{{"name":{},"instructions":{},"l1_hits":{},"ll_hits":{},"ram_hits":{},"#,
+                r#""estimated_cycles":{}}}"#,
+            ),
+            json_string(name),
+            events.instructions,
+            events.l1_hits(),
+            events.ll_hits(),
+            events.ram_hits(),
+            events.estimated_cycles(),
+        );
+    } else {
+        println!(
+            "{:<2$} {:>15} instructions",
+            format!("{} ...", label(options, name)), events.instructions, options.width,
+        );
+        println!(
+            "    L1 hits: {}, LL hits: {}, RAM hits: {}, estimated cycles: {}",
+            events.l1_hits(),
+            events.ll_hits(),
+            events.ram_hits(),
+            events.estimated_cycles(),
+        );
+    }
+}
+
+/// Measures the instructions executed and memory accesses made by a single
+/// execution of the supplied function using Callgrind.
+///
+/// The current benchmark binary is executed again under Callgrind to measure
+/// the function (see the [module documentation](index.html)). Returns `None`
+/// when called in a process executed under Callgrind for a different benchmark
+/// (in which case the function is not executed) or for a dry run.
+pub fn measure_instructions<T>(
+    options: &Options, name: &str, f: impl FnOnce() -> T
+) -> io::Result<Option<Events>> {
+    match env::var(BENCHMARK) {
+        Ok(benchmark) if benchmark == name => {
+            iteration(f);
+            process::exit(0);
+        },
+        Ok(_) => return Ok(None),
+        Err(_) if options.dry_run => {
+            retain_ref(&f());
+            return Ok(None);
+        },
+        Err(_) => { },
+    }
+
+    let output = env::temp_dir().join(format!("microbench-callgrind-{}.out", process::id()));
+    let status = Command::new("valgrind")
+        .arg("--tool=callgrind")
+        .arg("--cache-sim=yes")
+        .arg("--collect-atstart=no")
+        .arg("--toggle-collect=*microbench::callgrind::iteration*")
+        .arg(format!("--callgrind-out-file={}", output.display()))
+        .arg(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(BENCHMARK, name)
+        .env("MICROBENCH_DRY_RUN", "1")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "`valgrind` not found"),
+            _ => e,
+        })?;
+
+    let result = fs::read_to_string(&output);
+    let _ = fs::remove_file(&output);
+    if !status.success() {
+        let message = format!("Callgrind failed ({})", status);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid Callgrind output");
+    Events::parse(&result?).map(Some).ok_or_else(invalid)
+}
+
+/// Executes the supplied function (the only code counted by Callgrind).
+#[inline(never)]
+fn iteration<T>(f: impl FnOnce() -> T) {
+    retain_ref(&f());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "\
+            version: 1\n\
+            events: Ir Dr Dw I1mr D1mr D1mw ILmr DLmr DLmw\n\
+            fn=(1) microbench::callgrind::iteration\n\
+            0 100 30 20 2 3 1 1 1 0\n\
+            totals: 100 30 20 2 3 1 1 1 0\n";
+        let events = Events::parse(output).unwrap();
+        assert_eq!(events.instructions, 100);
+        assert_eq!(events.l1_hits(), 144);
+        assert_eq!(events.ll_hits(), 4);
+        assert_eq!(events.ram_hits(), 2);
+        assert_eq!(events.estimated_cycles(), 144 + 20 + 70);
+
+        assert_eq!(Events::parse("events: Ir\n"), None);
+    }
+}
@@ -0,0 +1,639 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command-line interface.
+//!
+//! The command-line interface is compatible with `cargo bench` so benchmarks
+//! can be defined in the `benches` directory of a package (with `harness =
+//! false` set for each benchmark target in `Cargo.toml`) and run with `cargo
+//! bench -- <filter>`. The flags `cargo bench` passes to benchmark binaries
+//! (e.g., `--bench`) are accepted and the `--exact` flag is supported but the
+//! other flags supported by the standard benchmark harness are ignored. The
+//! [`bench_main`](../macro.bench_main.html) macro defines a `main` function
+//! that runs groups of benchmarks with this command-line interface.
+//!
+//! ```toml
+//! [[bench]]
+//! name = "fibonacci"
+//! harness = false
+//! ```
+//!
+//! ```no_run
+//! use microbench::{Options, bench_group, retain};
+//!
+//! bench_group!(sum, Options::default();
+//!     "iter" => || (0..retain(1024u64)).sum::<u64>(),
+//!     "fold" => || (0..retain(1024u64)).fold(0, |a, b| a + b),
+//! );
+//!
+//! microbench::bench_main!(sum);
+//! ```
+
+use std::cell::{Cell};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::panic;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::{Duration};
+
+use crate::{Format, Options, Teardown, suppress_environment_warnings, warn_environment};
+use crate::baseline::{self, Baseline};
+use crate::config;
+use crate::criterion;
+use crate::group::{Group};
+use crate::history::{History};
+use crate::metadata;
+use crate::system;
+use crate::time;
+use crate::utility;
+
+/// The environment variable that identifies a worker process (`<index>/<count>`).
+const WORKER: &str = "MICROBENCH_WORKER";
+/// The environment variable that contains the name of the baseline a worker
+/// process saves its results as.
+const WORKER_BASELINE: &str = "MICROBENCH_WORKER_BASELINE";
+
+/// The usage information printed by `--help`.
+const USAGE: &str = "\
+Usage: <binary> [OPTIONS] [FILTER]
+
+Runs the benchmarks whose names (e.g., `group/name`) contain FILTER (or match
+FILTER if it is a glob pattern containing `*` or `?`).
+
+The filter and exclusions can also be set with the `MICROBENCH_FILTER` and
+`MICROBENCH_EXCLUDE` (comma-separated) environment variables.
+
+The filter, exclusions, and other options can also be set in a `microbench.toml`
+configuration file in the package directory (or any of its ancestors).
+
+Durations are in seconds unless they have a unit (e.g., `500ms` or `2m`).
+
+Options:
+    --filter <FILTER>    Only run the benchmarks whose names contain FILTER
+    --exclude <FILTER>   Skips the benchmarks whose names contain FILTER (repeatable)
+    --time <DURATION>    Sets the maximum amount of time each benchmark will run for
+    --warmup <DURATION>  Sets the amount of time each benchmark will warm up for
+    --factor <FACTOR>    Sets the geometric growth factor for sample iterations (> 1.0)
+    --format <FORMAT>    Sets the format of the results (`pretty` or `json`)
+    --compact            Prints numbers compactly with SI suffixes (e.g., `9.41k`)
+    --save-baseline <NAME>
+                         Saves the results as a baseline with the supplied name
+    --baseline <NAME>    Compares the results to the baseline with the supplied name
+    --threshold <PERCENT>
+                         Fails if any benchmark is slower than the baseline by more than PERCENT
+    --history            Appends the results to the history file
+    --criterion          Also saves the results in the Criterion.rs layout (e.g., for `critcmp`)
+    --github-benchmark <PATH>
+                         Saves the results for the `github-action-benchmark` GitHub Action
+    --tag <KEY=VALUE>    Attaches a metadata tag to the results (repeatable)
+    --dry-run, --test    Runs each benchmark once without measuring it
+    --profile <DURATION> Runs each benchmark for DURATION without measuring it (for profilers)
+    --list               Lists the benchmarks instead of running them
+    --jobs <N>           Distributes the benchmarks across N worker processes
+    --exact              Only run the benchmarks whose names are exactly FILTER
+    -h, --help           Prints this usage information";
+
+/// Command-line arguments that control which benchmarks are run and how.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Arguments {
+    /// Only run the benchmarks whose names contain this string (or match this
+    /// glob pattern).
+    pub filter: Option<String>,
+    /// Skip the benchmarks whose names contain any of these strings (or match
+    /// any of these glob patterns).
+    pub exclude: Vec<String>,
+    /// Whether to append the results to the history file.
+    pub history: bool,
+    /// Whether to also save the results in the Criterion.rs layout (as the
+    /// baseline with the name in `save_baseline` or `new`).
+    pub criterion: bool,
+    /// The file to save the results to in the `customSmallerIsBetter` format
+    /// used by the `github-action-benchmark` GitHub Action.
+    pub github_benchmark: Option<String>,
+    /// The metadata tags to attach to the results.
+    pub tags: Vec<(String, String)>,
+    /// Whether the filter must match the names of benchmarks exactly.
+    pub exact: bool,
+    /// Whether to run each benchmark once without measuring it.
+    pub dry_run: bool,
+    /// The amount of time to run each benchmark for without measuring it.
+    pub profile: Option<Duration>,
+    /// Whether to list the benchmarks instead of running them.
+    pub list: bool,
+    /// The number of worker processes to distribute the benchmarks across.
+    pub jobs: Option<usize>,
+    /// Overrides the maximum amount of time benchmarks will run for.
+    pub time: Option<Duration>,
+    /// Overrides the amount of time benchmarks will warm up for.
+    pub warmup: Option<Duration>,
+    /// Overrides the geometric growth factor for benchmark sample iterations.
+    pub factor: Option<f64>,
+    /// Overrides the format of printed benchmark results.
+    pub format: Option<Format>,
+    /// Whether to print numbers compactly with SI suffixes.
+    pub compact: bool,
+    /// The name of the baseline to save the results as.
+    pub save_baseline: Option<String>,
+    /// The name of the baseline to compare the results to.
+    pub baseline: Option<String>,
+    /// The maximum slowdown relative to the baseline (e.g., `0.05` for 5%)
+    /// before the run fails.
+    pub threshold: Option<f64>,
+    /// Whether usage information was requested.
+    pub help: bool,
+}
+
+impl Arguments {
+    /// Parses the supplied command-line arguments (excluding the name of the
+    /// binary).
+    pub fn parse(arguments: impl IntoIterator<Item=String>) -> Result<Self, String> {
+        let mut parsed = Arguments::default();
+        let mut arguments = arguments.into_iter();
+        while let Some(argument) = arguments.next() {
+            let mut value = |flag: &str| {
+                arguments.next().ok_or_else(|| format!("missing value for `{}`", flag))
+            };
+
+            match &argument[..] {
+                "--filter" => parsed.filter = Some(value("--filter")?),
+                "--exclude" => parsed.exclude.push(value("--exclude")?),
+                "--time" => parsed.time = Some(duration("--time", &value("--time")?)?),
+                "--warmup" => parsed.warmup = Some(duration("--warmup", &value("--warmup")?)?),
+                "--factor" => {
+                    let factor = value("--factor")?;
+                    let invalid = || format!("invalid value for `--factor`: `{}`", factor);
+                    match factor.parse::<f64>() {
+                        Ok(factor) if factor.is_finite() && factor > 1.0 => parsed.factor = Some(factor),
+                        _ => return Err(invalid()),
+                    }
+                },
+                "--format" => {
+                    let format = value("--format")?;
+                    let invalid = || format!("invalid value for `--format`: `{}`", format);
+                    parsed.format = Some(Format::parse(&format).ok_or_else(invalid)?);
+                },
+                "--compact" => parsed.compact = true,
+                "--save-baseline" => parsed.save_baseline = Some(value("--save-baseline")?),
+                "--baseline" => parsed.baseline = Some(value("--baseline")?),
+                "--threshold" => {
+                    let threshold = value("--threshold")?;
+                    let invalid = || format!("invalid value for `--threshold`: `{}`", threshold);
+                    match threshold.trim_end_matches('%').parse::<f64>() {
+                        Ok(percent) if percent >= 0.0 => parsed.threshold = Some(percent / 100.0),
+                        _ => return Err(invalid()),
+                    }
+                },
+                "--history" => parsed.history = true,
+                "--criterion" => parsed.criterion = true,
+                "--github-benchmark" => parsed.github_benchmark = Some(value("--github-benchmark")?),
+                "--tag" => {
+                    let tag = value("--tag")?;
+                    match tag.split_once('=') {
+                        Some((k, v)) if !k.is_empty() => parsed.tags.push((k.into(), v.into())),
+                        _ => return Err(format!("invalid value for `--tag`: `{}`", tag)),
+                    }
+                },
+                "--exact" => parsed.exact = true,
+                "--dry-run" | "--test" => parsed.dry_run = true,
+                "--list" => parsed.list = true,
+                "--profile" => parsed.profile = Some(duration("--profile", &value("--profile")?)?),
+                "--jobs" => {
+                    let jobs = value("--jobs")?;
+                    let invalid = || format!("invalid value for `--jobs`: `{}`", jobs);
+                    match jobs.parse::<usize>() {
+                        Ok(jobs) if jobs != 0 => parsed.jobs = Some(jobs),
+                        _ => return Err(invalid()),
+                    }
+                },
+                "-h" | "--help" => parsed.help = true,
+                // Flags passed by `cargo bench` or supported by the standard
+                // benchmark harness that do not apply to this harness.
+                "--bench" | "--nocapture" | "--show-output" | "-q" | "--quiet" => {},
+                "--color" | "--logfile" => { value(&argument)?; },
+                flag if flag.starts_with('-') => return Err(format!("unknown flag `{}`", flag)),
+                _ => parsed.filter = Some(argument),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the supplied benchmarking options with the overrides in these
+    /// arguments applied.
+    pub fn apply(&self, mut options: Options) -> Options {
+        if let Some(time) = self.time { options = options.time(time); }
+        if let Some(warmup) = self.warmup { options = options.warmup(warmup); }
+        if let Some(factor) = self.factor { options = options.factor(factor); }
+        if let Some(format) = self.format { options = options.format(format); }
+        if self.compact { options = options.compact(true); }
+        if self.dry_run { options = options.dry_run(true); }
+        if let Some(profile) = self.profile { options = options.profile(profile); }
+        options
+    }
+
+    /// Returns whether the benchmark with the supplied name should be run.
+    pub fn matches(&self, name: &str) -> bool {
+        let matches = |filter: &str| {
+            if self.exact {
+                name == filter
+            } else if filter.contains(['*', '?']) {
+                utility::glob(filter, name)
+            } else {
+                name.contains(filter)
+            }
+        };
+
+        let included = self.filter.as_ref().map(|f| matches(f)).unwrap_or(true);
+        included && !self.exclude.iter().any(|e| matches(e))
+    }
+
+    /// Returns these arguments with the filter and exclusions set from the
+    /// `MICROBENCH_FILTER` and `MICROBENCH_EXCLUDE` environment variables or
+    /// the configuration file (see [`Options`](../struct.Options.html)) if the
+    /// filter and exclusions are not already set.
+    pub fn with_environment(mut self) -> Self {
+        if self.filter.is_none() {
+            self.filter = env::var("MICROBENCH_FILTER").ok().filter(|f| !f.is_empty());
+        }
+        if self.exclude.is_empty() {
+            if let Ok(exclude) = env::var("MICROBENCH_EXCLUDE") {
+                let exclude = exclude.split(',').map(str::trim).filter(|e| !e.is_empty());
+                self.exclude = exclude.map(String::from).collect();
+            }
+        }
+
+        let config = config::get();
+        if self.filter.is_none() {
+            self.filter = config.filter.clone();
+        }
+        if self.exclude.is_empty() {
+            self.exclude = config.exclude.clone();
+        }
+        self
+    }
+}
+
+/// Parses a duration.
+fn duration(flag: &str, value: &str) -> Result<Duration, String> {
+    time::parse_duration(value).ok_or_else(|| format!("invalid value for `{}`: `{}`", flag, value))
+}
+
+/// Runs the supplied groups of benchmarks as configured by the command-line
+/// arguments of the current process.
+///
+/// If the command-line arguments are invalid, an error and usage information
+/// are printed and the process exits with exit code `2`. See
+/// [`Arguments`](struct.Arguments.html) for the supported arguments (or run the
+/// benchmark binary with `--help`).
+pub fn run<'a>(groups: impl IntoIterator<Item=Group<'a>>) {
+    run_with_hooks(groups, || { }, || { });
+}
+
+/// Runs the supplied groups of benchmarks as configured by the command-line
+/// arguments of the current process with functions that are run once before
+/// the first benchmark and once after the last benchmark.
+///
+/// This is useful for expensive preparation shared by all of the groups (e.g.,
+/// starting a database server or warming a file cache) which is not included
+/// in the measurements. The teardown function is also run if a benchmark
+/// panics or if the process is interrupted (see
+/// [`handle_interrupts`](../system/fn.handle_interrupts.html)). When the
+/// benchmarks are distributed across worker processes (see `--jobs`), these
+/// functions are run by each worker process instead.
+///
+/// See [`run`](fn.run.html) for more information.
+pub fn run_with_hooks<'a>(
+    groups: impl IntoIterator<Item=Group<'a>>,
+    setup: impl FnOnce(),
+    teardown: impl FnOnce(),
+) {
+    let arguments = match Arguments::parse(env::args().skip(1)) {
+        Ok(arguments) => arguments.with_environment(),
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        },
+    };
+
+    if arguments.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    if arguments.list {
+        return list(&arguments, groups);
+    }
+
+    if arguments.threshold.is_some() && arguments.baseline.is_none() {
+        eprintln!("error: `--threshold` requires `--baseline`\n\n{}", USAGE);
+        process::exit(2);
+    }
+
+    // The parent process checks the environment so that problems with it are
+    // not warned about by every worker process.
+    let worker = Worker::from_environment();
+    if worker.is_some() {
+        suppress_environment_warnings();
+    }
+
+    if let Some(name) = &arguments.baseline {
+        match Baseline::load(Baseline::path(name)) {
+            Ok(reference) => {
+                match reference.system() {
+                    Some(recorded) if recorded != system::info() && worker.is_none() => {
+                        eprintln!("warning: baseline `{}` was recorded on a different system", name);
+                        eprintln!("    baseline: {}", recorded);
+                        eprintln!("    current:  {}\n", system::info());
+                    },
+                    _ => { },
+                }
+                baseline::set_reference(Some(reference));
+            },
+            Err(error) => {
+                eprintln!("error: failed to load baseline `{}` ({})", name, error);
+                process::exit(1);
+            },
+        }
+    }
+
+    for (key, value) in &arguments.tags {
+        metadata::set_run_tag(&key[..], &value[..]);
+    }
+
+    let recording = arguments.save_baseline.is_some()
+        || arguments.history
+        || arguments.criterion
+        || arguments.github_benchmark.is_some();
+    if recording || worker.is_some() {
+        baseline::start_recording();
+    }
+
+    let mut code = 0;
+    let recorded = match arguments.jobs {
+        Some(jobs) if jobs > 1 && worker.is_none() => {
+            warn_environment(&arguments.apply(Options::from_env()));
+            let (recorded, codes) = run_workers(jobs).unwrap_or_else(|error| {
+                eprintln!("error: failed to run worker processes ({})", error);
+                process::exit(1);
+            });
+            code = codes.into_iter().find(|c| *c != 0).unwrap_or(0);
+            recorded
+        },
+        _ => {
+            // Benchmarks are distributed across worker processes by their
+            // position among the benchmarks that match the filter.
+            let index = Cell::new(0);
+            let assigned = |name: &str| {
+                let matches = arguments.matches(name);
+                match (&worker, matches) {
+                    (Some(worker), true) => {
+                        index.set(index.get() + 1);
+                        (index.get() - 1) % worker.count == worker.index
+                    },
+                    _ => matches,
+                }
+            };
+
+            setup();
+            let teardown = Teardown::new(teardown);
+            for mut group in groups {
+                if system::interrupted() {
+                    break;
+                }
+
+                group.run_with(|o| arguments.apply(o.with_overrides()), assigned);
+            }
+            drop(teardown);
+            baseline::stop_recording().unwrap_or_default()
+        },
+    };
+
+    if let Some(worker) = &worker {
+        if let Err(error) = recorded.save(Baseline::path(&worker.baseline)) {
+            eprintln!("error: failed to save worker results ({})", error);
+            process::exit(1);
+        }
+    }
+
+    if arguments.history && worker.is_none() {
+        if let Err(error) = History::new(History::path()).append_baseline(&recorded) {
+            eprintln!("error: failed to append results to history ({})", error);
+            process::exit(1);
+        }
+    }
+
+    if let (Some(name), None) = (&arguments.save_baseline, &worker) {
+        // Merge the results with any existing results so that filtered runs do
+        // not discard the results of the benchmarks that were not run.
+        let path = Baseline::path(name);
+        let mut saved = match Baseline::load(&path) {
+            Ok(saved) => saved,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Baseline::default(),
+            Err(error) => {
+                eprintln!("error: failed to load baseline `{}` ({})", name, error);
+                process::exit(1);
+            },
+        };
+        saved.merge(&recorded);
+
+        if let Err(error) = saved.save(&path) {
+            eprintln!("error: failed to save baseline `{}` ({})", name, error);
+            process::exit(1);
+        }
+    }
+
+    if arguments.criterion && worker.is_none() {
+        let name = arguments.save_baseline.as_deref().unwrap_or("new");
+        if let Err(error) = criterion::save(&recorded, name) {
+            eprintln!("error: failed to save Criterion results ({})", error);
+            process::exit(1);
+        }
+    }
+
+    if let (Some(path), None) = (&arguments.github_benchmark, &worker) {
+        if let Err(error) = fs::write(path, recorded.to_github_benchmark()) {
+            eprintln!("error: failed to save results to `{}` ({})", path, error);
+            process::exit(1);
+        }
+    }
+
+    if let Some(threshold) = arguments.threshold {
+        let regressions = baseline::regressions(threshold);
+        if !regressions.is_empty() {
+            eprintln!(
+                "error: {} benchmark(s) regressed by more than {}%",
+                regressions.len(), threshold * 100.0,
+            );
+            for (name, change) in regressions {
+                eprintln!("    {} ({:+.2}%)", name, (change.ratio - 1.0) * 100.0);
+            }
+            process::exit(1);
+        }
+    }
+
+    if code != 0 {
+        process::exit(code);
+    }
+}
+
+/// Prints the full names of the supplied benchmarks that match the supplied
+/// arguments (one per line).
+///
+/// Each line has the same format as the lines printed by the standard benchmark
+/// harness (i.e., `<name>: benchmark`) followed by the tags attached to the
+/// group of the benchmark (if any) or is a JSON object containing the name,
+/// group, and tags of the benchmark if the format is JSON.
+fn list<'a>(arguments: &Arguments, groups: impl IntoIterator<Item=Group<'a>>) {
+    for group in groups {
+        let options = arguments.apply(group.options().with_overrides());
+        for name in group.names() {
+            let name = format!("{}/{}", group.name(), name);
+            if !arguments.matches(&name) {
+                continue;
+            }
+
+            if options.format == Format::Json {
+                println!(
+                    r#"{{"name":{},"group":{},"tags":{}}}"#,
+                    utility::json_string(&name),
+                    utility::json_string(group.name()),
+                    metadata::to_json(group.tags()),
+                );
+            } else if group.tags().is_empty() {
+                println!("{}: benchmark", name);
+            } else {
+                let tags = group.tags().iter().map(|(k, v)| format!("{}={}", k, v));
+                println!("{}: benchmark ({})", name, tags.collect::<Vec<_>>().join(", "));
+            }
+        }
+    }
+}
+
+/// The identity of a worker process.
+#[derive(Clone, Debug)]
+struct Worker {
+    index: usize,
+    count: usize,
+    baseline: String,
+}
+
+impl Worker {
+    /// Returns the identity of the current process if it is a worker process.
+    fn from_environment() -> Option<Self> {
+        let worker = env::var(WORKER).ok()?;
+        let (index, count) = worker.split_once('/')?;
+        let (index, count) = (index.parse().ok()?, count.parse().ok()?);
+        let baseline = env::var(WORKER_BASELINE).ok()?;
+        if index < count { Some(Worker { index, count, baseline }) } else { None }
+    }
+}
+
+/// Runs the benchmarks in the supplied number of worker processes (copies of
+/// the current process with the same command-line arguments) and returns the
+/// merged results and the exit codes of the worker processes.
+///
+/// The output of each worker process is printed once it exits so the output of
+/// the worker processes is not interleaved.
+fn run_workers(jobs: usize) -> io::Result<(Baseline, Vec<i32>)> {
+    let executable = env::current_exe()?;
+    let names = (0..jobs)
+        .map(|i| format!("worker-{}-{}", process::id(), i))
+        .collect::<Vec<_>>();
+
+    let mut children = vec![];
+    for (index, name) in names.iter().enumerate() {
+        let child = Command::new(&executable)
+            .args(env::args_os().skip(1))
+            .env(WORKER, format!("{}/{}", index, jobs))
+            .env(WORKER_BASELINE, name)
+            .stdout(Stdio::piped())
+            .spawn()?;
+        children.push(child);
+    }
+
+    // The outputs are collected on separate threads so that worker processes
+    // do not block on full pipes while waiting for earlier workers to exit.
+    let outputs = children.into_iter()
+        .map(|c| thread::spawn(move || c.wait_with_output()))
+        .collect::<Vec<_>>();
+
+    let mut recorded = Baseline::new();
+    let mut codes = vec![];
+    for (output, name) in outputs.into_iter().zip(&names) {
+        let output = output.join().unwrap_or_else(|p| panic::resume_unwind(p))?;
+        io::stdout().write_all(&output.stdout)?;
+        codes.push(output.status.code().unwrap_or(1));
+
+        let path = Baseline::path(name);
+        if let Ok(baseline) = Baseline::load(&path) {
+            recorded.merge(&baseline);
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    Ok((recorded, codes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Result<Arguments, String> {
+        Arguments::parse(arguments.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let arguments = parse(&["--time", "0.5", "--factor", "1.1", "--format", "json", "sum", "--bench"]);
+        assert_eq!(arguments, Ok(Arguments {
+            filter: Some("sum".into()),
+            time: Some(Duration::from_millis(500)),
+            factor: Some(1.1),
+            format: Some(Format::Json),
+            ..Default::default()
+        }));
+
+        assert!(parse(&["--time"]).is_err());
+        assert!(parse(&["--time", "soon"]).is_err());
+        assert_eq!(parse(&["--warmup", "250ms"]).unwrap().warmup, Some(Duration::from_millis(250)));
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert_eq!(parse(&["--threshold", "5%"]).unwrap().threshold, Some(0.05));
+        assert_eq!(parse(&["--jobs", "4"]).unwrap().jobs, Some(4));
+        assert!(parse(&["--test"]).unwrap().dry_run);
+        assert!(parse(&["--list"]).unwrap().list);
+        assert!(parse(&["--criterion"]).unwrap().criterion);
+        assert!(parse(&["--compact"]).unwrap().compact);
+        assert_eq!(parse(&["--github-benchmark", "out.json"]).unwrap().github_benchmark, Some("out.json".into()));
+        assert!(parse(&["--jobs", "0"]).is_err());
+        assert_eq!(parse(&["--factor", "1.0"]), Err("invalid value for `--factor`: `1.0`".into()));
+        assert!(parse(&["--factor", "0.5"]).is_err());
+        assert!(parse(&["--factor", "NaN"]).is_err());
+        assert!(parse(&["--factor", "inf"]).is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let arguments = parse(&["sort/*_16", "--exclude", "sort/quick*"]).unwrap();
+        assert!(arguments.matches("sort/merge_16"));
+        assert!(!arguments.matches("sort/merge_8"));
+        assert!(!arguments.matches("sort/quick_16"));
+
+        let arguments = parse(&["merge", "--exclude", "16"]).unwrap();
+        assert!(arguments.matches("sort/merge_8"));
+        assert!(!arguments.matches("sort/merge_16"));
+        assert!(!arguments.matches("sort/quick_8"));
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Configuration files (`microbench.toml`).
+//!
+//! Only a subset of TOML is supported: one `key = value` pair per line where
+//! the value is a string, a number, a boolean, or an array of strings (on a
+//! single line). Comments and blank lines are ignored.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock};
+use std::time::{Duration};
+
+use crate::{Bytes, Format, Options};
+use crate::json::{Value};
+use crate::time;
+
+/// The name of configuration files.
+const NAME: &str = "microbench.toml";
+
+/// Benchmark configuration read from a configuration file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub time: Option<Duration>,
+    pub warmup: Option<Duration>,
+    pub factor: Option<f64>,
+    pub memory: Option<Bytes>,
+    pub min_samples: Option<usize>,
+    pub format: Option<Format>,
+    pub filter: Option<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Config {
+    /// Parses the supplied configuration file contents.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |e: String| format!("line {}: {}", index + 1, e);
+            let (key, value) = line.split_once('=').ok_or_else(|| error("expected `=`".into()))?;
+            let key = key.trim().trim_matches('"');
+            let value = parse_value(value.trim()).map_err(error)?;
+            config.set(key, &value).map_err(error)?;
+        }
+        Ok(config)
+    }
+
+    /// Sets the supplied configuration key to the supplied value.
+    fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        let invalid = || format!("invalid value for `{}`", key);
+        let duration = |v: &Value| match v {
+            Value::Number(n) => time::duration_from_secs(*n),
+            Value::String(s) => time::parse_duration(s),
+            _ => None,
+        };
+        let integer = |v: &Value| match v {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as u64),
+            _ => None,
+        };
+
+        match key {
+            "time" => self.time = Some(duration(value).ok_or_else(invalid)?),
+            "warmup" => self.warmup = Some(duration(value).ok_or_else(invalid)?),
+            "factor" => self.factor = Some(value.as_f64().filter(|f| !f.is_nan()).ok_or_else(invalid)?),
+            "memory" => {
+                let memory = match value {
+                    Value::String(s) => s.parse().ok(),
+                    value => integer(value).map(Bytes),
+                };
+                self.memory = Some(memory.ok_or_else(invalid)?);
+            },
+            "min_samples" => self.min_samples = Some(integer(value).ok_or_else(invalid)? as usize),
+            "format" => {
+                let format = value.as_str().and_then(Format::parse);
+                self.format = Some(format.ok_or_else(invalid)?);
+            },
+            "filter" => self.filter = Some(value.as_str().ok_or_else(invalid)?.into()),
+            "exclude" => {
+                let exclude = match value {
+                    Value::Array(values) => values.iter().map(|v| v.as_str().map(String::from)).collect(),
+                    _ => None,
+                };
+                self.exclude = exclude.ok_or_else(invalid)?;
+            },
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+        Ok(())
+    }
+
+    /// Returns the supplied options with the options in this configuration
+    /// applied to the options that are set to their default values (i.e., the
+    /// options set explicitly take precedence over this configuration).
+    pub fn apply(&self, mut options: Options) -> Options {
+        let default = Options::builtin();
+        if let (Some(time), true) = (self.time, options.time == default.time) {
+            options = options.time(time);
+        }
+        if let (Some(warmup), true) = (self.warmup, options.warmup == default.warmup) {
+            options = options.warmup(warmup);
+        }
+        if let (Some(factor), true) = (self.factor, options.factor == default.factor) {
+            options = options.factor(factor);
+        }
+        if let (Some(memory), true) = (self.memory, options.memory == default.memory) {
+            options = options.memory(memory);
+        }
+        if let (Some(min_samples), true) = (self.min_samples, options.min_samples == default.min_samples) {
+            options = options.min_samples(min_samples);
+        }
+        if let (Some(format), true) = (self.format, options.format == default.format) {
+            options = options.format(format);
+        }
+        options
+    }
+}
+
+/// Returns the path of the configuration file for the current process (if
+/// any).
+///
+/// This is the path in the `MICROBENCH_CONFIG` environment variable (if set) or
+/// the first `microbench.toml` found in the package directory (or the current
+/// directory if not run by Cargo) or any of its ancestors.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("MICROBENCH_CONFIG") {
+        return Some(path.into());
+    }
+
+    let directory = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(directory) => directory.into(),
+        None => env::current_dir().ok()?,
+    };
+    Path::new(&directory).ancestors().map(|d| d.join(NAME)).find(|p| p.is_file())
+}
+
+/// Returns the configuration for the current process (which is only read
+/// once).
+///
+/// If the configuration file cannot be read or is invalid, a warning is
+/// printed and the configuration file is ignored.
+pub fn get() -> &'static Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let path = match path() {
+            Some(path) => path,
+            None => return Config::default(),
+        };
+
+        let config = fs::read_to_string(&path).map_err(|e| e.to_string());
+        match config.and_then(|c| Config::parse(&c)) {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("warning: ignoring invalid config `{}` ({})", path.display(), error);
+                Config::default()
+            },
+        }
+    })
+}
+
+/// Returns the supplied line without its trailing comment (if any).
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => { escaped = true; continue; },
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..index],
+            _ => { },
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Parses the supplied value.
+fn parse_value(value: &str) -> Result<Value, String> {
+    // Literal strings (which do not support escapes) are not valid JSON.
+    let literal = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\''));
+    match literal {
+        Some(literal) if !literal.contains('\'') => Ok(Value::String(literal.into())),
+        _ => Value::parse(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(r#"
+            # Shared benchmark configuration.
+            time = "2s"
+            warmup = 0.5 # seconds
+            memory = '1GiB'
+            format = "json"
+            exclude = ["slow/#*", "io/*"]
+        "#);
+        assert_eq!(config, Ok(Config {
+            time: Some(Duration::from_secs(2)),
+            warmup: Some(Duration::from_millis(500)),
+            memory: Some(Bytes::gibibytes(1)),
+            format: Some(Format::Json),
+            exclude: vec!["slow/#*".into(), "io/*".into()],
+            ..Default::default()
+        }));
+
+        assert!(Config::parse("time").is_err());
+        assert!(Config::parse("time = soon").is_err());
+        assert!(Config::parse("time = 1e30").is_err());
+        assert!(Config::parse("time = 1e11").is_err());
+        assert!(Config::parse("time = -1").is_err());
+        assert!(Config::parse("colour = true").is_err());
+        assert!(Config::parse("min_samples = 1.5").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let config = Config::parse("time = 2\nfactor = 1.5\nmin_samples = 10").unwrap();
+
+        let options = config.apply(Options::default());
+        assert_eq!(options.time.0, 2_000_000_000);
+        assert_eq!(options.factor, 1.5);
+        assert_eq!(options.min_samples, 10);
+
+        // Options set explicitly take precedence over the configuration.
+        let options = config.apply(Options::default().time(Duration::from_secs(1)).min_samples(20));
+        assert_eq!(options.time.0, 1_000_000_000);
+        assert_eq!(options.factor, 1.5);
+        assert_eq!(options.min_samples, 20);
+    }
+}
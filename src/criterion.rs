@@ -0,0 +1,199 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Criterion-compatible results.
+//!
+//! Results can be saved in the directory layout used by Criterion.rs
+//! (`<target>/criterion/<benchmark>/<baseline>/`) so that tools which read
+//! that layout (e.g., `critcmp`) can compare the results of runs.
+//!
+//! Only the files needed to compare results are written (`benchmark.json` and
+//! `estimates.json`). Since baselines only contain an estimate and an interval
+//! for each benchmark, every statistic in `estimates.json` is set to the
+//! estimate and the standard deviations and errors are set to zero.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Options};
+use crate::baseline::{Baseline, Estimate};
+use crate::utility::{json_number, json_string};
+
+/// Returns the directory Criterion-compatible results are saved in
+/// (`<target>/criterion`).
+///
+/// The target directory is `CARGO_TARGET_DIR` if set or `target` otherwise.
+pub fn directory() -> PathBuf {
+    let target = env::var_os("CARGO_TARGET_DIR").unwrap_or_else(|| "target".into());
+    Path::new(&target).join("criterion")
+}
+
+/// Returns the directory the results of the benchmark with the supplied name
+/// are saved in for the baseline with the supplied name.
+///
+/// The components of the benchmark name (e.g., `group` and `name` for
+/// `group/name`) are separate directories with characters that are not valid
+/// in file names replaced with underscores (like Criterion.rs).
+pub fn path(benchmark: &str, baseline: &str) -> PathBuf {
+    let mut path = directory();
+    for component in benchmark.split('/') {
+        path.push(sanitize(component));
+    }
+    path.join(sanitize(baseline))
+}
+
+/// Saves the supplied baseline as the Criterion baseline with the supplied
+/// name (e.g., `new`), replacing the existing results for the benchmarks in the
+/// baseline.
+pub fn save(baseline: &Baseline, name: &str) -> io::Result<()> {
+    for (benchmark, estimate) in baseline.iter() {
+        let path = path(benchmark, name);
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("benchmark.json"), benchmark_json(benchmark) + "\n")?;
+        fs::write(path.join("estimates.json"), estimates_json(estimate) + "\n")?;
+    }
+    Ok(())
+}
+
+/// Returns the contents of `benchmark.json` for the benchmark with the supplied
+/// name.
+fn benchmark_json(benchmark: &str) -> String {
+    let (group, function) = match benchmark.split_once('/') {
+        Some((group, function)) => (group, json_string(function)),
+        None => (benchmark, "null".into()),
+    };
+    format!(
+        concat!(
+            r#"{{"group_id":{},"function_id":{},"value_str":null,"throughput":null,"#,
+            r#""full_id":{},"directory_name":{},"title":{}}}"#,
+        ),
+        json_string(group),
+        function,
+        json_string(benchmark),
+        json_string(&directory_name(benchmark)),
+        json_string(benchmark),
+    )
+}
+
+/// Returns the contents of `estimates.json` for the supplied estimate.
+///
+/// If the confidence level of the interval is unknown (e.g., for baselines
+/// saved before confidence levels were recorded), the interval is assumed to
+/// have the default confidence level.
+fn estimates_json(estimate: Estimate) -> String {
+    let confidence = match estimate.confidence {
+        confidence if confidence.is_finite() => confidence,
+        _ => Options::default().confidence,
+    };
+    let interval = if estimate.interval.0.is_finite() { estimate.interval.0 } else { 0.0 };
+    let point = statistic(estimate.estimate.0, interval, confidence);
+    let zero = statistic(0.0, 0.0, confidence);
+    format!(
+        r#"{{"mean":{0},"median":{0},"median_abs_dev":{1},"slope":{0},"std_dev":{1}}}"#,
+        point, zero,
+    )
+}
+
+/// Returns a Criterion statistic with the supplied point estimate and interval
+/// half-width.
+fn statistic(estimate: f64, interval: f64, confidence: f64) -> String {
+    format!(
+        concat!(
+            r#"{{"confidence_interval":{{"confidence_level":{},"lower_bound":{},"#,
+            r#""upper_bound":{}}},"point_estimate":{},"standard_error":0.0}}"#,
+        ),
+        json_number(confidence),
+        json_number(estimate - interval),
+        json_number(estimate + interval),
+        json_number(estimate),
+    )
+}
+
+/// Returns the directory name Criterion.rs uses for the benchmark with the
+/// supplied name.
+fn directory_name(benchmark: &str) -> String {
+    benchmark.split('/').map(sanitize).collect::<Vec<_>>().join("/")
+}
+
+/// Returns the supplied string with the characters that are not valid in file
+/// names replaced with underscores.
+fn sanitize(string: &str) -> String {
+    string.chars().map(|c| if "?\"/\\*<>:|^".contains(c) { '_' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::time::{Nanoseconds};
+
+    #[test]
+    fn test_path() {
+        assert_eq!(sanitize("a?b\"c/d\\e*f<g>h:i|j^k"), "a_b_c_d_e_f_g_h_i_j_k");
+        assert_eq!(sanitize("sum 1024"), "sum 1024");
+        assert_eq!(directory_name("group/a:b"), "group/a_b");
+        assert_eq!(directory_name("name"), "name");
+
+        let path = path("group/a:b", "new?");
+        assert!(path.starts_with(directory()));
+        assert!(path.ends_with(Path::new("group").join("a_b").join("new_")));
+    }
+
+    #[test]
+    fn test_benchmark_json() {
+        assert_eq!(benchmark_json("group/a:b"), concat!(
+            r#"{"group_id":"group","function_id":"a:b","value_str":null,"throughput":null,"#,
+            r#""full_id":"group/a:b","directory_name":"group/a_b","title":"group/a:b"}"#,
+        ));
+        assert_eq!(benchmark_json("name"), concat!(
+            r#"{"group_id":"name","function_id":null,"value_str":null,"throughput":null,"#,
+            r#""full_id":"name","directory_name":"name","title":"name"}"#,
+        ));
+    }
+
+    #[test]
+    fn test_estimates_json() {
+        let estimate = Estimate {
+            estimate: Nanoseconds(10.0),
+            interval: Nanoseconds(0.5),
+            confidence: 0.99,
+            error: Nanoseconds(0.25),
+            dof: 8.0,
+        };
+        let statistic = concat!(
+            r#"{"confidence_interval":{"confidence_level":0.99,"lower_bound":9.5,"upper_bound":10.5},"#,
+            r#""point_estimate":10,"standard_error":0.0}"#,
+        );
+        let zero = concat!(
+            r#"{"confidence_interval":{"confidence_level":0.99,"lower_bound":0,"upper_bound":0},"#,
+            r#""point_estimate":0,"standard_error":0.0}"#,
+        );
+        let expected = format!(
+            r#"{{"mean":{0},"median":{0},"median_abs_dev":{1},"slope":{0},"std_dev":{1}}}"#,
+            statistic, zero,
+        );
+        assert_eq!(estimates_json(estimate), expected);
+
+        // Unknown intervals have a width of zero.
+        let unknown = Estimate { interval: Nanoseconds(f64::NAN), ..estimate };
+        assert!(estimates_json(unknown).contains(r#""lower_bound":10,"upper_bound":10"#));
+
+        // Unknown confidence levels are assumed to be the default.
+        let unknown = Estimate { confidence: f64::NAN, ..estimate };
+        let level = format!(r#""confidence_level":{}"#, Options::default().confidence);
+        assert!(estimates_json(unknown).contains(&level));
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cycle counts using the Cortex-M DWT cycle counter.
+//!
+//! This module is only available on ARM targets when the `dwt` crate feature is
+//! enabled. The cycle counter (`CYCCNT`) of the data watchpoint and trace (DWT)
+//! unit is available on most Cortex-M3, Cortex-M4, and Cortex-M7 cores (but
+//! not on Cortex-M0 or Cortex-M0+ cores). Combined with
+//! [`measure_with_clock`](../fn.measure_with_clock.html), this allows
+//! benchmarks to run on microcontrollers without the standard library.
+//!
+//! ```no_run
+//! use microbench::{Sample, measure_with_clock};
+//! use microbench::dwt::{Dwt};
+//! use core::time::{Duration};
+//!
+//! // The core clock frequency (e.g., 168 MHz).
+//! let mut dwt = unsafe { Dwt::enable(168_000_000) };
+//! let mut buffer = [Sample::default(); 64];
+//! let time = Duration::from_millis(500);
+//! let samples = measure_with_clock(|| dwt.now(), time, 1.5, &mut buffer, || (0..100u64).sum::<u64>());
+//! let cycles = dwt.cycles_per_iteration(samples);
+//! ```
+
+use std::ptr;
+
+use crate::{Sample};
+use crate::statistics::{OnlineModel};
+use crate::time::{Nanoseconds};
+
+/// The debug exception and monitor control register (`DEMCR`).
+const DEMCR: *mut u32 = 0xE000_EDFC as *mut u32;
+/// The trace enable bit of `DEMCR` (`TRCENA`).
+const DEMCR_TRCENA: u32 = 1 << 24;
+/// The DWT control register (`DWT_CTRL`).
+const DWT_CTRL: *mut u32 = 0xE000_1000 as *mut u32;
+/// The cycle counter enable bit of `DWT_CTRL` (`CYCCNTENA`).
+const DWT_CTRL_CYCCNTENA: u32 = 1;
+/// The DWT cycle counter register (`DWT_CYCCNT`).
+const DWT_CYCCNT: *mut u32 = 0xE000_1004 as *mut u32;
+/// The DWT lock access register (`DWT_LAR`, only present on some cores).
+const DWT_LAR: *mut u32 = 0xE000_1FB0 as *mut u32;
+/// The value that unlocks the DWT registers when written to `DWT_LAR`.
+const DWT_LAR_KEY: u32 = 0xC5AC_CE55;
+
+/// The DWT cycle counter.
+///
+/// The 32-bit cycle counter wraps around frequently (e.g., every 25 seconds at
+/// 168 MHz) so readings are extended to 64 bits which requires the counter to
+/// be read at least once per wrap around.
+#[derive(Copy, Clone, Debug)]
+pub struct Dwt {
+    frequency: u32,
+    last: u32,
+    high: u64,
+}
+
+impl Dwt {
+    /// Enables and resets the cycle counter of a core with the supplied clock
+    /// frequency (in Hz).
+    ///
+    /// # Safety
+    ///
+    /// The core must have a DWT unit with a cycle counter and nothing else
+    /// (e.g., a debugger) may be using the cycle counter.
+    pub unsafe fn enable(frequency: u32) -> Self {
+        ptr::write_volatile(DEMCR, ptr::read_volatile(DEMCR) | DEMCR_TRCENA);
+        ptr::write_volatile(DWT_LAR, DWT_LAR_KEY);
+        ptr::write_volatile(DWT_CYCCNT, 0);
+        ptr::write_volatile(DWT_CTRL, ptr::read_volatile(DWT_CTRL) | DWT_CTRL_CYCCNTENA);
+        Dwt { frequency, last: 0, high: 0 }
+    }
+
+    /// Returns the number of cycles that have elapsed since the cycle counter
+    /// was enabled.
+    pub fn cycles(&mut self) -> u64 {
+        let cycles = unsafe { ptr::read_volatile(DWT_CYCCNT) };
+        if cycles < self.last {
+            self.high += 1 << 32;
+        }
+        self.last = cycles;
+        self.high + u64::from(cycles)
+    }
+
+    /// Returns the number of nanoseconds that have elapsed since the cycle
+    /// counter was enabled (based on the clock frequency).
+    pub fn now(&mut self) -> Nanoseconds<u64> {
+        let cycles = self.cycles();
+        Nanoseconds(((cycles as u128 * 1_000_000_000) / u128::from(self.frequency)) as u64)
+    }
+
+    /// Returns the estimated number of cycles per iteration for the supplied
+    /// samples (which were measured with this cycle counter).
+    pub fn cycles_per_iteration(&self, samples: &[Sample]) -> f64 {
+        let model = samples.iter()
+            .map(|s| (s.iterations as f64, s.elapsed.0 as f64))
+            .collect::<OnlineModel>()
+            .model();
+        model.beta * (f64::from(self.frequency) / 1e9)
+    }
+}
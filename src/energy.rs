@@ -0,0 +1,150 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Energy measurement using Intel RAPL (Linux only).
+//!
+//! This module is only available on Linux when the `rapl` crate feature is
+//! enabled. The energy counters are read from the `powercap` sysfs interface
+//! (`/sys/class/powercap/intel-rapl:*`) which may require elevated privileges.
+//! The counters are typically updated approximately every millisecond so the
+//! energy consumption of short samples is not measured precisely (which is
+//! mitigated by regressing the measurements against the number of iterations).
+
+use std::fs;
+use std::io;
+use std::ops::{ControlFlow};
+use std::path::{PathBuf};
+
+use crate::{Options, Sample, bench_impl, format_quantity, note, retain_ref, sample_impl};
+use crate::statistics::{Model};
+use crate::time::{Stopwatch, TimeSource};
+
+/// The directory containing the RAPL domains.
+const POWERCAP: &str = "/sys/class/powercap";
+
+/// A RAPL energy counter for a package.
+#[derive(Clone, Debug)]
+struct Domain {
+    energy: PathBuf,
+    range: u64,
+}
+
+impl Domain {
+    /// Returns the counters for all of the packages on this machine.
+    fn packages() -> io::Result<Vec<Domain>> {
+        let mut domains = vec![];
+        for entry in fs::read_dir(POWERCAP)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with("intel-rapl:") && name.matches(':').count() == 1 {
+                let range = fs::read_to_string(path.join("max_energy_range_uj"))?;
+                let range = range.trim().parse().map_err(|_| io::ErrorKind::InvalidData)?;
+                domains.push(Domain { energy: path.join("energy_uj"), range });
+            }
+        }
+
+        if domains.is_empty() {
+            Err(io::Error::new(io::ErrorKind::NotFound, "no RAPL domains found"))
+        } else {
+            Ok(domains)
+        }
+    }
+
+    /// Returns the current value of this counter in microjoules.
+    fn read(&self) -> io::Result<u64> {
+        let energy = fs::read_to_string(&self.energy)?;
+        Ok(energy.trim().parse().map_err(|_| io::ErrorKind::InvalidData)?)
+    }
+
+    /// Returns the microjoules consumed between the supplied counter values.
+    fn delta(&self, start: u64, end: u64) -> u64 {
+        if end >= start { end - start } else { (self.range - start) + end }
+    }
+}
+
+/// Benchmarks the supplied function and prints the results (including the
+/// energy consumed per iteration).
+///
+/// See [`measure_energy`](fn.measure_energy.html) for more information.
+pub fn bench_energy<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
+    let mut result = Ok(vec![]);
+    bench_impl(options, name, || match measure_energy_impl(options, f) {
+        Ok((samples, retries)) => {
+            let (samples, energy): (Vec<Sample>, Vec<f64>) = samples.into_iter().unzip();
+            result = Ok(samples.iter().map(|s| s.iterations).zip(energy).collect());
+            (samples, retries)
+        },
+        Err(error) => {
+            result = Err(error);
+            (vec![], 0)
+        },
+    });
+
+    match result {
+        Ok(energy) if energy.len() >= 2 => {
+            let model = energy.iter().map(|e| (e.0 as f64, e.1)).collect::<Model>();
+            let energy = format_quantity(options, model.beta.max(0.0) * 1e9);
+            note(options, format_args!("    {} nJ/iter", energy));
+        },
+        Ok(_) => { },
+        Err(error) => note(options, format_args!(
+            "    warning: failed to read RAPL energy counters ({})", error,
+        )),
+    }
+}
+
+/// Measures the execution time and energy consumption (in joules) of the
+/// supplied function.
+///
+/// The energy consumed by all of the packages (i.e., sockets) on this machine
+/// is measured for each sample so that it can be regressed against the number
+/// of iterations just like execution time. It should be noted that this
+/// includes the energy consumed by other processes running on this machine.
+pub fn measure_energy<T>(
+    options: &Options, f: impl FnMut() -> T
+) -> io::Result<Vec<(Sample, f64)>> {
+    measure_energy_impl(options, f).map(|(s, _)| s)
+}
+
+/// Measures the execution time and energy consumption of the supplied
+/// function and also returns the number of retries.
+fn measure_energy_impl<T>(
+    options: &Options, mut f: impl FnMut() -> T
+) -> io::Result<(Vec<(Sample, f64)>, u32)> {
+    let domains = Domain::packages()?;
+    let read = || domains.iter().map(|d| d.read()).collect::<io::Result<Vec<_>>>();
+    read()?;
+
+    let mut error = None;
+    let (samples, retries) = sample_impl(options, options.clock.resolution(), |iterations| {
+        let mut sample = || {
+            let start = read()?;
+            let stopwatch = Stopwatch::new(options.clock);
+            for _ in 0..iterations { retain_ref(&f()); }
+            let elapsed = stopwatch.elapsed();
+            let end = read()?;
+            let microjoules = domains.iter().enumerate()
+                .map(|(i, d)| d.delta(start[i], end[i]))
+                .sum::<u64>();
+            Ok((elapsed, microjoules as f64 / 1e6))
+        };
+
+        sample().map_err(|e| error = Some(e)).ok()
+    }, |_, _| ControlFlow::Continue(()));
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok((samples, retries)),
+    }
+}
@@ -0,0 +1,415 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Groups of benchmarks.
+
+use std::cmp;
+use std::fmt;
+
+use crate::{Format, Options, SkipReason, Teardown, bench, skip};
+use crate::metadata::{self, Tags};
+use crate::system;
+
+/// A function that benchmarks something and prints the results.
+type Function<'a> = Box<dyn FnMut(&Options, &str) + 'a>;
+/// A function that returns the reason a benchmark should be skipped (if any).
+type Condition<'a> = Box<dyn FnMut() -> Option<SkipReason> + 'a>;
+/// A function that overrides some of the benchmarking options for a benchmark.
+type Configure<'a> = Box<dyn Fn(Options) -> Options + 'a>;
+/// A function that is run before or after the benchmarks in a group.
+type Hook<'a> = Box<dyn FnMut() + 'a>;
+
+/// A benchmark in a group.
+struct Entry<'a> {
+    name: String,
+    function: Function<'a>,
+    condition: Option<Condition<'a>>,
+    configure: Option<Configure<'a>>,
+}
+
+impl Entry<'_> {
+    /// Returns the options for this benchmark given the supplied options shared
+    /// by the benchmarks in its group and the supplied further overrides.
+    fn options(&self, shared: Options, layer: impl Fn(Options) -> Options) -> Options {
+        layer(self.configure.as_ref().map_or(shared, |c| c(shared)))
+    }
+}
+
+/// A named group of benchmarks that share a set of benchmarking options.
+///
+/// When run, the group name is printed as a header followed by the results of
+/// the benchmarks in the group (indented and aligned). Groups are usually
+/// defined with the [`bench_group`](../macro.bench_group.html) macro.
+///
+/// ```no_run
+/// use microbench::{Options, retain};
+/// use microbench::group::{Group};
+///
+/// let data = (0..1024u64).collect::<Vec<_>>();
+/// Group::new("sum", Options::default())
+///     .bench("iter", || retain(&data).iter().sum::<u64>())
+///     .bench("fold", || retain(&data).iter().fold(0, |a, b| a + b))
+///     .run();
+/// ```
+pub struct Group<'a> {
+    name: String,
+    options: Options,
+    tags: Tags,
+    entries: Vec<Entry<'a>>,
+    setup: Option<Hook<'a>>,
+    teardown: Option<Hook<'a>>,
+}
+
+impl<'a> Group<'a> {
+    /// Constructs a new empty `Group`.
+    pub fn new(name: impl Into<String>, options: Options) -> Self {
+        Group {
+            name: name.into(),
+            options,
+            tags: vec![],
+            entries: vec![],
+            setup: None,
+            teardown: None,
+        }
+    }
+
+    /// Returns the name of this group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the benchmarking options shared by the benchmarks in this group.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Returns the tags attached to the benchmarks in this group.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Attaches the supplied tag to the benchmarks in this group.
+    ///
+    /// See the [`metadata`](../metadata/index.html) module for more
+    /// information.
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets the function that is run once before the first benchmark in this
+    /// group is run.
+    ///
+    /// This is useful for expensive preparation shared by the benchmarks in this
+    /// group (e.g., starting a database server or warming a file cache) which is
+    /// not included in the measurements. The supplied function is not run if
+    /// none of the benchmarks in this group are run (e.g., because they are all
+    /// excluded by a filter).
+    pub fn setup(mut self, f: impl FnMut() + 'a) -> Self {
+        self.setup = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the function that is run once after the last benchmark in this
+    /// group is run.
+    ///
+    /// The supplied function is also run if a benchmark in this group panics
+    /// or if the process is interrupted (see
+    /// [`handle_interrupts`](../system/fn.handle_interrupts.html)). See
+    /// [`setup`](#method.setup) for more information.
+    pub fn teardown(mut self, f: impl FnMut() + 'a) -> Self {
+        self.teardown = Some(Box::new(f));
+        self
+    }
+
+    /// Returns the names of the benchmarks in this group.
+    pub fn names(&self) -> impl Iterator<Item=&str> {
+        self.entries.iter().map(|e| &e.name[..])
+    }
+
+    /// Returns the names and options of the benchmarks in this group with the
+    /// supplied function applied to the options of each benchmark (i.e., after
+    /// the per-benchmark overrides) to apply any further overrides.
+    pub(crate) fn benchmarks(
+        &self,
+        layer: impl Fn(Options) -> Options,
+    ) -> impl Iterator<Item=(&str, Options)> {
+        let shared = self.options;
+        self.entries.iter().map(move |e| (&e.name[..], e.options(shared, &layer)))
+    }
+
+    /// Adds a benchmark of the supplied function to this group.
+    ///
+    /// See [`bench`](../fn.bench.html) for more information.
+    pub fn bench<T>(self, name: impl Into<String>, mut f: impl FnMut() -> T + 'a) -> Self {
+        self.custom(name, move |options, name| bench(options, name, &mut f))
+    }
+
+    /// Adds a custom benchmark to this group.
+    ///
+    /// The supplied function is passed the benchmarking options and the name of
+    /// the benchmark and should benchmark something and print the results
+    /// (e.g., with [`bench_setup`](../fn.bench_setup.html)).
+    pub fn custom(mut self, name: impl Into<String>, f: impl FnMut(&Options, &str) + 'a) -> Self {
+        let function = Box::new(f);
+        self.entries.push(Entry { name: name.into(), function, condition: None, configure: None });
+        self
+    }
+
+    /// Sets the function that overrides some of the benchmarking options for
+    /// the most recently added benchmark in this group.
+    ///
+    /// The supplied function is passed the benchmarking options shared by the
+    /// benchmarks in this group and should return the benchmarking options for
+    /// the benchmark. When run by the command-line interface, the configuration
+    /// file (see [`Options`](../struct.Options.html)), environment variables,
+    /// and command-line arguments are then applied to the returned options. The
+    /// benchmark is skipped if the resulting options are invalid (see
+    /// [`Options::build`](../struct.Options.html#method.build)).
+    ///
+    /// ```no_run
+    /// # use std::time::{Duration};
+    /// use microbench::{Options, retain};
+    /// use microbench::group::{Group};
+    ///
+    /// Group::new("sort", Options::default())
+    ///     .bench("small", || retain(vec![3, 1, 2]).sort())
+    ///     .bench("large", || retain((0..1_000_000).rev().collect::<Vec<_>>()).sort())
+    ///     .configure(|o| o.time(Duration::from_secs(10)).factor(1.5))
+    ///     .run();
+    /// ```
+    ///
+    /// Panics if this group does not contain any benchmarks.
+    pub fn configure(mut self, f: impl Fn(Options) -> Options + 'a) -> Self {
+        let entry = self.entries.last_mut().expect("group does not contain any benchmarks");
+        entry.configure = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the function that determines whether the most recently added
+    /// benchmark in this group is skipped.
+    ///
+    /// The supplied function is called when the benchmark would be run and
+    /// should return the reason the benchmark should be skipped (if any). See
+    /// [`skip`](../fn.skip.html) for more information.
+    ///
+    /// ```no_run
+    /// use microbench::{Options, SkipReason};
+    /// use microbench::group::{Group};
+    ///
+    /// Group::new("simd", Options::default())
+    ///     .bench("avx2", || { })
+    ///     .skip_if(|| {
+    ///         let supported = cfg!(target_feature="avx2");
+    ///         if supported { None } else { Some(SkipReason::new("AVX2 not supported")) }
+    ///     })
+    ///     .run();
+    /// ```
+    ///
+    /// Panics if this group does not contain any benchmarks.
+    pub fn skip_if(mut self, f: impl FnMut() -> Option<SkipReason> + 'a) -> Self {
+        let entry = self.entries.last_mut().expect("group does not contain any benchmarks");
+        entry.condition = Some(Box::new(f));
+        self
+    }
+
+    /// Runs the benchmarks in this group and prints the results.
+    pub fn run(&mut self) {
+        self.run_with(|o| o, |_| true);
+    }
+
+    /// Runs the benchmarks in this group whose full names (e.g., `group/name`)
+    /// match the supplied filter and prints the results.
+    ///
+    /// The supplied function is applied to the options of each benchmark (i.e.,
+    /// after the per-benchmark overrides) to apply any further overrides.
+    pub(crate) fn run_with(
+        &mut self,
+        layer: impl Fn(Options) -> Options,
+        filter: impl Fn(&str) -> bool,
+    ) {
+        let group = &self.name;
+        let mut entries = self.entries.iter_mut()
+            .filter(|e| filter(&format!("{}/{}", group, e.name)))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return;
+        }
+
+        if let Some(setup) = &mut self.setup {
+            setup();
+        }
+        let teardown = &mut self.teardown;
+        let _teardown = Teardown::new(|| if let Some(teardown) = teardown { teardown() });
+
+        let shared = self.options;
+        let mut options = layer(shared);
+        if options.format == Format::Pretty {
+            options.width = entries.iter().map(|e| e.name.len() + 16).fold(32, cmp::max);
+            options.prefix = group.len() + 1;
+            println!("{}", group);
+        }
+
+        let tags = self.tags.iter().map(|(k, v)| (&k[..], &v[..])).collect::<Vec<_>>();
+        metadata::with_tags(&tags, || {
+            for entry in &mut entries {
+                if system::interrupted() {
+                    break;
+                }
+
+                let name = format!("{}/{}", group, entry.name);
+                let (width, prefix) = (options.width, options.prefix);
+                let options = Options { width, prefix, ..entry.options(shared, &layer) };
+                match options.build() {
+                    Ok(options) => match entry.condition.as_mut().and_then(|c| c()) {
+                        Some(reason) => skip(&options, &name, &reason),
+                        None => (entry.function)(&options, &name),
+                    },
+                    Err(error) => {
+                        let reason = SkipReason::new(format!("invalid options: {}", error));
+                        skip(&options, &name, &reason);
+                    },
+                }
+            }
+        });
+    }
+}
+
+impl fmt::Debug for Group<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Group")
+            .field("name", &self.name)
+            .field("options", &self.options)
+            .field("tags", &self.tags)
+            .field("entries", &self.names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Defines a function that returns a named group of benchmarks that share a set
+/// of benchmarking options.
+///
+/// The function has the same name as the group and returns a
+/// [`Group`](group/struct.Group.html) which can be run with
+/// [`Group::run`](group/struct.Group.html#method.run).
+///
+/// ```no_run
+/// use microbench::{Options, bench_group, retain};
+///
+/// fn fibonacci(n: u64) -> u64 {
+///     if n < 2 { n } else { fibonacci(n - 2) + fibonacci(n - 1) }
+/// }
+///
+/// bench_group!(fibonacci_group, Options::default();
+///     "fibonacci_8" => || fibonacci(retain(8)),
+///     "fibonacci_16" => || fibonacci(retain(16)),
+/// );
+///
+/// fibonacci_group().run();
+/// ```
+#[macro_export]
+macro_rules! bench_group {
+    ($vis:vis $group:ident, $options:expr; $($name:expr => $f:expr),* $(,)?) => {
+        $vis fn $group() -> $crate::group::Group<'static> {
+            $crate::group::Group::new(stringify!($group), $options)
+                $(.bench($name, $f))*
+        }
+    };
+}
+
+/// Defines a `main` function that runs the supplied groups of benchmarks with
+/// the [command-line interface](cli/index.html).
+///
+/// The groups are usually defined with the
+/// [`bench_group`](macro.bench_group.html) macro. This is intended for
+/// benchmark targets (with `harness = false`) run by `cargo bench`.
+///
+/// ```no_run
+/// use microbench::{Options, bench_group, bench_main, retain};
+///
+/// bench_group!(sum, Options::default();
+///     "iter" => || (0..retain(1024u64)).sum::<u64>(),
+/// );
+///
+/// bench_main!(sum);
+/// ```
+///
+/// Functions that are run once before the first benchmark and once after the
+/// last benchmark (see [`cli::run_with_hooks`](cli/fn.run_with_hooks.html))
+/// can be supplied before the groups.
+///
+/// ```no_run
+/// use microbench::{Options, bench_group, bench_main, retain};
+///
+/// bench_group!(sum, Options::default();
+///     "iter" => || (0..retain(1024u64)).sum::<u64>(),
+/// );
+///
+/// fn start() { /* e.g., start a database server */ }
+/// fn stop() { /* e.g., stop the database server */ }
+///
+/// bench_main!(setup = start, teardown = stop; sum);
+/// ```
+#[macro_export]
+macro_rules! bench_main {
+    (setup = $setup:expr, teardown = $teardown:expr; $($group:path),+ $(,)?) => {
+        fn main() {
+            $crate::cli::run_with_hooks(vec![$($group()),+], $setup, $teardown);
+        }
+    };
+    ($($group:path),+ $(,)?) => {
+        fn main() {
+            $crate::cli::run(vec![$($group()),+]);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::{Cell};
+    use std::time::{Duration};
+
+    #[test]
+    fn test_options() {
+        let group = Group::new("group", Options::default().time(Duration::from_secs(1)))
+            .custom("a", |_, _| { })
+            .configure(|o| o.time(Duration::from_secs(2)).factor(1.5))
+            .custom("b", |_, _| { });
+
+        let options = group.benchmarks(|o| o).map(|(_, o)| o).collect::<Vec<_>>();
+        assert_eq!((options[0].time.0, options[0].factor), (2_000_000_000, 1.5));
+        assert_eq!((options[1].time.0, options[1].factor), (1_000_000_000, 1.01));
+
+        // Further overrides (e.g., `--time`) take precedence over the
+        // per-benchmark overrides.
+        let layer = |o: Options| o.time(Duration::from_secs(3));
+        let options = group.benchmarks(layer).map(|(_, o)| o).collect::<Vec<_>>();
+        assert_eq!((options[0].time.0, options[0].factor), (3_000_000_000, 1.5));
+        assert_eq!((options[1].time.0, options[1].factor), (3_000_000_000, 1.01));
+    }
+
+    #[test]
+    fn test_run_invalid() {
+        let (valid, invalid) = (Cell::new(false), Cell::new(false));
+        Group::new("group", Options::default())
+            .custom("valid", |_, _| valid.set(true))
+            .custom("invalid", |_, _| invalid.set(true))
+            .configure(|o| o.factor(0.5))
+            .run();
+        assert!(valid.get());
+        assert!(!invalid.get());
+    }
+}
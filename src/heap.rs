@@ -0,0 +1,127 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Heap profiling using `dhat`.
+//!
+//! This module is only available when the `dhat` crate feature is enabled.
+//! Heap usage can only be profiled when the `dhat` allocator is installed as the
+//! global allocator.
+//!
+//! ```
+//! #[global_allocator]
+//! static ALLOCATOR: dhat::Alloc = dhat::Alloc;
+//! ```
+//!
+//! A `dhat` profiler is running while heap usage is profiled so heap usage
+//! can't be profiled while another `dhat` profiler is running (e.g., one
+//! created at the start of `main`).
+
+use std::cell::{Cell};
+
+use crate::{Format, Options, bench_impl, measure_with_source_impl, note, retain, retain_ref};
+
+thread_local! {
+    /// The heap statistics for the benchmark being run on the current thread.
+    static STATISTICS: Cell<Option<Statistics>> = const { Cell::new(None) };
+}
+
+/// Heap statistics for an execution of a function.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// The number of bytes allocated.
+    pub total_bytes: u64,
+    /// The number of blocks (i.e., allocations) allocated.
+    pub total_blocks: u64,
+    /// The maximum number of bytes live at any one time.
+    pub max_bytes: u64,
+    /// The maximum number of blocks live at any one time.
+    pub max_blocks: u64,
+}
+
+impl Statistics {
+    /// Returns these heap statistics as a JSON object.
+    pub(crate) fn to_json(self) -> String {
+        format!(
+            r#"{{"total_bytes":{},"total_blocks":{},"max_bytes":{},"max_blocks":{}}}"#,
+            self.total_bytes, self.total_blocks, self.max_bytes, self.max_blocks,
+        )
+    }
+}
+
+/// Benchmarks the supplied function and prints the results (including the heap
+/// statistics for a single iteration).
+///
+/// The heap statistics are included in the result for the benchmark when
+/// printing JSON (as the `heap` field). See
+/// [`measure_heap`](fn.measure_heap.html) for more information.
+pub fn bench_heap<T>(options: &Options, name: &str, mut f: impl FnMut() -> T) {
+    let statistics = if options.dry_run { None } else { measure_heap(&mut f) };
+    STATISTICS.with(|s| s.set(statistics));
+    bench_impl(options, name, || measure_with_source_impl(options, options.clock, f));
+    STATISTICS.with(|s| s.set(None));
+
+    if options.dry_run || options.format == Format::Json {
+        return;
+    }
+
+    match statistics {
+        Some(statistics) => note(options, format_args!(
+            "    heap: {} bytes in {} blocks (peak: {} bytes in {} blocks)",
+            statistics.total_bytes,
+            statistics.total_blocks,
+            statistics.max_bytes,
+            statistics.max_blocks,
+        )),
+        None => note(options, format_args!(
+            "    warning: failed to profile heap usage (allocator not installed)",
+        )),
+    }
+}
+
+/// Measures the heap usage of a single (untimed) execution of the supplied
+/// function using `dhat`.
+///
+/// Only the allocations performed while executing the supplied function are
+/// included. Returns `None` if the `dhat` allocator is not installed as the
+/// global allocator.
+///
+/// # Panics
+///
+/// Panics if another `dhat` profiler is running.
+pub fn measure_heap<T>(f: impl FnOnce() -> T) -> Option<Statistics> {
+    let _profiler = dhat::Profiler::builder().testing().build();
+    retain_ref(&f());
+    let stats = dhat::HeapStats::get();
+
+    // Allocations are not recorded if the allocator isn't installed.
+    if stats.total_blocks == 0 {
+        retain(Box::new(0u8));
+        if dhat::HeapStats::get().total_blocks == 0 {
+            return None;
+        }
+    }
+
+    Some(Statistics {
+        total_bytes: stats.total_bytes,
+        total_blocks: stats.total_blocks,
+        max_bytes: stats.max_bytes as u64,
+        max_blocks: stats.max_blocks as u64,
+    })
+}
+
+/// Returns the heap statistics for the benchmark being run on the current thread
+/// as a JSON object, if any.
+pub(crate) fn json() -> Option<String> {
+    STATISTICS.with(|s| s.get()).map(Statistics::to_json)
+}
@@ -0,0 +1,243 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Historical results.
+//!
+//! A history is an append-only file of the results of runs (one JSON object
+//! per line per benchmark) that can be queried to track the performance of
+//! benchmarks over time.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{PathBuf};
+use std::process::{Command};
+
+use crate::baseline::{Baseline, Estimate};
+use crate::json::{Value};
+use crate::metadata::{self, Tags};
+use crate::time::{self, Nanoseconds};
+use crate::utility::{json_number, json_string};
+
+/// The result of a benchmark in a run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// The name of the benchmark.
+    pub name: String,
+    /// The time at which the run finished (in seconds since the Unix epoch).
+    pub timestamp: u64,
+    /// The git commit the run was performed on, if known.
+    pub commit: Option<String>,
+    /// The estimated execution time of the benchmark.
+    pub estimate: Estimate,
+    /// The tags attached to the benchmark.
+    pub tags: Tags,
+}
+
+/// An append-only file of the results of runs.
+#[derive(Clone, Debug)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// Constructs a new `History` backed by the supplied file (which will be
+    /// created if necessary when results are appended).
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        History { path: path.into() }
+    }
+
+    /// Returns the default path of the history file
+    /// (`<target>/microbench/history.jsonl`).
+    ///
+    /// The target directory is `CARGO_TARGET_DIR` if set or `target` otherwise.
+    pub fn path() -> PathBuf {
+        Baseline::path("history").with_extension("jsonl")
+    }
+
+    /// Appends the supplied results of a run to this history.
+    pub fn append(&self, records: &[Record]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut file = io::BufWriter::new(file);
+        for record in records {
+            let commit = record.commit.as_ref().map_or_else(|| "null".into(), |c| json_string(c));
+            writeln!(
+                file,
+                r#"{{"name":{},"timestamp":{},"commit":{},"estimate":{},"interval":{},"tags":{}}}"#,
+                json_string(&record.name),
+                record.timestamp,
+                commit,
+                json_number(record.estimate.estimate.0),
+                json_number(record.estimate.interval.0),
+                metadata::to_json(&record.tags),
+            )?;
+        }
+        file.flush()
+    }
+
+    /// Appends the supplied results of a run (e.g., as recorded with
+    /// [`baseline::start_recording`](../baseline/fn.start_recording.html)) to
+    /// this history with the current time and git commit.
+    pub fn append_baseline(&self, baseline: &Baseline) -> io::Result<()> {
+        let timestamp = now();
+        let commit = commit();
+        let records = baseline.iter()
+            .map(|(name, estimate)| Record {
+                name: name.into(),
+                timestamp,
+                commit: commit.clone(),
+                estimate,
+                tags: baseline.tags(name).to_vec(),
+            })
+            .collect::<Vec<_>>();
+        self.append(&records)
+    }
+
+    /// Returns all of the results in this history (in the order they were
+    /// appended).
+    pub fn records(&self) -> io::Result<Vec<Record>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        text.lines().filter(|l| !l.trim().is_empty()).map(|line| {
+            let value = Value::parse(line).map_err(invalid)?;
+            let field = |key| value.get(key).and_then(Value::as_f64);
+            let name = value.get("name").and_then(Value::as_str);
+            let commit = value.get("commit").and_then(Value::as_str).map(String::from);
+            match (name, field("timestamp"), field("estimate"), field("interval")) {
+                (Some(name), Some(timestamp), Some(estimate), Some(interval)) => Ok(Record {
+                    name: name.into(),
+                    timestamp: timestamp as u64,
+                    commit,
+                    estimate: Estimate {
+                        estimate: Nanoseconds(estimate),
+                        interval: Nanoseconds(interval),
+                        confidence: f64::NAN,
+                        error: Nanoseconds(f64::NAN),
+                        dof: f64::NAN,
+                    },
+                    tags: metadata::from_json(value.get("tags")),
+                }),
+                _ => Err(invalid("missing or invalid fields".into())),
+            }
+        }).collect()
+    }
+
+    /// Returns the results in this history for the benchmark with the supplied
+    /// name (in chronological order).
+    pub fn trend(&self, name: &str) -> io::Result<Vec<Record>> {
+        let mut records = self.records()?;
+        records.retain(|r| r.name == name);
+        records.sort_by_key(|r| r.timestamp);
+        Ok(records)
+    }
+
+    /// Returns the results in this history for the benchmark with the supplied
+    /// name since the supplied time (in seconds since the Unix epoch).
+    pub fn trend_since(&self, name: &str, timestamp: u64) -> io::Result<Vec<Record>> {
+        let mut records = self.trend(name)?;
+        records.retain(|r| r.timestamp >= timestamp);
+        Ok(records)
+    }
+}
+
+/// Returns the current time (in seconds since the Unix epoch).
+fn now() -> u64 {
+    time::since_epoch().as_secs()
+}
+
+/// Returns the current git commit.
+///
+/// The `MICROBENCH_COMMIT` environment variable is used if set, otherwise the
+/// commit is determined with `git rev-parse HEAD`.
+pub fn commit() -> Option<String> {
+    if let Ok(commit) = env::var("MICROBENCH_COMMIT") {
+        return Some(commit);
+    }
+
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().into())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    fn record(name: &str, timestamp: u64, estimate: f64) -> Record {
+        Record {
+            name: name.into(),
+            timestamp,
+            commit: Some(format!("commit-{}", timestamp)),
+            estimate: Estimate {
+                estimate: Nanoseconds(estimate),
+                interval: Nanoseconds(estimate / 10.0),
+                confidence: f64::NAN,
+                error: Nanoseconds(f64::NAN),
+                dof: f64::NAN,
+            },
+            tags: vec![("size".into(), timestamp.to_string())],
+        }
+    }
+
+    fn summary(records: &[Record]) -> Vec<(&str, u64, f64)> {
+        records.iter().map(|r| (&r.name[..], r.timestamp, r.estimate.estimate.0)).collect()
+    }
+
+    #[test]
+    fn test_history() {
+        let path = env::temp_dir().join(format!("microbench-history-{}.jsonl", process::id()));
+        let history = History::new(&path);
+        let _ = fs::remove_file(&path);
+
+        assert!(history.records().unwrap().is_empty());
+
+        let mut untracked = record("a", 30, 300.0);
+        untracked.commit = None;
+        history.append(&[record("a", 20, 200.0), record("b", 10, 100.0)]).unwrap();
+        history.append(&[untracked.clone(), record("a", 10, 100.0)]).unwrap();
+
+        let records = history.records();
+        let trend = history.trend("a");
+        let since = history.trend_since("a", 20);
+        let missing = history.trend("c");
+        fs::remove_file(&path).unwrap();
+
+        let records = records.unwrap();
+        assert_eq!(summary(&records), [("a", 20, 200.0), ("b", 10, 100.0), ("a", 30, 300.0), ("a", 10, 100.0)]);
+        for (record, expected) in records.iter().zip(&[record("a", 20, 200.0), record("b", 10, 100.0), untracked]) {
+            assert_eq!(record.commit, expected.commit);
+            assert_eq!(record.estimate.interval, expected.estimate.interval);
+            assert!(record.estimate.error.0.is_nan());
+            assert_eq!(record.tags, expected.tags);
+        }
+
+        assert_eq!(summary(&trend.unwrap()), [("a", 10, 100.0), ("a", 20, 200.0), ("a", 30, 300.0)]);
+        assert_eq!(summary(&since.unwrap()), [("a", 20, 200.0), ("a", 30, 300.0)]);
+        assert!(missing.unwrap().is_empty());
+    }
+}
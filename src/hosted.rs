@@ -0,0 +1,3110 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The parts of this crate that require the standard library.
+//!
+//! The items in this module are re-exported from the crate root.
+
+use std::borrow::{Cow};
+use std::cmp;
+use std::env;
+use std::error;
+use std::fmt;
+use std::future::{Future};
+use std::iter;
+use std::mem;
+use std::ops::{Add, ControlFlow, Mul, Sub};
+use std::process;
+use std::slice;
+use std::str::{FromStr};
+use std::sync::{Barrier, Once, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration};
+
+use crate::{GeometricSequence, Sample, retain, retain_ref};
+use crate::{baseline, config, metadata, profiler, statistics, system, time, utility};
+use crate::statistics::{BayesianModel, ComplexityModel, Kahan, Model, Moments, QuantileModel, StudentT};
+use crate::system::{Frequencies, FrequencyMonitor};
+use crate::time::{Clock, Nanoseconds, Stopwatch, TimeSource, Unit};
+use crate::utility::{Random, format_compact, format_number, json_number, json_string};
+
+/// The maximum number of values buffered by `measure_drop` before dropping them.
+const DROP_CAPACITY: u64 = 4096;
+
+/// The skewness above which per-iteration times are considered heavily skewed.
+const SKEWNESS: f64 = 1.0;
+
+/// The autocorrelation above which residuals are considered to indicate drift.
+const AUTOCORRELATION: f64 = 0.5;
+
+/// The minimum number of clock ticks (i.e., multiples of the resolution of the
+/// clock) samples are expected to take.
+const MIN_TICKS: u64 = 10;
+
+/// The amount of time to wait for a profiler to be attached before profiling.
+const PROFILE_DELAY: Duration = Duration::from_secs(1);
+
+/// The minimum number of nanoseconds each batch of iterations is executed for
+/// while profiling.
+const PROFILE_BATCH: u64 = 10_000_000;
+
+/// The interval at which the CPU frequency is sampled while benchmarks are run.
+const FREQUENCY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The relative drop in the CPU frequency during the run of a benchmark above
+/// which the benchmark is considered to have been throttled.
+const FREQUENCY_DROP: f64 = 0.05;
+
+/// The warning for benchmarks compiled without optimizations.
+const UNOPTIMIZED: &str = "benchmarks were compiled without optimizations (use `--release`), \
+    results will not reflect the performance of optimized code";
+
+/// The warnings about the environment the benchmarks are run in.
+static ENVIRONMENT: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Ensures the warnings about the environment are printed at most once.
+static ENVIRONMENT_WARNED: Once = Once::new();
+
+/// A number of bytes.
+///
+/// ```
+/// use microbench::{Bytes};
+///
+/// let total = Bytes::mebibytes(256) * 2 + Bytes::kibibytes(512);
+/// assert_eq!(total.to_string(), "512.5 MiB");
+/// assert_eq!("512.5MiB".parse(), Ok(total));
+/// ```
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bytes(pub u64);
+
+impl Bytes {
+    /// Returns the number of bytes in the supplied number of kibibytes (2¹⁰ bytes).
+    pub fn kibibytes(kibibytes: u64) -> Self {
+        Bytes(kibibytes * 1024)
+    }
+
+    /// Returns the number of bytes in the supplied number of mebibytes (2²⁰ bytes).
+    pub fn mebibytes(mebibytes: u64) -> Self {
+        Bytes(mebibytes * 1024 * 1024)
+    }
+
+    /// Returns the number of bytes in the supplied number of gibibytes (2³⁰ bytes).
+    pub fn gibibytes(gibibytes: u64) -> Self {
+        Bytes(gibibytes * 1024 * 1024 * 1024)
+    }
+}
+
+impl FromStr for Bytes {
+    type Err = ParseBytesError;
+
+    /// Parses a number of bytes with an optional unit (e.g., `512MiB` or `2G`).
+    ///
+    /// The supported units are `B`, the binary units `KiB`, `MiB`, `GiB`, and
+    /// `TiB` (which may be abbreviated to `K`, `M`, `G`, and `T`), and the
+    /// decimal units `KB`, `MB`, `GB`, and `TB` (case-insensitive).
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let bytes = utility::parse_quantity(value, |unit| {
+            match &unit.to_ascii_lowercase()[..] {
+                "" | "b" => Some(1.0),
+                "k" | "kib" => Some(1024.0),
+                "m" | "mib" => Some(1024.0 * 1024.0),
+                "g" | "gib" => Some(1024.0 * 1024.0 * 1024.0),
+                "t" | "tib" => Some(1024.0 * 1024.0 * 1024.0 * 1024.0),
+                "kb" => Some(1e3),
+                "mb" => Some(1e6),
+                "gb" => Some(1e9),
+                "tb" => Some(1e12),
+                _ => None,
+            }
+        });
+
+        match bytes {
+            Some(bytes) if bytes <= u64::MAX as f64 => Ok(Bytes(bytes.round() as u64)),
+            _ => Err(ParseBytesError(value.into())),
+        }
+    }
+}
+
+impl Add for Bytes {
+    type Output = Bytes;
+
+    fn add(self, other: Bytes) -> Bytes {
+        Bytes(self.0 + other.0)
+    }
+}
+
+impl Sub for Bytes {
+    type Output = Bytes;
+
+    fn sub(self, other: Bytes) -> Bytes {
+        Bytes(self.0 - other.0)
+    }
+}
+
+impl Mul<u64> for Bytes {
+    type Output = Bytes;
+
+    fn mul(self, other: u64) -> Bytes {
+        Bytes(self.0 * other)
+    }
+}
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// An error indicating that a string is not a valid number of bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseBytesError(String);
+
+impl fmt::Display for ParseBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid number of bytes: `{}`", self.0)
+    }
+}
+
+impl error::Error for ParseBytesError { }
+
+/// An amount of work performed by each execution of a function.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Throughput {
+    /// A number of bytes processed by each execution of a function.
+    Bytes(Bytes),
+    /// A number of elements processed by each execution of a function.
+    Elements(u64),
+}
+
+impl Throughput {
+    /// Returns the supplied execution time formatted as a rate of work.
+    fn rate(self, time: Nanoseconds<f64>) -> String {
+        match self {
+            Throughput::Bytes(bytes) => format!("{:.3} GB/s", bytes.0 as f64 / time.0),
+            Throughput::Elements(elements) => {
+                format!("{:.3} Melem/s", elements as f64 / time.0 * 1_000.0)
+            },
+        }
+    }
+}
+
+/// A method of estimating the execution time of a function from samples.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Estimator {
+    /// The slope of the OLS linear regression model function.
+    Ols,
+    /// The minimum per-iteration execution time across all samples.
+    ///
+    /// This is a common technique for approximating the noise-free execution
+    /// time of a function since noise can only ever make execution slower.
+    Minimum,
+    /// The slope of the linear quantile regression model function for the
+    /// supplied quantile (e.g., `0.1` for the 10th percentile), which must be
+    /// between `0.0` and `1.0` (exclusive).
+    ///
+    /// Low quantiles are much more robust than OLS to noise caused by
+    /// interrupts and give a better estimate of steady-state execution time.
+    Quantile(f64),
+}
+
+impl Estimator {
+    /// Returns the estimated execution time of a single iteration of the
+    /// function that produced the supplied samples.
+    pub fn estimate(self, samples: &[Sample]) -> Nanoseconds<f64> {
+        match self {
+            Estimator::Ols => Analysis::new(samples).beta,
+            Estimator::Minimum => Nanoseconds(samples.iter()
+                .map(|m| m.elapsed.0 as f64 / m.iterations as f64)
+                .fold(f64::INFINITY, f64::min)),
+            Estimator::Quantile(quantile) => {
+                let data = samples.iter()
+                    .map(|m| (m.iterations as f64, m.elapsed.0 as f64))
+                    .collect::<Vec<_>>();
+                Nanoseconds(QuantileModel::new(&data, quantile).beta)
+            },
+        }
+    }
+}
+
+impl fmt::Display for Estimator {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Estimator::Ols => write!(formatter, "OLS"),
+            Estimator::Minimum => write!(formatter, "minimum"),
+            Estimator::Quantile(quantile) => write!(formatter, "quantile {:.2}", quantile),
+        }
+    }
+}
+
+/// The verdict of a comparison of the execution times of two functions.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The function is significantly faster than the function it is compared
+    /// to.
+    Faster,
+    /// The function is significantly slower than the function it is compared
+    /// to.
+    Slower,
+    /// There is no significant difference between the execution times of the
+    /// functions.
+    NoDifference,
+}
+
+impl Verdict {
+    /// Returns the verdict for the supplied ratio of the execution times of two
+    /// functions (e.g., `1.1` for a 10% slowdown) and the p-value of a
+    /// statistical test of the difference between the execution times.
+    ///
+    /// The difference is significant if the p-value is less than one minus the
+    /// supplied confidence level (e.g., `0.05` for `0.95`). A `NaN` p-value is
+    /// never significant.
+    pub fn new(ratio: f64, p: f64, level: f64) -> Self {
+        if p.is_nan() || p >= 1.0 - level {
+            Verdict::NoDifference
+        } else if ratio < 1.0 {
+            Verdict::Faster
+        } else {
+            Verdict::Slower
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Faster => write!(formatter, "faster"),
+            Verdict::Slower => write!(formatter, "slower"),
+            Verdict::NoDifference => write!(formatter, "no significant difference"),
+        }
+    }
+}
+
+/// A format for printed benchmark results.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable results (one line per benchmark followed by any
+    /// warnings).
+    Pretty,
+    /// Machine-readable results (one JSON object per line per benchmark).
+    ///
+    /// Other output (e.g., warnings that are not specific to the results of a
+    /// benchmark) is printed to standard error instead of standard output.
+    Json,
+}
+
+impl Format {
+    /// Parses the name of a format (e.g., `json`).
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pretty" => Some(Format::Pretty),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A plan for the number of iterations in each benchmark sample.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// Iteration counts that grow geometrically (see the `factor` option) until
+    /// the maximum amount of time benchmarks will run for has elapsed.
+    Geometric,
+    /// The supplied iteration count repeated the supplied number of times.
+    ///
+    /// Fewer samples are collected if the maximum amount of time benchmarks
+    /// will run for elapses first. Since every sample has the same number of iterations, the samples
+    /// cannot be analyzed with linear regression (use a non-regression
+    /// estimator such as `Estimator::Minimum` instead).
+    Repeat {
+        /// The number of iterations in each sample.
+        iterations: u64,
+        /// The number of samples.
+        count: u64,
+    },
+    /// The supplied iteration counts.
+    ///
+    /// Fewer samples are collected if the maximum amount of time benchmarks
+    /// will run for elapses first. This schedule can be serialized but not
+    /// deserialized.
+    #[cfg_attr(feature="serde", serde(deserialize_with="deserialize_fixed"))]
+    Fixed(&'static [u64]),
+}
+
+/// Rejects the iteration counts of a `Schedule::Fixed` when deserializing.
+///
+/// The iteration counts are borrowed for the rest of the program so they cannot
+/// be deserialized without leaking them.
+#[cfg(feature="serde")]
+fn deserialize_fixed<'de, D: serde::Deserializer<'de>>(
+    _: D
+) -> Result<&'static [u64], D::Error> {
+    Err(serde::de::Error::custom("fixed schedules cannot be deserialized"))
+}
+
+/// A set of benchmarking options.
+///
+/// Some of the default options can be overridden with a configuration file
+/// named `microbench.toml` in the package directory (or any of its ancestors)
+/// so that all of the benchmarks in a project share the same settings. Options
+/// set explicitly (e.g., by a group of benchmarks) to anything other than their
+/// default values take precedence over the configuration file. The
+/// configuration file is only read by
+/// [`Options::from_config`](struct.Options.html#method.from_config) (and
+/// [`Options::from_env`](struct.Options.html#method.from_env)) and by the
+/// command-line interface:
+///
+/// ```toml
+/// time = "2s"
+/// warmup = "500ms"
+/// factor = 1.05
+/// memory = "1GiB"
+/// min_samples = 100
+/// format = "pretty"
+/// filter = "sort/*"           # only used by the command-line interface
+/// exclude = ["sort/bubble*"]  # only used by the command-line interface
+/// ```
+///
+/// The path of the configuration file can also be set with the
+/// `MICROBENCH_CONFIG` environment variable. Only a subset of TOML is supported
+/// (one `key = value` pair per line). Invalid configuration files are ignored
+/// (with a warning).
+///
+/// Some of the default options (including those set by the configuration file)
+/// can also be overridden with environment variables (e.g., to shorten
+/// benchmarks in CI without recompiling). These overrides are applied by
+/// `Options::default()` (so they are the defaults for any options that are not
+/// set explicitly) and by the command-line interface (which applies the
+/// configuration file and then these overrides to the options of each benchmark
+/// so they also take precedence over options set explicitly):
+///
+/// | Variable                 | Option        | Example |
+/// |--------------------------|---------------|---------|
+/// | `MICROBENCH_TIME`        | `time`        | `2.5s`  |
+/// | `MICROBENCH_WARMUP`      | `warmup`      | `500ms` |
+/// | `MICROBENCH_FACTOR`      | `factor`      | `1.05`  |
+/// | `MICROBENCH_MEMORY`      | `memory`      | `1GiB`  |
+/// | `MICROBENCH_MIN_SAMPLES` | `min_samples` | `100`   |
+/// | `MICROBENCH_FORMAT`      | `format`      | `json`  |
+/// | `MICROBENCH_DRY_RUN`     | `dry_run`     | `1`     |
+///
+/// Durations are parsed with [`time::parse_duration`](time/fn.parse_duration.html)
+/// (e.g., `2.5` or `2.5s`), amounts of memory are parsed as [`Bytes`](struct.Bytes.html)
+/// (e.g., `1024` or `1GiB`), and flags are `1` or `true` (or `0` or `false`).
+/// Invalid values are ignored (with a warning).
+///
+/// When the `serde` crate feature is enabled, options can be serialized and
+/// deserialized (fields missing when deserializing are set to their defaults)
+/// except that options with a `Schedule::Fixed` cannot be deserialized.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(default))]
+#[derive(Copy, Clone, Debug)]
+pub struct Options {
+    pub(crate) clock: Clock,
+    pub(crate) compact: bool,
+    pub(crate) confidence: f64,
+    pub(crate) core: Option<usize>,
+    pub(crate) deadline: Option<Nanoseconds<u64>>,
+    pub(crate) dry_run: bool,
+    pub(crate) estimator: Estimator,
+    pub(crate) factor: f64,
+    pub(crate) flush: Option<Bytes>,
+    pub(crate) format: Format,
+    pub(crate) interleave: bool,
+    pub(crate) max_iterations: u64,
+    pub(crate) max_samples: Option<usize>,
+    pub(crate) memory: Bytes,
+    pub(crate) min_samples: usize,
+    pub(crate) priority: bool,
+    pub(crate) profile: Option<Nanoseconds<u64>>,
+    pub(crate) retries: u32,
+    pub(crate) rss: bool,
+    pub(crate) schedule: Schedule,
+    pub(crate) shuffle: bool,
+    pub(crate) stability: f64,
+    pub(crate) threads: usize,
+    pub(crate) throttling: bool,
+    pub(crate) throughput: Option<Throughput>,
+    pub(crate) time: Nanoseconds<u64>,
+    pub(crate) unit: Unit,
+    pub(crate) verification: Option<Nanoseconds<u64>>,
+    pub(crate) warmup: Nanoseconds<u64>,
+    #[cfg_attr(feature="serde", serde(skip))]
+    pub(crate) width: usize,
+    #[cfg_attr(feature="serde", serde(skip))]
+    pub(crate) prefix: usize,
+}
+
+impl Options {
+    /// Returns the default options with the options in the configuration file
+    /// applied (see above).
+    ///
+    /// Invalid configuration files are ignored (with a warning).
+    pub fn from_config() -> Self {
+        Options::default().with_config()
+    }
+
+    /// Returns the default options with the options in the configuration file
+    /// and the overrides read from environment variables applied (see above).
+    ///
+    /// Invalid values are ignored (with a warning).
+    pub fn from_env() -> Self {
+        Options::from_config().with_environment()
+    }
+
+    /// Returns these options with the options in the configuration file
+    /// applied.
+    pub(crate) fn with_config(self) -> Self {
+        config::get().apply(self)
+    }
+
+    /// Returns these options with the overrides read from environment
+    /// variables applied.
+    pub(crate) fn with_environment(self) -> Self {
+        environment().apply(self)
+    }
+
+    /// Returns these options with the options in the configuration file and
+    /// then the overrides read from environment variables applied.
+    pub(crate) fn with_overrides(self) -> Self {
+        self.with_config().with_environment()
+    }
+
+    /// Returns these options if they are valid.
+    ///
+    /// The builder methods accept any values but some values produce
+    /// meaningless results (e.g., a growth factor that does not grow the
+    /// number of sample iterations). This method can be used to reject such
+    /// values with a descriptive error.
+    ///
+    /// ```
+    /// use std::time::{Duration};
+    ///
+    /// use microbench::{Options, OptionsError};
+    ///
+    /// let options = Options::default().time(Duration::from_secs(1)).build();
+    /// assert!(options.is_ok());
+    ///
+    /// let options = Options::default().factor(0.5).build();
+    /// assert_eq!(options.unwrap_err(), OptionsError::Factor(0.5));
+    /// ```
+    pub fn build(self) -> Result<Self, OptionsError> {
+        let quantile = match self.estimator {
+            Estimator::Quantile(quantile) => quantile,
+            _ => 0.5,
+        };
+
+        if self.factor.is_nan() || self.factor <= 1.0 {
+            Err(OptionsError::Factor(self.factor))
+        } else if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            Err(OptionsError::Confidence(self.confidence))
+        } else if !(quantile > 0.0 && quantile < 1.0) {
+            Err(OptionsError::Quantile(quantile))
+        } else if self.time.0 == 0 {
+            Err(OptionsError::Time)
+        } else if self.deadline.is_some_and(|d| d.0 == 0) {
+            Err(OptionsError::Deadline)
+        } else if self.memory.0 == 0 {
+            Err(OptionsError::Memory)
+        } else if self.max_iterations == 0 {
+            Err(OptionsError::MaxIterations)
+        } else if self.max_samples == Some(0) {
+            Err(OptionsError::MaxSamples)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Sets the clock used to measure the execution time of benchmarks.
+    ///
+    /// The maximum amount of time benchmarks will run for (i.e., the `time`
+    /// option) is always measured using the wall clock.
+    ///
+    /// **Default:** `Clock::Wall`
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets whether printed numbers are formatted compactly with SI suffixes
+    /// (e.g., `9.41k` instead of `9_410.000`).
+    ///
+    /// This makes columns of results narrower at the cost of precision. Numbers
+    /// in machine-readable formats are never formatted compactly.
+    ///
+    /// **Default:** `false`
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Sets the probability that the intervals around estimated execution times
+    /// contain the true execution times (e.g., `0.99`).
+    ///
+    /// This is used for the printed intervals, for determining whether
+    /// differences between benchmarks (or between a benchmark and a baseline)
+    /// are significant, and for the `stability` option. Higher probabilities
+    /// produce wider intervals and more conservative comparisons.
+    ///
+    /// **Default:** `0.95`
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    /// Sets the maximum amount of time a single benchmark sample may take.
+    ///
+    /// Samples that are predicted to exceed this amount of time (based on the
+    /// execution time per iteration of the previous sample) are skipped and
+    /// samples that actually exceed this amount of time are discarded. This
+    /// prevents a single sample with a huge number of iterations (e.g., of a
+    /// function with superlinear execution time) from consuming the entire
+    /// time budget.
+    ///
+    /// **Default:** `None`
+    pub fn deadline(mut self, deadline: impl Into<Option<Duration>>) -> Self {
+        self.deadline = deadline.into().map(Nanoseconds::from);
+        self
+    }
+
+    /// Sets whether benchmarks are dry runs.
+    ///
+    /// When enabled, the functions being benchmarked are executed exactly once
+    /// (without warming up) and a line indicating that the benchmark ran is
+    /// printed instead of the results. This is useful for quickly checking that
+    /// benchmarks still work (e.g., in CI or after refactoring). The results of
+    /// dry runs are not recorded in baselines or history.
+    ///
+    /// **Default:** `false`
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets the method used to estimate the execution time of benchmarks.
+    ///
+    /// Comparisons (e.g., [`bench_compare`](fn.bench_compare.html)) always use
+    /// the OLS slopes since the statistical test of the difference between the
+    /// execution times requires their standard errors.
+    ///
+    /// **Default:** `Estimator::Ols`
+    pub fn estimator(mut self, estimator: Estimator) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    /// Sets the CPU core benchmarks will be restricted to running on.
+    ///
+    /// When set, the thread collecting benchmark samples is pinned to this CPU
+    /// core while collecting samples so that it does not migrate between cores
+    /// (which can cause visible steps in the sample data). The thread's
+    /// previous CPU affinity is restored afterwards. Pinning is only supported
+    /// on Linux and Windows. See
+    /// [`system::pin_to_core`](system/fn.pin_to_core.html) for more
+    /// information.
+    ///
+    /// **Default:** `None`
+    pub fn pin_to_core(mut self, core: impl Into<Option<usize>>) -> Self {
+        self.core = core.into();
+        self
+    }
+
+    /// Sets the geometric growth factor for benchmark sample iterations.
+    ///
+    /// **Default:** `1.01`
+    pub fn factor(mut self, factor: f64) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Sets the size of the buffer used to flush the CPU caches before each
+    /// benchmark sample.
+    ///
+    /// When set, a buffer of this size (which should be larger than the
+    /// last-level cache, e.g., `Bytes::mebibytes(64)`) is written to (untimed)
+    /// before each sample to evict the data used by the benchmark from the CPU
+    /// caches. Note that only the first iteration of each sample runs with cold
+    /// caches, so this is most useful with `Schedule::Repeat { iterations: 1,
+    /// .. }` or with [`measure_calls`](fn.measure_calls.html) (which flushes the
+    /// caches before every call).
+    ///
+    /// **Default:** `None`
+    pub fn flush(mut self, flush: impl Into<Option<Bytes>>) -> Self {
+        self.flush = flush.into();
+        self
+    }
+
+    /// Sets the format of printed benchmark results.
+    ///
+    /// **Default:** `Format::Pretty`
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets whether compared benchmarks alternate samples.
+    ///
+    /// When enabled, [`bench_compare`](fn.bench_compare.html) collects samples
+    /// for both functions within the same time window (alternating between the
+    /// functions after every sample) instead of benchmarking the functions one
+    /// after the other. This ensures that slow changes in the environment
+    /// (e.g., thermal throttling) affect both functions equally. See
+    /// [`measure_interleaved`](fn.measure_interleaved.html) for more
+    /// information.
+    ///
+    /// **Default:** `false`
+    pub fn interleave(mut self, interleave: bool) -> Self {
+        self.interleave = interleave;
+        self
+    }
+
+    /// Sets the maximum number of iterations in a benchmark sample.
+    ///
+    /// Samples with more iterations are not collected (even if time remains)
+    /// unless the schedule explicitly specifies the iteration counts. Lowering
+    /// this limit can prevent extremely cheap functions from reaching iteration
+    /// counts where a single sample takes an unreasonable amount of time.
+    ///
+    /// **Default:** `1_000_000_000_000_000`
+    pub fn max_iterations(mut self, max_iterations: u64) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Sets the maximum number of samples benchmarks will collect.
+    ///
+    /// Benchmarks stop collecting samples once this many samples have been
+    /// collected (even if time remains), which bounds the cost of analyzing the
+    /// samples of cheap functions. This takes precedence over the
+    /// `min_samples` option. For [`bench_calls`](fn.bench_calls.html), this is
+    /// the maximum number of calls that will be timed.
+    ///
+    /// **Default:** `None`
+    pub fn max_samples(mut self, max_samples: impl Into<Option<usize>>) -> Self {
+        self.max_samples = max_samples.into();
+        self
+    }
+
+    /// Sets the maximum amount of memory benchmarks will allocate.
+    ///
+    /// **Default:** `Bytes::mebibytes(512)`
+    pub fn memory(mut self, memory: Bytes) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Sets the minimum number of samples benchmarks will collect.
+    ///
+    /// If the maximum amount of time benchmarks will run for elapses before
+    /// this many samples have been collected, benchmarks will continue to run
+    /// until this many samples have been collected (which is useful for slow
+    /// functions).
+    ///
+    /// **Default:** `0`
+    pub fn min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    /// Sets whether the scheduling priority of benchmarks is raised.
+    ///
+    /// When enabled, the scheduling priority of the thread collecting benchmark
+    /// samples is raised to the highest priority while collecting samples to
+    /// reduce preemption by other threads and processes. The previous priority
+    /// is restored afterwards. Raising the priority usually requires elevated
+    /// privileges on Unix platforms, a warning is printed if the priority could
+    /// not be raised. See
+    /// [`system::elevate_priority`](system/fn.elevate_priority.html) for more
+    /// information.
+    ///
+    /// **Default:** `false`
+    pub fn priority(mut self, priority: bool) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the amount of time benchmarks are profiled for instead of being
+    /// measured.
+    ///
+    /// When set, the process ID is printed and, after waiting briefly so that a
+    /// profiler (e.g., `perf record -p <pid>`) can be attached, the functions
+    /// being benchmarked are executed repeatedly for this amount of time
+    /// without collecting samples (or warming up) so that profiles are not
+    /// polluted by the benchmarking machinery. No results are printed or
+    /// recorded for profiled benchmarks.
+    ///
+    /// **Default:** `None`
+    pub fn profile(mut self, profile: impl Into<Option<Duration>>) -> Self {
+        self.profile = profile.into().map(Nanoseconds::from);
+        self
+    }
+
+    /// Sets the maximum number of times the samples of unstable benchmarks are
+    /// recollected.
+    ///
+    /// The samples of a benchmark are considered unstable if the width of the
+    /// interval around the estimated execution time relative to the estimated
+    /// execution time exceeds the `stability` option. The samples are
+    /// recollected (without warming up again and with the full time budget
+    /// unless limited by the `verification` option) until they are stable or this number of retries is exhausted, and the
+    /// most stable set of samples is used. The number of retries is noted in the
+    /// printed results.
+    ///
+    /// **Default:** `0`
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets whether the peak resident set size (RSS) of benchmarks is measured.
+    ///
+    /// When enabled, printed results include the peak RSS of the process while
+    /// the benchmark was running and the difference between the peak RSS and
+    /// the RSS of the process before the benchmark started. See
+    /// [`system::peak_rss`](system/fn.peak_rss.html) for more information.
+    ///
+    /// **Default:** `false`
+    pub fn rss(mut self, rss: bool) -> Self {
+        self.rss = rss;
+        self
+    }
+
+    /// Sets the plan for the number of iterations in each benchmark sample.
+    ///
+    /// **Default:** `Schedule::Geometric`
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Sets whether the iteration counts of benchmark samples are shuffled.
+    ///
+    /// When enabled, the iteration counts for all of the samples are chosen
+    /// before any samples are collected and the samples are then collected in a
+    /// random order. This decorrelates the number of iterations in each sample
+    /// from the time at which the sample was collected so that slow changes in
+    /// the environment (e.g., thermal throttling) do not bias the estimated
+    /// execution time.
+    ///
+    /// For `Schedule::Geometric`, the iteration counts are chosen by a short
+    /// pilot phase (which counts towards the time budget) that estimates the
+    /// execution time of the benchmark and the samples that can be collected in
+    /// the remaining time.
+    ///
+    /// **Default:** `false`
+    pub fn shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Sets the maximum width of the interval around the estimated execution
+    /// time of a benchmark relative to the estimated execution time (e.g.,
+    /// `0.05` for ±5%) before the samples of the benchmark are considered
+    /// unstable.
+    ///
+    /// This is only used when the `retries` option is not zero.
+    ///
+    /// **Default:** `0.05`
+    pub fn stability(mut self, stability: f64) -> Self {
+        self.stability = stability;
+        self
+    }
+
+    /// Sets the number of threads that will concurrently execute the functions
+    /// benchmarked by the concurrent benchmarking and measuring functions.
+    ///
+    /// See [`measure_concurrent`](fn.measure_concurrent.html) for more
+    /// information.
+    ///
+    /// **Default:** `1`
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets whether benchmarks are monitored for thermal throttling.
+    ///
+    /// When enabled, the frequency of the CPU core a benchmark is run on (see
+    /// [`pin_to_core`](#method.pin_to_core)) is sampled on a background thread
+    /// while the benchmark is run and a warning is printed if the core was
+    /// thermally throttled or its frequency dropped during the run. See
+    /// [`system::FrequencyMonitor`](system/struct.FrequencyMonitor.html) for
+    /// the supported platforms.
+    ///
+    /// **Default:** `false`
+    pub fn throttling(mut self, throttling: bool) -> Self {
+        self.throttling = throttling;
+        self
+    }
+
+    /// Sets the amount of work performed by each execution of benchmarked
+    /// functions.
+    ///
+    /// When set, printed results include the rate at which the work is
+    /// performed (e.g., `GB/s`) as derived from the estimated execution time.
+    ///
+    /// **Default:** `None`
+    pub fn throughput(mut self, throughput: impl Into<Option<Throughput>>) -> Self {
+        self.throughput = throughput.into();
+        self
+    }
+
+    /// Sets the maximum amount of time benchmarks will run for (excluding the
+    /// time spent warming up and recollecting unstable samples).
+    ///
+    /// This is the time budget for collecting samples and applies to every
+    /// schedule (see the `min_samples` option for an exception). The time spent
+    /// warming up and recollecting unstable samples have separate budgets (the
+    /// `warmup` and `verification` options).
+    ///
+    /// **Default:** `Duration::new(5, 0)`
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = time.into();
+        self
+    }
+
+    /// Sets the unit of time used to print execution times.
+    ///
+    /// Using the same unit for all benchmarks makes the printed results of
+    /// benchmarks with very different execution times easier to compare.
+    /// Execution times in machine-readable formats are always in nanoseconds.
+    ///
+    /// **Default:** `Unit::Nanos`
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Sets the maximum amount of time spent recollecting the samples of
+    /// unstable benchmarks (see the `retries` option).
+    ///
+    /// When set, no more recollections are started once this much time has
+    /// elapsed and a recollection in progress is stopped early (regardless of
+    /// the schedule). Otherwise, each recollection has the full time budget
+    /// (i.e., the `time` option). This time does not count towards the `time`
+    /// option either way.
+    ///
+    /// **Default:** `None`
+    pub fn verification(mut self, verification: impl Into<Option<Duration>>) -> Self {
+        self.verification = verification.into().map(Nanoseconds::from);
+        self
+    }
+
+    /// Sets the amount of time benchmarks will run for before samples are
+    /// collected.
+    ///
+    /// Warming up a benchmark allows caches to be populated and any lazy
+    /// initialization to be performed before the first sample is collected.
+    /// This time does not count towards the maximum amount of time benchmarks
+    /// will run for (i.e., the `time` option).
+    ///
+    /// **Default:** `Duration::new(0, 0)`
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup.into();
+        self
+    }
+}
+
+impl Options {
+    /// Returns the built-in default options (i.e., without the overrides read
+    /// from environment variables).
+    pub(crate) fn builtin() -> Self {
+        let clock = Clock::Wall;
+        let compact = false;
+        let confidence = 0.95;
+        let core = None;
+        let deadline = None;
+        let dry_run = false;
+        let estimator = Estimator::Ols;
+        let factor = 1.01;
+        let flush = None;
+        let format = Format::Pretty;
+        let interleave = false;
+        let max_iterations = 1_000_000_000_000_000;
+        let max_samples = None;
+        let memory = Bytes::mebibytes(512);
+        let min_samples = 0;
+        let priority = false;
+        let profile = None;
+        let retries = 0;
+        let rss = false;
+        let schedule = Schedule::Geometric;
+        let shuffle = false;
+        let stability = 0.05;
+        let threads = 1;
+        let throttling = false;
+        let throughput = None;
+        let time = Duration::new(5, 0).into();
+        let unit = Unit::Nanos;
+        let verification = None;
+        let warmup = Duration::new(0, 0).into();
+        let width = 32;
+        let prefix = 0;
+        Options {
+            clock,
+            compact,
+            confidence,
+            core,
+            deadline,
+            dry_run,
+            estimator,
+            factor,
+            flush,
+            format,
+            interleave,
+            max_iterations,
+            max_samples,
+            memory,
+            min_samples,
+            priority,
+            profile,
+            retries,
+            rss,
+            schedule,
+            shuffle,
+            stability,
+            threads,
+            throttling,
+            throughput,
+            time,
+            unit,
+            verification,
+            warmup,
+            width,
+            prefix,
+        }
+    }
+}
+
+impl Default for Options {
+    /// Returns the default options with the overrides read from environment
+    /// variables applied (see above).
+    ///
+    /// Invalid values are ignored (with a warning).
+    fn default() -> Self {
+        Options::builtin().with_environment()
+    }
+}
+
+/// An error indicating that a set of benchmarking options is invalid.
+///
+/// See [`Options::build`](struct.Options.html#method.build) for more
+/// information.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OptionsError {
+    /// The geometric growth factor is not greater than `1.0`.
+    Factor(f64),
+    /// The confidence level is not between `0.0` and `1.0` (exclusive).
+    Confidence(f64),
+    /// The quantile of the quantile regression estimator is not between `0.0`
+    /// and `1.0` (exclusive).
+    Quantile(f64),
+    /// The maximum amount of time benchmarks will run for is zero.
+    Time,
+    /// The maximum amount of time a single sample may take is zero.
+    Deadline,
+    /// The maximum amount of memory benchmarks will allocate is zero.
+    Memory,
+    /// The maximum number of iterations in a sample is zero.
+    MaxIterations,
+    /// The maximum number of samples is zero.
+    MaxSamples,
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::Factor(factor) => {
+                write!(f, "growth factor must be greater than 1.0 (was {})", factor)
+            },
+            OptionsError::Confidence(confidence) => {
+                write!(f, "confidence must be between 0.0 and 1.0 (was {})", confidence)
+            },
+            OptionsError::Quantile(quantile) => {
+                write!(f, "quantile must be between 0.0 and 1.0 (was {})", quantile)
+            },
+            OptionsError::Time => write!(f, "time must be greater than zero"),
+            OptionsError::Deadline => write!(f, "deadline must be greater than zero"),
+            OptionsError::Memory => write!(f, "memory must be greater than zero"),
+            OptionsError::MaxIterations => write!(f, "maximum iterations must be greater than zero"),
+            OptionsError::MaxSamples => write!(f, "maximum samples must be greater than zero"),
+        }
+    }
+}
+
+impl error::Error for OptionsError { }
+
+/// Overrides for the default benchmarking options read from environment
+/// variables.
+#[derive(Copy, Clone, Debug, Default)]
+struct Environment {
+    time: Option<Duration>,
+    warmup: Option<Duration>,
+    factor: Option<f64>,
+    memory: Option<Bytes>,
+    min_samples: Option<usize>,
+    format: Option<Format>,
+    dry_run: Option<bool>,
+}
+
+impl Environment {
+    /// Returns the supplied options with these overrides applied.
+    fn apply(&self, mut options: Options) -> Options {
+        if let Some(time) = self.time { options = options.time(time); }
+        if let Some(warmup) = self.warmup { options = options.warmup(warmup); }
+        if let Some(factor) = self.factor { options = options.factor(factor); }
+        if let Some(memory) = self.memory { options = options.memory(memory); }
+        if let Some(min_samples) = self.min_samples { options = options.min_samples(min_samples); }
+        if let Some(format) = self.format { options = options.format(format); }
+        if let Some(dry_run) = self.dry_run { options = options.dry_run(dry_run); }
+        options
+    }
+}
+
+/// Returns the overrides for the default benchmarking options read from
+/// environment variables (which are only read once).
+fn environment() -> &'static Environment {
+    fn read<T>(name: &str, parse: impl FnOnce(&str) -> Option<T>) -> Option<T> {
+        let value = env::var(name).ok()?;
+        let parsed = parse(value.trim());
+        if parsed.is_none() {
+            eprintln!("warning: ignoring invalid value for `{}`: `{}`", name, value);
+        }
+        parsed
+    }
+
+    static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+    ENVIRONMENT.get_or_init(|| Environment {
+        time: read("MICROBENCH_TIME", time::parse_duration),
+        warmup: read("MICROBENCH_WARMUP", time::parse_duration),
+        factor: read("MICROBENCH_FACTOR", |v| v.parse().ok().filter(|f: &f64| *f > 1.0)),
+        memory: read("MICROBENCH_MEMORY", |v| v.parse().ok()),
+        min_samples: read("MICROBENCH_MIN_SAMPLES", |v| v.parse().ok()),
+        format: read("MICROBENCH_FORMAT", Format::parse),
+        dry_run: read("MICROBENCH_DRY_RUN", |v| match v {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => None,
+        }),
+    })
+}
+
+/// An asynchronous runtime that can run futures to completion.
+///
+/// This trait is implemented for `tokio::runtime::Runtime` when the `tokio`
+/// crate feature is enabled.
+pub trait Executor {
+    /// Runs the supplied future to completion on the current thread and
+    /// returns its output.
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output;
+}
+
+#[cfg(feature="tokio")]
+impl Executor for tokio::runtime::Runtime {
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        tokio::runtime::Runtime::block_on(self, future)
+    }
+}
+
+/// A timer that can be paused to exclude work from measurements.
+///
+/// See [`measure_timer`](fn.measure_timer.html) for more information.
+#[derive(Copy, Clone, Debug)]
+pub struct Timer {
+    clock: Clock,
+    elapsed: u64,
+    stopwatch: Option<Stopwatch>,
+}
+
+impl Timer {
+    /// Pauses this timer.
+    ///
+    /// Any work performed while this timer is paused is not included in the
+    /// measurements. Pausing a paused timer has no effect.
+    pub fn pause(&mut self) {
+        if let Some(stopwatch) = self.stopwatch.take() {
+            self.elapsed += stopwatch.elapsed().0;
+        }
+    }
+
+    /// Resumes this timer.
+    ///
+    /// Resuming a running timer has no effect.
+    pub fn resume(&mut self) {
+        if self.stopwatch.is_none() {
+            self.stopwatch = Some(Stopwatch::new(self.clock));
+        }
+    }
+}
+
+/// The reason a benchmark was skipped.
+///
+/// See [`skip`](fn.skip.html) for more information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkipReason(pub String);
+
+impl SkipReason {
+    /// Constructs a new `SkipReason`.
+    pub fn new(reason: impl Into<String>) -> Self {
+        SkipReason(reason.into())
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A statistical analysis of a set of execution time samples.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct Analysis {
+    /// The y-intercept of the simple linear regression model function.
+    pub alpha: Nanoseconds<f64>,
+    /// The slope of the simple linear regression model function.
+    pub beta: Nanoseconds<f64>,
+    /// The goodness of fit of the simple linear regression model function.
+    pub r2: f64,
+    /// The standard error of the slope of the simple linear regression model
+    /// function.
+    pub error: Nanoseconds<f64>,
+    /// The residual degrees of freedom of the simple linear regression model
+    /// function.
+    pub dof: f64,
+    /// The standard deviation of the residuals of the simple linear regression
+    /// model function.
+    pub deviation: Nanoseconds<f64>,
+    /// The mean number of iterations of the samples.
+    pub iterations: f64,
+    /// The sum of the squared deviations of the numbers of iterations of the
+    /// samples from their mean.
+    pub sxx: f64,
+    /// The skewness of the per-iteration execution times of the samples.
+    pub skewness: f64,
+    /// The excess kurtosis of the per-iteration execution times of the samples.
+    pub kurtosis: f64,
+    /// The lag-1 autocorrelation of the per-iteration residuals of the simple
+    /// linear regression model function (in sample collection order).
+    pub autocorrelation: f64,
+}
+
+impl Analysis {
+    /// Returns a new analysis for the supplied samples.
+    ///
+    /// This is the same analysis that is printed by the benchmarking functions
+    /// (e.g., [`bench`](fn.bench.html)) and can be used to analyze the samples
+    /// returned by the measuring functions (e.g., [`measure`](fn.measure.html)).
+    pub fn new(samples: &[Sample]) -> Self {
+        let model = samples.iter()
+            .map(|m| (m.iterations as f64, m.elapsed.0 as f64))
+            .collect::<Model>();
+        let Model { alpha, beta, r2, error, dof, deviation, xmean, sxx } = model;
+        let Moments { skewness, kurtosis, .. } = samples.iter()
+            .map(|m| m.elapsed.0 as f64 / m.iterations as f64)
+            .collect::<Moments>();
+        let residuals = samples.iter()
+            .map(|m| {
+                let iterations = m.iterations as f64;
+                (m.elapsed.0 as f64 - (alpha + (beta * iterations))) / iterations
+            })
+            .collect::<Vec<_>>();
+        let autocorrelation = statistics::autocorrelation(&residuals, 1);
+        Self {
+            alpha: Nanoseconds(alpha),
+            beta: Nanoseconds(beta),
+            r2,
+            error: Nanoseconds(error),
+            dof,
+            deviation: Nanoseconds(deviation),
+            iterations: xmean,
+            sxx,
+            skewness,
+            kurtosis,
+            autocorrelation,
+        }
+    }
+
+    /// Returns the half-width of the confidence interval around the estimated
+    /// execution time (`beta`) with the supplied confidence level (e.g.,
+    /// `0.95`).
+    ///
+    /// See [`Model::interval`](statistics/struct.Model.html#method.interval)
+    /// for more information. Returns `None` if there are fewer than three
+    /// samples.
+    pub fn interval(&self, level: f64) -> Option<Nanoseconds<f64>> {
+        self.model().interval(level).map(Nanoseconds)
+    }
+
+    /// Returns the half-width of the prediction interval around the estimated
+    /// execution time (`beta`) with the supplied confidence level (e.g.,
+    /// `0.95`).
+    ///
+    /// The interval contains the per-iteration execution time of a new sample
+    /// with the mean number of iterations with the supplied probability. See
+    /// [`Model::prediction`](statistics/struct.Model.html#method.prediction)
+    /// for more information. Returns `None` if there are fewer than three
+    /// samples.
+    pub fn prediction(&self, level: f64) -> Option<Nanoseconds<f64>> {
+        let prediction = self.model().prediction(self.iterations, level)?;
+        Some(Nanoseconds(prediction / self.iterations))
+    }
+
+    /// Returns the simple linear regression model function of this analysis.
+    fn model(&self) -> Model {
+        Model {
+            alpha: self.alpha.0,
+            beta: self.beta.0,
+            r2: self.r2,
+            error: self.error.0,
+            dof: self.dof,
+            deviation: self.deviation.0,
+            xmean: self.iterations,
+            sxx: self.sxx,
+        }
+    }
+
+    /// Returns warnings about potential problems with this analysis.
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.skewness > SKEWNESS {
+            warnings.push(format!(
+                "per-iteration times are heavily skewed (skewness {:.3}), the estimate \
+                 may not reflect typical performance",
+                self.skewness,
+            ));
+        }
+        if self.autocorrelation > AUTOCORRELATION {
+            warnings.push(format!(
+                "residuals are highly autocorrelated ({:.3}), the execution time may have \
+                 drifted during the run",
+                self.autocorrelation,
+            ));
+        }
+        warnings
+    }
+}
+
+/// A benchmark registered with the [`microbench`](attr.microbench.html)
+/// attribute.
+#[cfg(feature="macros")]
+#[derive(Copy, Clone, Debug)]
+pub struct Benchmark {
+    /// The name of the benchmark (the name of the annotated function).
+    pub name: &'static str,
+    /// The path of the module that contains the benchmark.
+    pub module: &'static str,
+    pub(crate) function: fn(&Options, &str),
+}
+
+#[cfg(feature="macros")]
+impl Benchmark {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, module: &'static str, function: fn(&Options, &str)) -> Self {
+        Benchmark { name, module, function }
+    }
+
+    /// Benchmarks this benchmark and prints the results.
+    pub fn run(&self, options: &Options) {
+        (self.function)(options, self.name);
+    }
+}
+
+#[cfg(feature="macros")]
+inventory::collect!(Benchmark);
+
+/// Benchmarks the supplied function and prints the results.
+pub fn bench<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
+    bench_impl(options, name, move || measure_with_source_impl(options, options.clock, f));
+}
+
+/// Benchmarks the supplied function using the supplied source of time and
+/// prints the results.
+///
+/// See [`measure_with_source`](fn.measure_with_source.html) for more
+/// information.
+pub fn bench_with_source<T>(
+    options: &Options, name: &str, source: impl TimeSource, f: impl FnMut() -> T
+) {
+    bench_impl(options, name, move || measure_with_source_impl(options, source, f));
+}
+
+/// Benchmarks the supplied functions and prints the results and the speedup of
+/// the second function relative to the first function.
+///
+/// The speedup is accompanied by the probability that the second function is
+/// actually faster (or slower) than the first function and a
+/// [`Verdict`](enum.Verdict.html) backed by Welch's t-test of the difference
+/// between the functions at the configured confidence level (see
+/// [`Options::confidence`](struct.Options.html#method.confidence)).
+pub fn bench_compare<T, U>(
+    options: &Options,
+    a: &str,
+    f: impl FnMut() -> T,
+    b: &str,
+    g: impl FnMut() -> U,
+) {
+    if options.interleave {
+        let stopwatch = Stopwatch::default();
+        let (asamples, bsamples) = measure_interleaved(options, f, g);
+        let elapsed = stopwatch.elapsed();
+        let a = (a, report_impl(options, a, elapsed, asamples, &[]));
+        let b = (b, report_impl(options, b, elapsed, bsamples, &[]));
+        exit_if_interrupted(options);
+        compare_impl(options, a, b);
+    } else {
+        let a = (a, bench_impl(options, a, || measure_with_source_impl(options, options.clock, f)));
+        let b = (b, bench_impl(options, b, || measure_with_source_impl(options, options.clock, g)));
+        compare_impl(options, a, b);
+    }
+}
+
+/// Benchmarks the supplied function ignoring drop time and prints the results.
+///
+/// See [`measure_drop`](fn.measure_drop.html) for more information.
+pub fn bench_drop<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
+    bench_impl(options, name, move || measure_drop_impl(options, f));
+}
+
+/// Benchmarks the supplied function ignoring setup time and prints the results.
+///
+/// See [`measure_setup`](fn.measure_setup.html) for more information.
+pub fn bench_setup<I, T>(
+    options: &Options,
+    name: &str,
+    setup: impl FnMut() -> I,
+    f: impl FnMut(I) -> T,
+) {
+    bench_impl(options, name, move || measure_setup_impl(options, setup, f));
+}
+
+/// Benchmarks the supplied function ignoring setup time and prints the results.
+///
+/// See [`measure_setup_ref`](fn.measure_setup_ref.html) for more information.
+pub fn bench_setup_ref<I, T>(
+    options: &Options,
+    name: &str,
+    setup: impl FnMut() -> I,
+    f: impl FnMut(&mut I) -> T,
+) {
+    bench_impl(options, name, move || measure_setup_ref_impl(options, setup, f));
+}
+
+/// Benchmarks the supplied function ignoring teardown time and prints the
+/// results.
+///
+/// See [`measure_teardown`](fn.measure_teardown.html) for more information.
+pub fn bench_teardown<T>(
+    options: &Options,
+    name: &str,
+    f: impl FnMut() -> T,
+    teardown: impl FnMut(T),
+) {
+    bench_impl(options, name, move || measure_teardown_impl(options, f, teardown));
+}
+
+/// Benchmarks the supplied asynchronous function and prints the results.
+///
+/// See [`measure_async`](fn.measure_async.html) for more information.
+pub fn bench_async<F: Future>(
+    options: &Options,
+    name: &str,
+    executor: &mut impl Executor,
+    f: impl FnMut() -> F,
+) {
+    bench_impl(options, name, move || measure_async_impl(options, executor, f));
+}
+
+/// Benchmarks the supplied function excluding paused time and prints the
+/// results.
+///
+/// See [`measure_timer`](fn.measure_timer.html) for more information.
+pub fn bench_timer<T>(options: &Options, name: &str, f: impl FnMut(&mut Timer) -> T) {
+    bench_impl(options, name, move || measure_timer_impl(options, f));
+}
+
+/// Benchmarks the supplied function on multiple threads concurrently and prints
+/// the results.
+///
+/// See [`measure_concurrent`](fn.measure_concurrent.html) for more information.
+pub fn bench_concurrent<T>(options: &Options, name: &str, f: impl Fn() -> T + Sync) {
+    bench_impl(options, name, move || measure_concurrent_impl(options, f));
+}
+
+/// Benchmarks the supplied custom-timed function and prints the results.
+///
+/// See [`measure_custom`](fn.measure_custom.html) for more information.
+pub fn bench_custom(options: &Options, name: &str, f: impl FnMut(u64) -> Duration) {
+    bench_impl(options, name, move || measure_custom_impl(options, f));
+}
+
+/// Benchmarks the supplied function with each of the supplied inputs and prints
+/// the results.
+///
+/// The results for each input are labeled with the supplied name followed by
+/// the input (e.g., `sort/1000`).
+pub fn bench_with_inputs<I: fmt::Display, T>(
+    options: &Options,
+    name: &str,
+    inputs: &[I],
+    mut f: impl FnMut(&I) -> T,
+) {
+    for input in inputs {
+        let name = format!("{}/{}", name, input);
+        bench_impl(options, &name, || measure_with_source_impl(options, options.clock, || f(input)));
+    }
+}
+
+/// A function benchmarked by [`bench_matrix`](fn.bench_matrix.html).
+pub type MatrixFunction<'f, I, T> = &'f dyn Fn(&I) -> T;
+
+/// Benchmarks each of the supplied functions with each of the supplied inputs
+/// and prints the results followed by a table of the estimated execution times
+/// (with a row per input and a column per function).
+///
+/// The results for each function and input are labeled with the supplied name
+/// followed by the name of the function and the name of the input (e.g.,
+/// `map/btree/1000`). The fastest function for each input is marked with `*` in
+/// the table.
+///
+/// ```no_run
+/// use microbench::{self, Options};
+///
+/// let inputs = [("100", 100), ("10000", 10_000)].map(|(n, len)| {
+///     (n, (0..len).map(|i| (i * 7919) % len).collect::<Vec<u64>>())
+/// });
+///
+/// let options = Options::default();
+/// microbench::bench_matrix(&options, "sort", &[
+///     ("stable", &|v: &Vec<u64>| { let mut v = v.clone(); v.sort(); v }),
+///     ("unstable", &|v: &Vec<u64>| { let mut v = v.clone(); v.sort_unstable(); v }),
+/// ], &inputs);
+/// ```
+pub fn bench_matrix<I, T>(
+    options: &Options,
+    name: &str,
+    functions: &[(&str, MatrixFunction<I, T>)],
+    inputs: &[(&str, I)],
+) {
+    let mut estimates = vec![];
+    for (input, value) in inputs {
+        let row = functions.iter().map(|(function, f)| {
+            let name = format!("{}/{}/{}", name, function, input);
+            let (samples, analysis) = bench_impl(options, &name, || {
+                measure_with_source_impl(options, options.clock, || f(value))
+            });
+            let estimate = match options.estimator {
+                Estimator::Ols => analysis.beta,
+                estimator => estimator.estimate(&samples),
+            };
+            Some(estimate.0).filter(|e| samples.len() >= 2 && *e >= 0.0)
+        });
+        estimates.push(row.collect::<Vec<_>>());
+    }
+
+    if options.dry_run || options.profile.is_some() {
+        return;
+    }
+
+    if options.format == Format::Json {
+        let strings = |names: Vec<&str>| names.into_iter().map(json_string).collect::<Vec<_>>().join(",");
+        let rows = estimates.iter().map(|row| {
+            let row = row.iter().map(|e| e.map_or("null".into(), json_number));
+            format!("[{}]", row.collect::<Vec<_>>().join(","))
+        });
+        println!(
+            r#"{{"name":{},"functions":[{}],"inputs":[{}],"estimates":[{}]}}"#,
+            json_string(name),
+            strings(functions.iter().map(|f| f.0).collect()),
+            strings(inputs.iter().map(|i| i.0).collect()),
+            rows.collect::<Vec<_>>().join(","),
+        );
+        return;
+    }
+
+    // The first column contains the input names and the remaining columns
+    // contain the estimated execution times (or `-` if unknown).
+    let header = format!("{} ({}/iter)", name, options.unit);
+    let mut table = vec![];
+    table.push(iter::once(header).chain(functions.iter().map(|f| f.0.to_string())).collect::<Vec<_>>());
+    for ((input, _), row) in inputs.iter().zip(&estimates) {
+        let fastest = row.iter().flatten().cloned().fold(f64::INFINITY, f64::min);
+        let cells = row.iter().map(|e| match e {
+            Some(e) if *e == fastest => format!("*{}", format_time(options, *e)),
+            Some(e) => format_time(options, *e),
+            None => "-".into(),
+        });
+        table.push(iter::once(input.to_string()).chain(cells).collect());
+    }
+
+    let widths = (0..=functions.len())
+        .map(|c| table.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    println!();
+    for row in &table {
+        let mut line = format!("{:<1$}", row[0], widths[0]);
+        for (cell, width) in row.iter().zip(&widths).skip(1) {
+            line.push_str(&format!("  {:>1$}", cell, width));
+        }
+        println!("{}", line);
+    }
+}
+
+/// Benchmarks the supplied function with inputs of each of the supplied sizes
+/// and prints the results along with the estimated complexity of the function.
+///
+/// The results for each input size are labeled with the supplied name followed
+/// by the input size (e.g., `sort/1000`). The setup function is executed once
+/// per input size to produce the input that is passed to every execution of the
+/// supplied function for that input size. The estimated execution times are
+/// then fitted to common complexity classes (`O(1)`, `O(n)`, `O(n log n)`, and
+/// `O(n²)`) and the best fitting complexity class is printed.
+pub fn bench_scaling<I, T>(
+    options: &Options,
+    name: &str,
+    sizes: &[usize],
+    mut setup: impl FnMut(usize) -> I,
+    mut f: impl FnMut(&I) -> T,
+) {
+    let mut data = vec![];
+    for &size in sizes {
+        let input = setup(size);
+        let name = format!("{}/{}", name, size);
+        let (samples, analysis) = bench_impl(options, &name, || {
+            measure_with_source_impl(options, options.clock, || f(&input))
+        });
+        let estimate = match options.estimator {
+            Estimator::Ols => analysis.beta,
+            estimator => estimator.estimate(&samples),
+        };
+        if samples.len() >= 2 && estimate.0 > 0.0 {
+            data.push((size as f64, estimate.0));
+        }
+    }
+
+    if options.dry_run || options.profile.is_some() {
+        return;
+    }
+
+    if data.len() < 2 {
+        note(options, format_args!("{} has unknown complexity (not enough sizes)", name));
+    } else if options.format == Format::Json {
+        let model = data.into_iter().collect::<ComplexityModel>();
+        println!(
+            r#"{{"name":{},"complexity":"{}","coefficient":{},"error":{}}}"#,
+            json_string(name),
+            model.complexity,
+            json_number(model.coefficient),
+            json_number(model.error),
+        );
+    } else {
+        let model = data.into_iter().collect::<ComplexityModel>();
+        let coefficient = format_time(options, model.coefficient);
+        println!(
+            "{} is {} (~{} {} per unit, {:.1}% error)",
+            name, model.complexity, coefficient, options.unit, model.error * 100.0,
+        );
+    }
+}
+
+/// Benchmarks each call of the supplied function and prints the results.
+///
+/// See [`measure_calls`](fn.measure_calls.html) for more information.
+pub fn bench_calls<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
+    let stopwatch = Stopwatch::default();
+    let mut latencies = measure_calls(options, f).iter()
+        .map(|l| l.0 as f64)
+        .collect::<Vec<_>>();
+    let elapsed = stopwatch.elapsed();
+    latencies.sort_by(f64::total_cmp);
+    if options.dry_run {
+        dry_run_impl(options, name);
+        return exit_if_interrupted(options);
+    } else if options.profile.is_some() {
+        profiled_impl(options, name, elapsed);
+        return exit_if_interrupted(options);
+    }
+
+    let prefix = format!("{} ({}) ...", label(options, name), elapsed);
+    if options.format == Format::Json {
+        let quantile = |q| if latencies.is_empty() {
+            "null".into()
+        } else {
+            json_number(statistics::quantile(&latencies, q))
+        };
+        println!(
+            r#"{{"name":{},"elapsed":{},"calls":{},"mean":{},"p50":{},"p90":{},"p99":{}}}"#,
+            json_string(name),
+            elapsed.0,
+            latencies.len(),
+            json_number(latencies.iter().cloned().kahan_mean()),
+            quantile(0.5),
+            quantile(0.9),
+            quantile(0.99),
+        );
+    } else if latencies.is_empty() {
+        println!("{:<2$} {:>15}", prefix, "           not enough samples", options.width);
+    } else {
+        let time = |n| format_time(options, n);
+        let mean = time(latencies.iter().cloned().kahan_mean());
+        let quantile = |q| time(statistics::quantile(&latencies, q));
+        println!(
+            "{:<6$} {:>15} {}/call (p50: {}, p90: {}, p99: {})",
+            prefix, mean, options.unit, quantile(0.5), quantile(0.9), quantile(0.99), options.width,
+        );
+    }
+
+    exit_if_interrupted(options);
+}
+
+/// Prints that the benchmark with the supplied name was skipped for the
+/// supplied reason.
+///
+/// Skipped benchmarks are not recorded in baselines or history. This is
+/// useful for benchmarks that cannot be run on every machine (e.g., because
+/// they require hardware that is not present).
+///
+/// ```no_run
+/// use microbench::{Options, SkipReason, bench, skip};
+///
+/// let options = Options::default();
+/// if cfg!(target_feature="avx2") {
+///     bench(&options, "avx2", || { });
+/// } else {
+///     skip(&options, "avx2", &SkipReason::new("AVX2 not supported"));
+/// }
+/// ```
+pub fn skip(options: &Options, name: &str, reason: &SkipReason) {
+    if options.format == Format::Json {
+        println!(
+            r#"{{"name":{},"skipped":{},"tags":{}}}"#,
+            json_string(name),
+            json_string(&reason.0),
+            metadata::to_json(&metadata::current()),
+        );
+    } else {
+        let prefix = format!("{} ...", label(options, name));
+        println!("{:<2$} skipped ({})", prefix, reason, options.width);
+    }
+}
+
+/// Benchmarks all of the functions registered with the
+/// [`microbench`](attr.microbench.html) attribute and prints the results.
+///
+/// The benchmarks are run in order of module path and then name.
+#[cfg(feature="macros")]
+pub fn run_all(options: &Options) {
+    let mut benchmarks = inventory::iter::<Benchmark>.into_iter().collect::<Vec<_>>();
+    benchmarks.sort_by_key(|b| (b.module, b.name));
+    for benchmark in benchmarks {
+        benchmark.run(options);
+    }
+}
+
+/// Measures the execution time of the supplied function.
+pub fn measure<T>(
+    options: &Options, f: impl FnMut() -> T
+) -> Vec<Sample> {
+    measure_with_source(options, options.clock, f)
+}
+
+/// Measures the execution time of the supplied function using the supplied
+/// source of time instead of the clock in the benchmarking options.
+///
+/// See [`TimeSource`](time/trait.TimeSource.html) for more information.
+pub fn measure_with_source<T>(
+    options: &Options, source: impl TimeSource, f: impl FnMut() -> T
+) -> Vec<Sample> {
+    measure_with_source_impl(options, source, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_with_source`](fn.measure_with_source.html) and also returns the
+/// number of retries.
+pub(crate) fn measure_with_source_impl<T>(
+    options: &Options, source: impl TimeSource, mut f: impl FnMut() -> T
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, source.resolution(), |iterations| {
+        let stopwatch = Stopwatch::new(|| source.now());
+        for _ in 0..iterations { retain_ref(&f()); }
+        Some(stopwatch.elapsed())
+    })
+}
+
+/// Measures the execution time of the supplied function and passes each sample
+/// to the supplied callback as it is collected.
+///
+/// The callback is also passed the attempt that collected the sample: `0` for
+/// the first set of samples and `n` for the `n`th recollection of unstable
+/// samples (see [`Options::retries`](struct.Options.html#method.retries)).
+/// Every sample is passed to the callback even though only the most stable set
+/// of samples is returned, so the samples of an attempt should be discarded by
+/// the callback when the next attempt starts if only the returned samples are
+/// of interest.
+///
+/// The callback can return `ControlFlow::Break(())` to stop collecting samples
+/// early (the sample passed to the callback is still included in the samples of
+/// its attempt). This can be useful for displaying progress or for stopping
+/// benchmarks based on external conditions.
+pub fn measure_with<T>(
+    options: &Options,
+    mut f: impl FnMut() -> T,
+    callback: impl FnMut(&Sample, u32) -> ControlFlow<()>,
+) -> Vec<Sample> {
+    let (samples, _) = sample_impl(options, options.clock.resolution(), |iterations| {
+        let stopwatch = Stopwatch::new(options.clock);
+        for _ in 0..iterations { retain_ref(&f()); }
+        Some((stopwatch.elapsed(), ()))
+    }, callback);
+    samples.into_iter().map(|(s, _)| s).collect()
+}
+
+/// Measures the execution times of the supplied functions in alternation.
+///
+/// This function collects samples for both of the supplied functions within the
+/// same time window, executing the first function for a number of iterations,
+/// then the second function for the same number of iterations, and so on. This
+/// ensures that slow changes in the environment (e.g., thermal throttling or
+/// other processes starting or stopping) affect the measurements for both
+/// functions equally instead of penalizing whichever function is measured
+/// second. The time budget is shared between the two functions.
+pub fn measure_interleaved<T, U>(
+    options: &Options,
+    mut f: impl FnMut() -> T,
+    mut g: impl FnMut() -> U,
+) -> (Vec<Sample>, Vec<Sample>) {
+    let (samples, _) = sample_impl(options, options.clock.resolution(), |iterations| {
+        let stopwatch = Stopwatch::new(options.clock);
+        for _ in 0..iterations { retain_ref(&f()); }
+        let elapsed = stopwatch.elapsed();
+        let stopwatch = Stopwatch::new(options.clock);
+        for _ in 0..iterations { retain_ref(&g()); }
+        Some((elapsed, Sample { iterations, elapsed: stopwatch.elapsed() }))
+    }, |_, _| ControlFlow::Continue(()));
+    samples.into_iter().unzip()
+}
+
+/// Measures the execution time of the supplied function ignoring drop time.
+///
+/// This function does not include the time it takes to drop the values returned
+/// by the supplied function in the measurements. This can be useful when you
+/// want to exclude the running time of a slow implementation of `Drop` from
+/// your benchmark. However, it should be noted that this function introduces a
+/// very small amount of overhead which will be reflected in the measurements
+/// (typically of the order of a few nanoseconds).
+///
+/// The values returned by the supplied function are stored in a reused buffer
+/// with room for a fixed number of values and are dropped (untimed) whenever the
+/// buffer fills up. The `memory` option controls the maximum amount of memory
+/// this buffer is allowed to occupy.
+pub fn measure_drop<T>(
+    options: &Options, f: impl FnMut() -> T
+) -> Vec<Sample> {
+    measure_drop_impl(options, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_drop`](fn.measure_drop.html) and also returns the number of
+/// retries.
+pub(crate) fn measure_drop_impl<T>(
+    options: &Options, mut f: impl FnMut() -> T
+) -> (Vec<Sample>, u32) {
+    let size = cmp::max(1, mem::size_of::<T>() as u64);
+    let capacity = (options.memory.0 / size).clamp(1, DROP_CAPACITY);
+    debug!("dropping outputs in chunks of {} (memory limit: {})", capacity, options.memory);
+    let mut outputs = Vec::new();
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let mut elapsed = 0;
+        let mut remaining = iterations;
+        while remaining != 0 {
+            let chunk = cmp::min(remaining, capacity);
+            outputs.reserve(chunk as usize);
+            let stopwatch = Stopwatch::new(options.clock);
+            for _ in 0..chunk { outputs.push(f()); }
+            elapsed += stopwatch.elapsed().0;
+            outputs.clear();
+            remaining -= chunk;
+        }
+        Some(Nanoseconds(elapsed))
+    })
+}
+
+/// Measures the execution time of the supplied function ignoring setup time.
+///
+/// This function does not include the time it takes to execute the setup
+/// function in the measurements. This can be useful when you want to exclude
+/// the running time of some non-trivial setup which is needed for every
+/// execution of the supplied function. However, it should be noted that this
+/// function introduces a very small amount of overhead which will be reflected
+/// in the measurements (typically of the order of a few nanoseconds).
+///
+/// **Warning:** This function can potentially allocate very large amounts of
+/// memory. The `memory` option controls the maximum amount of memory this
+/// function is allowed to allocate.
+pub fn measure_setup<I, T>(
+    options: &Options,
+    setup: impl FnMut() -> I,
+    f: impl FnMut(I) -> T,
+) -> Vec<Sample> {
+    measure_setup_impl(options, setup, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_setup`](fn.measure_setup.html) and also returns the number of
+/// retries.
+pub(crate) fn measure_setup_impl<I, T>(
+    options: &Options,
+    mut setup: impl FnMut() -> I,
+    mut f: impl FnMut(I) -> T,
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let size = cmp::max(1, mem::size_of::<I>() as u64);
+        if options.memory < Bytes(iterations * size) {
+            return None;
+        }
+
+        let inputs = retain((0..iterations).map(|_| setup()).collect::<Vec<_>>());
+        let stopwatch = Stopwatch::new(options.clock);
+        for input in inputs { retain_ref(&f(input)); }
+        Some(stopwatch.elapsed())
+    })
+}
+
+/// Measures the execution time of the supplied function ignoring setup time.
+///
+/// Unlike [`measure_setup`](fn.measure_setup.html), this function executes the
+/// setup function only once per sample and passes a mutable reference to the
+/// same input to every execution of the supplied function in that sample. This
+/// avoids allocating and moving an input for every execution of the supplied
+/// function but means that the supplied function should leave the input in a
+/// state that is suitable for subsequent executions.
+pub fn measure_setup_ref<I, T>(
+    options: &Options,
+    setup: impl FnMut() -> I,
+    f: impl FnMut(&mut I) -> T,
+) -> Vec<Sample> {
+    measure_setup_ref_impl(options, setup, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_setup_ref`](fn.measure_setup_ref.html) and also returns the number
+/// of retries.
+pub(crate) fn measure_setup_ref_impl<I, T>(
+    options: &Options,
+    mut setup: impl FnMut() -> I,
+    mut f: impl FnMut(&mut I) -> T,
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let mut input = setup();
+        let stopwatch = Stopwatch::new(options.clock);
+        for _ in 0..iterations { retain_ref(&f(&mut input)); }
+        let elapsed = stopwatch.elapsed();
+        mem::drop(input);
+        Some(elapsed)
+    })
+}
+
+/// Measures the execution time of the supplied function ignoring teardown time.
+///
+/// This function passes each value returned by the supplied function to the
+/// teardown function and does not include the time it takes to execute the
+/// teardown function in the measurements. This can be useful when every
+/// execution of the supplied function needs to be cleaned up (e.g., deleting
+/// temporary files or resetting global state) before the next execution.
+/// However, it should be noted that this function times each execution of the
+/// supplied function individually which introduces a small amount of overhead
+/// which will be reflected in the measurements (typically of the order of a few
+/// tens of nanoseconds).
+pub fn measure_teardown<T>(
+    options: &Options,
+    f: impl FnMut() -> T,
+    teardown: impl FnMut(T),
+) -> Vec<Sample> {
+    measure_teardown_impl(options, f, teardown).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_teardown`](fn.measure_teardown.html) and also returns the number
+/// of retries.
+pub(crate) fn measure_teardown_impl<T>(
+    options: &Options,
+    mut f: impl FnMut() -> T,
+    mut teardown: impl FnMut(T),
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let mut elapsed = 0;
+        for _ in 0..iterations {
+            let stopwatch = Stopwatch::new(options.clock);
+            let output = retain(f());
+            elapsed += stopwatch.elapsed().0;
+            teardown(output);
+        }
+        Some(Nanoseconds(elapsed))
+    })
+}
+
+/// Measures the execution time of the supplied function excluding paused time.
+///
+/// The supplied function is passed a timer which can be paused and resumed to
+/// exclude work in the middle of each execution of the supplied function from
+/// the measurements (e.g., shuffling an input in place). The timer is resumed
+/// (if necessary) before each execution of the supplied function. However, it
+/// should be noted that pausing and resuming the timer introduces a small
+/// amount of overhead which will be reflected in the measurements (typically
+/// of the order of a few tens of nanoseconds).
+pub fn measure_timer<T>(
+    options: &Options, f: impl FnMut(&mut Timer) -> T
+) -> Vec<Sample> {
+    measure_timer_impl(options, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_timer`](fn.measure_timer.html) and also returns the number of
+/// retries.
+pub(crate) fn measure_timer_impl<T>(
+    options: &Options, mut f: impl FnMut(&mut Timer) -> T
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let stopwatch = Some(Stopwatch::new(options.clock));
+        let mut timer = Timer { clock: options.clock, elapsed: 0, stopwatch };
+        for _ in 0..iterations {
+            timer.resume();
+            retain_ref(&f(&mut timer));
+        }
+        timer.pause();
+        Some(Nanoseconds(timer.elapsed))
+    })
+}
+
+/// Measures the execution time of the supplied function on multiple threads
+/// concurrently.
+///
+/// For each sample, the `threads` option controls how many threads execute the
+/// supplied function concurrently (each for the number of iterations of the
+/// sample). The elapsed time of a sample is the elapsed time of the slowest
+/// thread so the measurements reflect the execution time of the supplied
+/// function under contention (e.g., when benchmarking concurrent data
+/// structures). However, it should be noted that the threads are spawned for
+/// every sample and may not all start executing the supplied function at
+/// exactly the same time.
+pub fn measure_concurrent<T>(
+    options: &Options, f: impl Fn() -> T + Sync
+) -> Vec<Sample> {
+    measure_concurrent_impl(options, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_concurrent`](fn.measure_concurrent.html) and also returns the
+/// number of retries.
+pub(crate) fn measure_concurrent_impl<T>(
+    options: &Options, f: impl Fn() -> T + Sync
+) -> (Vec<Sample>, u32) {
+    let threads = cmp::max(1, options.threads);
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let (barrier, f) = (&Barrier::new(threads), &f);
+        thread::scope(|scope| {
+            let handles = (0..threads)
+                .map(|_| scope.spawn(move || {
+                    barrier.wait();
+                    let stopwatch = Stopwatch::new(options.clock);
+                    for _ in 0..iterations { retain_ref(&f()); }
+                    stopwatch.elapsed()
+                }))
+                .collect::<Vec<_>>();
+            handles.into_iter().map(|h| h.join().unwrap()).max()
+        })
+    })
+}
+
+/// Measures the execution time reported by the supplied function.
+///
+/// The supplied function is passed the number of iterations to perform and
+/// should return the amount of time it took to perform those iterations as
+/// measured by the function itself. This can be useful when the work being
+/// benchmarked needs to be timed by another clock (e.g., GPU timestamps or
+/// device counters).
+pub fn measure_custom(
+    options: &Options, f: impl FnMut(u64) -> Duration
+) -> Vec<Sample> {
+    measure_custom_impl(options, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_custom`](fn.measure_custom.html) and also returns the number of
+/// retries.
+pub(crate) fn measure_custom_impl(
+    options: &Options, mut f: impl FnMut(u64) -> Duration
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, None, |iterations| Some(f(iterations).into()))
+}
+
+/// Measures the execution time of the supplied asynchronous function.
+///
+/// The futures returned by the supplied function are awaited sequentially
+/// inside a single future per sample which is run to completion by the supplied
+/// executor. This means that the overhead of entering the runtime is incurred
+/// once per sample rather than once per execution of the supplied function.
+pub fn measure_async<F: Future>(
+    options: &Options,
+    executor: &mut impl Executor,
+    f: impl FnMut() -> F,
+) -> Vec<Sample> {
+    measure_async_impl(options, executor, f).0
+}
+
+/// Measures the execution time of the supplied function as described by
+/// [`measure_async`](fn.measure_async.html) and also returns the number of
+/// retries.
+pub(crate) fn measure_async_impl<F: Future>(
+    options: &Options,
+    executor: &mut impl Executor,
+    mut f: impl FnMut() -> F,
+) -> (Vec<Sample>, u32) {
+    measure_impl(options, options.clock.resolution(), |iterations| {
+        let f = &mut f;
+        Some(executor.block_on(async move {
+            let stopwatch = Stopwatch::new(options.clock);
+            for _ in 0..iterations { retain_ref(&f().await); }
+            stopwatch.elapsed()
+        }))
+    })
+}
+
+/// Measures the execution time of each call of the supplied function.
+///
+/// Unlike the other measuring functions, this function times each call of the
+/// supplied function individually instead of timing batches of calls. This
+/// produces a distribution of call latencies rather than samples suitable for
+/// linear regression and should only be used for functions that are slow
+/// enough that the resolution and overhead of the timer are insignificant.
+///
+/// **Warning:** This function can potentially allocate very large amounts of
+/// memory. The `memory` option controls the maximum amount of memory this
+/// function is allowed to allocate.
+pub fn measure_calls<T>(
+    options: &Options, mut f: impl FnMut() -> T
+) -> Vec<Nanoseconds<u64>> {
+    if options.dry_run {
+        let call = Stopwatch::new(options.clock);
+        retain_ref(&f());
+        return vec![call.elapsed()];
+    }
+
+    if let Some(profile) = options.profile {
+        profile_impl(options, profile, |iterations| {
+            for _ in 0..iterations { retain_ref(&f()); }
+            None::<(Nanoseconds<u64>, ())>
+        });
+        return vec![];
+    }
+
+    let size = mem::size_of::<Nanoseconds<u64>>() as u64;
+    let _pinned = options.core.and_then(system::pin_to_core);
+    let _elevated = if options.priority { system::elevate_priority() } else { None };
+    let warmup = Stopwatch::default();
+    while warmup.elapsed() < options.warmup && !system::interrupted() { retain_ref(&f()); }
+    let stopwatch = Stopwatch::default();
+    let mut buffer = options.flush.map(|b| vec![0u8; b.0 as usize]);
+    let mut latencies = vec![];
+    while stopwatch.elapsed() < options.time && options.memory.0 / size > latencies.len() as u64 {
+        if system::interrupted() || options.max_samples.is_some_and(|m| latencies.len() >= m) {
+            break;
+        }
+
+        if let Some(buffer) = &mut buffer { utility::flush(buffer); }
+        let call = Stopwatch::new(options.clock);
+        retain_ref(&f());
+        latencies.push(call.elapsed());
+    }
+    if options.memory.0 / size <= latencies.len() as u64 {
+        debug!("memory limit reached after {} calls ({})", latencies.len(), options.memory);
+    }
+    latencies
+}
+
+/// Prints an analysis of the samples produced by the supplied function.
+///
+/// The supplied function should also return the number of times the samples
+/// were recollected (see
+/// [`Options::retries`](struct.Options.html#method.retries)).
+pub(crate) fn bench_impl(
+    options: &Options, name: &str, f: impl FnOnce() -> (Vec<Sample>, u32)
+) -> (Vec<Sample>, Analysis) {
+    warn_environment(options);
+    let pinned = options.core.map(|c| system::pin_to_core(c).ok_or(c));
+    let elevated = if options.priority { Some(system::elevate_priority()) } else { None };
+    let rss = if options.rss { system::reset_peak_rss(); system::rss() } else { None };
+    let measured = options.throttling && !options.dry_run && options.profile.is_none();
+    let core = if measured { options.core.or_else(system::current_core) } else { None };
+    let monitor = core.map(|c| FrequencyMonitor::start(c, FREQUENCY_INTERVAL));
+    let stopwatch = Stopwatch::default();
+    #[cfg(feature="tracing")]
+    let span = tracing::info_span!("benchmark", name).entered();
+    let (samples, retries) = profiler::scope(name, f);
+    #[cfg(feature="tracing")]
+    span.exit();
+    let elapsed = stopwatch.elapsed();
+    let peak = if options.rss { system::peak_rss() } else { None };
+    let throttling = monitor.and_then(|m| throttling(&m.stop()));
+    let result = report_impl(options, name, elapsed, samples, throttling.as_ref().map_or(&[][..], slice::from_ref));
+
+    if retries != 0 {
+        note(options, format_args!("    retried {} time(s) (unstable samples)", retries));
+    }
+
+    if let Some(Err(core)) = pinned {
+        note(options, format_args!("    warning: failed to pin benchmark to CPU core {}", core));
+    }
+
+    if let Some(None) = elevated {
+        note(options, format_args!("    warning: failed to raise priority (insufficient privileges?)"));
+    }
+
+    if let (Some(rss), Some(peak)) = (rss, peak) {
+        let mebibytes = |b: u64| b as f64 / Bytes::mebibytes(1).0 as f64;
+        let delta = mebibytes(peak.0) - mebibytes(rss.0);
+        let (peak, delta) = (mebibytes(peak.0), delta);
+        note(options, format_args!("    peak RSS: {:.1} MiB ({:+.1} MiB)", peak, delta));
+    } else if options.rss {
+        note(options, format_args!("    warning: failed to measure peak RSS (unsupported platform)"));
+    }
+
+    exit_if_interrupted(options);
+    result
+}
+
+/// Returns warnings about problems with the environment the benchmarks are run
+/// in that are likely to make the results misleading or noisy.
+///
+/// See [`system::optimized`](system/fn.optimized.html),
+/// [`system::frequency_scaling`](system/fn.frequency_scaling.html), and
+/// [`system::virtualization`](system/fn.virtualization.html) for more
+/// information.
+fn environment_warnings() -> &'static [String] {
+    ENVIRONMENT.get_or_init(|| {
+        let mut warnings = vec![];
+        if !system::optimized() {
+            warnings.push(UNOPTIMIZED.into());
+        }
+        warnings.extend(system::frequency_scaling().iter().map(|s| s.to_string()));
+        warnings.extend(system::virtualization().iter().map(|v| v.to_string()));
+        warnings
+    })
+}
+
+/// Prints the warnings about the environment the benchmarks are run in (only
+/// the first time this is called for a run that is not a dry run or a
+/// profiling run).
+pub(crate) fn warn_environment(options: &Options) {
+    if options.dry_run || options.profile.is_some() {
+        return;
+    }
+
+    ENVIRONMENT_WARNED.call_once(|| {
+        let warnings = environment_warnings();
+        for warning in warnings {
+            note(options, format_args!("warning: {}", warning));
+        }
+
+        if !warnings.is_empty() {
+            note(options, format_args!(""));
+        }
+    });
+}
+
+/// Disables the printing of the warnings about the environment (e.g., for
+/// worker processes whose parent process has already printed them).
+pub(crate) fn suppress_environment_warnings() {
+    ENVIRONMENT_WARNED.call_once(|| { });
+}
+
+/// Returns a warning if the supplied CPU frequencies (sampled while a benchmark
+/// was run) indicate that the CPU was throttled.
+fn throttling(frequencies: &Frequencies) -> Option<String> {
+    const BLENDED: &str = "the estimate may blend throttled and unthrottled performance";
+    if let Some(throttles) = frequencies.throttles.filter(|t| *t != 0) {
+        return Some(format!("CPU was thermally throttled {} time(s) during the run, {}", throttles, BLENDED));
+    }
+
+    let ((first, second), drop) = (frequencies.halves()?, frequencies.drop()?);
+    if drop > FREQUENCY_DROP {
+        Some(format!(
+            "CPU frequency dropped by {:.1}% during the run ({:.0} MHz to {:.0} MHz), {}",
+            drop * 100.0, first, second, BLENDED,
+        ))
+    } else {
+        None
+    }
+}
+
+/// The number of [`Teardown`] guards that have not been dropped yet.
+static TEARDOWNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Exits the process if it has been interrupted.
+///
+/// While a harness (e.g., a group) holds a [`Teardown`] guard, the process is
+/// not exited here. Instead, the harness stops running benchmarks and the
+/// process is exited when the last guard is dropped so that the teardown hooks
+/// are still run.
+fn exit_if_interrupted(options: &Options) {
+    if system::interrupted() {
+        note(options, format_args!("interrupted"));
+        if TEARDOWNS.load(Ordering::SeqCst) == 0 {
+            process::exit(130);
+        }
+    }
+}
+
+/// A guard that runs a teardown hook when dropped (even if a benchmark panics).
+///
+/// If the process has been interrupted, the last of these guards to be dropped
+/// exits the process after running its teardown hook.
+pub(crate) struct Teardown<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Teardown<F> {
+    /// Constructs a new `Teardown` that runs the supplied hook when dropped.
+    pub(crate) fn new(f: F) -> Self {
+        TEARDOWNS.fetch_add(1, Ordering::SeqCst);
+        Teardown(Some(f))
+    }
+}
+
+impl<F: FnOnce()> Drop for Teardown<F> {
+    fn drop(&mut self) {
+        if let Some(f) = self.0.take() {
+            f();
+        }
+
+        let remaining = TEARDOWNS.fetch_sub(1, Ordering::SeqCst) - 1;
+        if remaining == 0 && system::interrupted() && !thread::panicking() {
+            process::exit(130);
+        }
+    }
+}
+
+/// Returns the label for the benchmark with the supplied name.
+///
+/// For benchmarks in groups, this is the name of the benchmark without the
+/// group name (e.g., `group/name`) indented below the group name.
+pub(crate) fn label<'n>(options: &Options, name: &'n str) -> Cow<'n, str> {
+    match name.get(options.prefix..) {
+        Some(label) if options.prefix != 0 => format!("  {}", label).into(),
+        _ => name.into(),
+    }
+}
+
+/// Returns the supplied number formatted for printing.
+pub(crate) fn format_quantity(options: &Options, number: f64) -> String {
+    if options.compact { format_compact(number) } else { format_number(number, 3, '_') }
+}
+
+/// Returns the supplied number of nanoseconds in the unit of time used to print
+/// execution times, formatted for printing.
+fn format_time(options: &Options, nanoseconds: f64) -> String {
+    format_quantity(options, options.unit.convert(nanoseconds))
+}
+
+/// Prints the supplied line of output that is not part of the results of a
+/// benchmark (to standard error for machine-readable formats).
+pub(crate) fn note(options: &Options, line: fmt::Arguments) {
+    match options.format {
+        Format::Pretty => println!("{}", line),
+        Format::Json => eprintln!("{}", line),
+    }
+}
+
+/// Prints an analysis of the supplied samples (and the supplied warnings about
+/// the run that produced them).
+fn report_impl(
+    options: &Options, name: &str, elapsed: Nanoseconds<u64>, samples: Vec<Sample>, run_warnings: &[String],
+) -> (Vec<Sample>, Analysis) {
+    let analysis = Analysis::new(&samples);
+    if options.dry_run {
+        dry_run_impl(options, name);
+        return (samples, analysis);
+    } else if options.profile.is_some() {
+        profiled_impl(options, name, elapsed);
+        return (samples, analysis);
+    }
+
+    let (estimate, details) = match options.estimator {
+        Estimator::Ols => match analysis.prediction(options.confidence) {
+            Some(prediction) => {
+                let prediction = format_time(options, prediction.0);
+                (analysis.beta, format!("± {} ({:.3} R²)", prediction, analysis.r2))
+            },
+            None => (analysis.beta, format!("({:.3} R²)", analysis.r2)),
+        },
+        estimator => (estimator.estimate(&samples), format!("({})", estimator)),
+    };
+
+    let prefix = format!("{} ({}) ...", label(options, name), elapsed);
+    let valid = samples.len() >= 2 && !estimate.0.is_nan() && estimate.0 >= 0.0;
+    let interval = match options.estimator {
+        Estimator::Ols => analysis.interval(options.confidence).unwrap_or(Nanoseconds(f64::NAN)),
+        _ => Nanoseconds(f64::NAN),
+    };
+    let prediction = match options.estimator {
+        Estimator::Ols => analysis.prediction(options.confidence).unwrap_or(Nanoseconds(f64::NAN)),
+        _ => Nanoseconds(f64::NAN),
+    };
+    let (error, dof) = match options.estimator {
+        Estimator::Ols => (analysis.error, analysis.dof),
+        _ => (Nanoseconds(f64::NAN), f64::NAN),
+    };
+    let confidence = options.confidence;
+    let current = baseline::Estimate { estimate, interval, confidence, error, dof };
+    let reference = if valid { baseline::observe(name, current) } else { None };
+
+    if options.format == Format::Json {
+        let mut warnings = analysis.warnings().iter().map(|w| json_string(w)).collect::<Vec<_>>();
+        warnings.extend(run_warnings.iter().chain(environment_warnings()).map(|w| json_string(w)));
+        let reference = match reference {
+            Some(reference) => {
+                let change = current.change(&reference);
+                let (lower, upper) = change.interval(options.confidence);
+                format!(
+                    r#"{{"estimate":{},"ratio":{},"interval":[{},{}],"noise":{},"p":{},"verdict":{}}}"#,
+                    json_number(reference.estimate.0),
+                    json_number(change.ratio),
+                    json_number(lower),
+                    json_number(upper),
+                    change.noise.map_or("null".into(), |n| n.to_string()),
+                    json_number(change.p),
+                    json_string(&change.verdict(options.confidence).to_string()),
+                )
+            },
+            None => "null".into(),
+        };
+        #[cfg(feature="dhat")]
+        let heap = crate::heap::json().map(|h| format!(r#","heap":{}"#, h)).unwrap_or_default();
+        #[cfg(not(feature="dhat"))]
+        let heap = "";
+        println!(
+            concat!(
+                r#"{{"name":{},"elapsed":{},"samples":{},"estimator":{},"estimate":{},"#,
+                r#""interval":{},"prediction":{},"r2":{},"baseline":{},"tags":{},"system":{},"#,
+                r#""warnings":[{}]{}}}"#,
+            ),
+            json_string(name),
+            elapsed.0,
+            samples.len(),
+            json_string(&options.estimator.to_string()),
+            if valid { json_number(estimate.0) } else { "null".into() },
+            json_number(interval.0),
+            json_number(prediction.0),
+            json_number(analysis.r2),
+            reference,
+            metadata::to_json(&metadata::current()),
+            system::info().to_json(),
+            warnings.join(","),
+            heap,
+        );
+    } else if !valid {
+        println!("{:<2$} {:>15}", prefix, "           not enough samples", options.width);
+    } else {
+        let number = format_time(options, estimate.0);
+        let (unit, width) = (options.unit, options.width);
+        let mut line = format!("{:<4$} {:>15} {}/iter {}", prefix, number, unit, details, width);
+        if let (Clock::Cycles, Some(frequency)) = (options.clock, time::cycles_per_nanosecond()) {
+            line.push_str(&format!(" [{:.1} cycles/iter]", estimate.0 * frequency));
+        }
+        if let Some(throughput) = options.throughput {
+            line.push_str(&format!(" [{}]", throughput.rate(estimate)));
+        }
+        println!("{}", line);
+
+        if let Some(reference) = reference {
+            let change = current.change(&reference);
+            println!(
+                "    baseline: {} {}/iter ({:+.2}%{}, {}{})",
+                format_time(options, reference.estimate.0),
+                options.unit,
+                (change.ratio - 1.0) * 100.0,
+                format_change_interval(change.interval(options.confidence)),
+                change.verdict(options.confidence),
+                format_p_value(change.p),
+            );
+        }
+
+        for warning in analysis.warnings().iter().chain(run_warnings) {
+            println!("    warning: {}", warning);
+        }
+    }
+
+    (samples, analysis)
+}
+
+/// Prints that the benchmark with the supplied name ran (as a dry run).
+pub(crate) fn dry_run_impl(options: &Options, name: &str) {
+    if options.format == Format::Json {
+        println!(r#"{{"name":{},"dry_run":true}}"#, json_string(name));
+    } else {
+        let prefix = format!("{} ...", label(options, name));
+        println!("{:<1$} ok (dry run)", prefix, options.width);
+    }
+}
+
+/// Prints that the benchmark with the supplied name was profiled.
+fn profiled_impl(options: &Options, name: &str, elapsed: Nanoseconds<u64>) {
+    let prefix = format!("{} ({}) ...", label(options, name), elapsed);
+    note(options, format_args!("{:<1$} profiled", prefix, options.width));
+}
+
+/// A comparison of the execution times of two functions.
+///
+/// Every statistic is derived from the OLS slopes (regardless of the estimator)
+/// so that the ratio agrees with the statistical test and the verdict.
+#[derive(Copy, Clone, Debug)]
+struct Comparison {
+    ratio: f64,
+    faster: f64,
+    p: f64,
+    verdict: Verdict,
+    relative: f64,
+    interval: (f64, f64),
+    d: f64,
+    within: bool,
+}
+
+impl Comparison {
+    fn new(
+        options: &Options,
+        (asamples, aanalysis): (&[Sample], &Analysis),
+        (bsamples, banalysis): (&[Sample], &Analysis),
+    ) -> Self {
+        let ratio = banalysis.beta.0 / aanalysis.beta.0;
+
+        let data = |samples: &[Sample]| samples.iter()
+            .map(|m| (m.iterations as f64, m.elapsed.0 as f64))
+            .collect::<BayesianModel>();
+        let faster = data(bsamples).beta.probability_less(&data(asamples).beta);
+
+        // Welch's t-test of the difference between the OLS slopes.
+        let distribution = |a: &Analysis| StudentT { location: a.beta.0, scale: a.error.0, dof: a.dof };
+        let difference = distribution(banalysis).difference(&distribution(aanalysis));
+        let p = p_value(&difference);
+        let verdict = Verdict::new(ratio, p, options.confidence);
+
+        // Effect sizes (the relative change and Cohen's d for per-iteration times).
+        let relative = relative_difference(&difference, aanalysis.beta.0);
+        let interval = if p.is_nan() { (f64::NAN, f64::NAN) } else { relative.interval(options.confidence) };
+        let d = effect_size(asamples, bsamples);
+
+        let noise = |a: &Analysis| a.interval(options.confidence).map_or(f64::NAN, |i| i.0);
+        let within = (aanalysis.beta.0 - banalysis.beta.0).abs() <= noise(aanalysis) + noise(banalysis);
+
+        Comparison { ratio, faster, p, verdict, relative: relative.location, interval, d, within }
+    }
+}
+
+/// Prints a comparison of the supplied samples and analyses.
+fn compare_impl(
+    options: &Options,
+    (a, (asamples, aanalysis)): (&str, (Vec<Sample>, Analysis)),
+    (b, (bsamples, banalysis)): (&str, (Vec<Sample>, Analysis)),
+) {
+    if asamples.len() < 2 || bsamples.len() < 2 {
+        return;
+    }
+
+    let comparison = Comparison::new(options, (&asamples, &aanalysis), (&bsamples, &banalysis));
+    let Comparison { ratio, faster, p, verdict, relative, interval, d, within } = comparison;
+    if options.format == Format::Json {
+        println!(
+            concat!(
+                r#"{{"name":{},"baseline":{},"ratio":{},"change":{},"interval":[{},{}],"d":{},"#,
+                r#""faster":{},"noise":{},"p":{},"verdict":{}}}"#,
+            ),
+            json_string(b),
+            json_string(a),
+            json_number(ratio),
+            json_number(relative),
+            json_number(interval.0),
+            json_number(interval.1),
+            json_number(d),
+            json_number(faster),
+            within,
+            json_number(p),
+            json_string(&verdict.to_string()),
+        );
+        return;
+    }
+
+    let (speedup, comparison, probability) = if ratio <= 1.0 {
+        (1.0 / ratio, "faster", faster)
+    } else {
+        (ratio, "slower", 1.0 - faster)
+    };
+
+    let (probability, p) = (probability * 100.0, format_p_value(p));
+    if verdict == Verdict::NoDifference {
+        println!(
+            "{} is not significantly different from {} ({:.3}x {}, {:.1}% probability{})",
+            b, a, speedup, comparison, probability, p,
+        );
+    } else {
+        println!("{} is {:.3}x {} than {} ({:.1}% probability{})", b, speedup, comparison, a, probability, p);
+    }
+
+    if let Some(effect) = format_effect(relative, interval, d) {
+        println!("    effect: {}", effect);
+    }
+}
+
+/// Returns Cohen's d for the per-iteration times of the supplied samples (i.e.,
+/// the effect size of the change from the first samples to the second samples).
+fn effect_size(a: &[Sample], b: &[Sample]) -> f64 {
+    let times = |samples: &[Sample]| samples.iter()
+        .filter(|s| s.iterations != 0)
+        .map(|s| s.elapsed.0 as f64 / s.iterations as f64)
+        .collect::<Vec<_>>();
+    statistics::cohens_d(&times(a), &times(b))
+}
+
+/// Returns the supplied relative change, the interval around it, and Cohen's
+/// d formatted for printing (e.g., `+1.50% [+1.20%, +1.80%], d = 0.62
+/// (medium)`), omitting whichever are unknown, or `None` if all are unknown.
+fn format_effect(relative: f64, interval: (f64, f64), d: f64) -> Option<String> {
+    let mut effects = vec![];
+    if relative.is_finite() {
+        effects.push(format!("{:+.2}%{}", relative * 100.0, format_change_interval(interval)));
+    }
+    if d.is_finite() {
+        effects.push(format!("d = {:.2} ({})", d, effect_magnitude(d)));
+    }
+    if effects.is_empty() { None } else { Some(effects.join(", ")) }
+}
+
+/// Returns the p-value of the supplied distribution of the difference between
+/// two estimates (`NaN` if the distribution is not finite).
+pub(crate) fn p_value(difference: &StudentT) -> f64 {
+    let finite = [difference.location, difference.scale, difference.dof].iter().all(|v| v.is_finite());
+    if finite && difference.scale > 0.0 && difference.dof > 0.0 { difference.p_value(0.0) } else { f64::NAN }
+}
+
+/// Returns the distribution of the supplied difference between two estimates
+/// relative to the supplied baseline estimate.
+///
+/// The uncertainty of the baseline estimate is ignored (i.e., the distribution
+/// is only approximate).
+pub(crate) fn relative_difference(difference: &StudentT, baseline: f64) -> StudentT {
+    let (location, scale) = (difference.location / baseline, difference.scale / baseline.abs());
+    StudentT { location, scale, dof: difference.dof }
+}
+
+/// Returns the supplied interval around a relative change formatted for
+/// printing after the relative change (e.g., ` [+1.20%, +1.80%]`), or nothing
+/// if the interval is unknown.
+fn format_change_interval((lower, upper): (f64, f64)) -> String {
+    if lower.is_finite() && upper.is_finite() {
+        format!(" [{:+.2}%, {:+.2}%]", lower * 100.0, upper * 100.0)
+    } else {
+        String::new()
+    }
+}
+
+/// Returns a description of the magnitude of the supplied Cohen's d (e.g.,
+/// `small` for `0.3`) using the conventional thresholds.
+fn effect_magnitude(d: f64) -> &'static str {
+    match d.abs() {
+        d if d < 0.2 => "negligible",
+        d if d < 0.5 => "small",
+        d if d < 0.8 => "medium",
+        _ => "large",
+    }
+}
+
+/// Returns the supplied p-value formatted for printing after other details
+/// (e.g., `, p = 0.012`), or nothing if the p-value is `NaN`.
+fn format_p_value(p: f64) -> String {
+    if p.is_nan() {
+        String::new()
+    } else if p < 0.001 {
+        ", p < 0.001".into()
+    } else {
+        format!(", p = {:.3}", p)
+    }
+}
+
+/// Collects samples produced by the supplied function which are timed with a
+/// source of time with the supplied resolution (if known).
+///
+/// Also returns the number of times the samples were recollected.
+fn measure_impl(
+    options: &Options,
+    resolution: Option<Nanoseconds<u64>>,
+    mut f: impl FnMut(u64) -> Option<Nanoseconds<u64>>,
+) -> (Vec<Sample>, u32) {
+    let callback = |_: &Sample, _| ControlFlow::Continue(());
+    let (samples, retries) = sample_impl(options, resolution, |i| Some((f(i)?, ())), callback);
+    (samples.into_iter().map(|(s, _)| s).collect(), retries)
+}
+
+/// Collects samples (and accompanying values) produced by the supplied function
+/// and passes each sample (and the attempt that collected it) to the supplied
+/// callback as it is collected.
+///
+/// The samples are timed with a source of time with the supplied resolution (if
+/// known) which is used to avoid samples that are too short to be measured
+/// accurately (see [`TimeSource::resolution`](time/trait.TimeSource.html#method.resolution)).
+///
+/// Also returns the number of times the samples were recollected because they
+/// were unstable (see [`Options::retries`](struct.Options.html#method.retries)).
+pub(crate) fn sample_impl<S>(
+    options: &Options,
+    resolution: Option<Nanoseconds<u64>>,
+    mut f: impl FnMut(u64) -> Option<(Nanoseconds<u64>, S)>,
+    mut callback: impl FnMut(&Sample, u32) -> ControlFlow<()>,
+) -> (Vec<(Sample, S)>, u32) {
+    if options.dry_run {
+        let samples = f(1).map(|(e, v)| vec![(Sample { iterations: 1, elapsed: e }, v)]);
+        return (samples.unwrap_or_default(), 0);
+    }
+
+    if let Some(profile) = options.profile {
+        profile_impl(options, profile, f);
+        return (vec![], 0);
+    }
+
+    let _pinned = options.core.and_then(system::pin_to_core);
+    let _elevated = if options.priority { system::elevate_priority() } else { None };
+    let warmup = Stopwatch::default();
+    GeometricSequence::new(1, options.factor)
+        .end(options.max_iterations)
+        .take_while(|_| warmup.elapsed() < options.warmup)
+        .take_while(|_| !system::interrupted())
+        .for_each(|i| { f(i); });
+
+    let stopwatch = Stopwatch::default();
+    let (mut samples, stopped) = collect_impl(options, resolution, &stopwatch, &mut f, &mut |s| callback(s, 0));
+    if options.retries == 0 || stopped {
+        return (samples, 0);
+    }
+
+    // Recollect the samples until they are stable (keeping the most stable set
+    // of samples) unless the callback stopped collecting samples early.
+    let mut instability = instability_impl(options, &samples);
+    let mut retries = 0;
+    let verification = Stopwatch::default();
+    while retries < options.retries {
+        let remaining = options.verification.map(|v| v.0.saturating_sub(verification.elapsed().0));
+        if instability <= options.stability || remaining == Some(0) || system::interrupted() {
+            break;
+        }
+
+        retries += 1;
+        debug!("recollecting unstable samples (instability: {:.3})", instability);
+        let time = Nanoseconds(remaining.map_or(options.time.0, |r| r.min(options.time.0)));
+        let options = &Options { time, ..*options };
+        let callback = &mut |s: &Sample| callback(s, retries);
+        let (retry, stopped) = collect_impl(options, resolution, &Stopwatch::default(), &mut f, callback);
+        let retried = instability_impl(options, &retry);
+        if retried < instability {
+            samples = retry;
+            instability = retried;
+        }
+        if stopped {
+            break;
+        }
+    }
+    (samples, retries)
+}
+
+/// Executes the supplied function repeatedly for the supplied amount of time
+/// (after waiting for a profiler to be attached).
+fn profile_impl<S>(
+    options: &Options,
+    profile: Nanoseconds<u64>,
+    mut f: impl FnMut(u64) -> Option<(Nanoseconds<u64>, S)>,
+) {
+    note(options, format_args!(
+        "profiling for {} in process {} (starting in {}s)",
+        profile, process::id(), PROFILE_DELAY.as_secs(),
+    ));
+    thread::sleep(PROFILE_DELAY);
+
+    let stopwatch = Stopwatch::default();
+    let mut iterations = 1u64;
+    while stopwatch.elapsed() < profile && !system::interrupted() {
+        let batch = Stopwatch::default();
+        f(iterations);
+        if batch.elapsed().0 < PROFILE_BATCH && iterations < options.max_iterations {
+            iterations *= 2;
+        }
+    }
+}
+
+/// Collects samples of the execution time of the supplied function and passes
+/// each sample to the supplied callback as it is collected.
+///
+/// Also returns whether the callback stopped collecting samples early.
+fn collect_impl<S>(
+    options: &Options,
+    resolution: Option<Nanoseconds<u64>>,
+    stopwatch: &Stopwatch<impl TimeSource>,
+    f: &mut impl FnMut(u64) -> Option<(Nanoseconds<u64>, S)>,
+    callback: &mut impl FnMut(&Sample) -> ControlFlow<()>,
+) -> (Vec<(Sample, S)>, bool) {
+    // Samples that are too short to be measured accurately are discarded until
+    // the first sample that is long enough (the pilot already skips these).
+    let mut minimum = 0;
+    let schedule: Box<dyn Iterator<Item=u64>> = match options.schedule {
+        Schedule::Geometric if options.shuffle => {
+            Box::new(pilot_impl(options, resolution, stopwatch, f).into_iter())
+        },
+        Schedule::Geometric => {
+            minimum = resolution.map_or(0, |r| r.0.saturating_mul(MIN_TICKS));
+            Box::new(GeometricSequence::new(1, options.factor).end(options.max_iterations))
+        },
+        Schedule::Repeat { iterations, count } => Box::new((0..count).map(move |_| iterations)),
+        Schedule::Fixed(iterations) if options.shuffle => {
+            let mut iterations = iterations.to_vec();
+            Random::from_time().shuffle(&mut iterations);
+            Box::new(iterations.into_iter())
+        },
+        Schedule::Fixed(iterations) => Box::new(iterations.iter().cloned()),
+    };
+
+    let mut buffer = options.flush.map(|b| vec![0u8; b.0 as usize]);
+    let mut samples = vec![];
+    let mut rate = None;
+    for iterations in schedule {
+        let expired = stopwatch.elapsed() >= options.time;
+        if expired && samples.len() >= options.min_samples || system::interrupted() {
+            let reason = if system::interrupted() { "interrupted" } else { "time limit reached" };
+            debug!("{} after {} samples", reason, samples.len());
+            break;
+        }
+
+        if options.max_samples.is_some_and(|m| samples.len() >= m) {
+            debug!("maximum number of samples reached ({})", samples.len());
+            break;
+        }
+
+        if let (Some(deadline), Some(rate)) = (options.deadline, rate) {
+            if rate * iterations as f64 > deadline.0 as f64 {
+                debug!("skipped sample of {} iterations (would exceed the deadline)", iterations);
+                continue;
+            }
+        }
+
+        if let Some(buffer) = &mut buffer { utility::flush(buffer); }
+        profiler::start();
+        let result = f(iterations);
+        profiler::stop();
+        if let Some((elapsed, value)) = result {
+            rate = Some(elapsed.0 as f64 / iterations as f64);
+            if options.deadline.is_some_and(|d| elapsed > d) {
+                debug!("discarded sample of {} iterations (exceeded the deadline)", iterations);
+                continue;
+            }
+
+            if elapsed.0 < minimum {
+                debug!("discarded sample of {} iterations (shorter than {} ticks)", iterations, MIN_TICKS);
+                continue;
+            }
+            minimum = 0;
+
+            let sample = Sample { iterations, elapsed };
+            #[cfg(feature="tracing")]
+            tracing::debug!(iterations, elapsed = elapsed.0, "sample");
+            let flow = callback(&sample);
+            samples.push((sample, value));
+            if flow.is_break() {
+                return (samples, true);
+            }
+        } else {
+            debug!("sample of {} iterations aborted", iterations);
+        }
+    }
+    (samples, false)
+}
+
+/// Returns the width of the interval around the estimated execution time of the
+/// supplied samples relative to the estimated execution time (or infinity if
+/// the estimated execution time is not positive).
+fn instability_impl<S>(options: &Options, samples: &[(Sample, S)]) -> f64 {
+    let samples = samples.iter().map(|s| s.0).collect::<Vec<_>>();
+    let analysis = Analysis::new(&samples);
+    let instability = analysis.interval(options.confidence).map_or(f64::NAN, |i| i.0 / analysis.beta.0);
+    if analysis.beta.0 > 0.0 && instability >= 0.0 { instability } else { f64::INFINITY }
+}
+
+/// Returns the shuffled iteration counts of the samples that can be collected
+/// in the remaining time as estimated by executing the supplied function.
+fn pilot_impl<S>(
+    options: &Options,
+    resolution: Option<Nanoseconds<u64>>,
+    stopwatch: &Stopwatch<impl TimeSource>,
+    f: &mut impl FnMut(u64) -> Option<(Nanoseconds<u64>, S)>,
+) -> Vec<u64> {
+    let budget = options.time.0 / 10;
+    let mut rate = None;
+    for i in GeometricSequence::new(1, options.factor).end(options.max_iterations) {
+        if system::interrupted() {
+            return vec![];
+        }
+
+        match f(i) {
+            Some((elapsed, _)) => rate = Some(elapsed.0 as f64 / i as f64),
+            None => break,
+        }
+        if stopwatch.elapsed().0 >= budget {
+            break;
+        }
+    }
+
+    let rate = match rate {
+        Some(rate) => rate.max(f64::MIN_POSITIVE),
+        None => return vec![],
+    };
+
+    let minimum = resolution.map_or(0, |r| r.0.saturating_mul(MIN_TICKS)) as f64;
+    let remaining = options.time.0.saturating_sub(stopwatch.elapsed().0) as f64;
+    let mut total = 0.0;
+    let mut iterations = GeometricSequence::new(1, options.factor)
+        .end(options.max_iterations)
+        .skip_while(|i| rate * (*i as f64) < minimum)
+        .take_while(|i| { total += rate * *i as f64; total <= remaining })
+        .collect::<Vec<_>>();
+    Random::from_time().shuffle(&mut iterations);
+    debug!("pilot estimated {:.3} ns/iter ({} samples planned)", rate, iterations.len());
+    iterations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::{Cell};
+
+    /// A fake source of time that advances by one nanosecond every time it is
+    /// read (so every sample takes one nanosecond).
+    struct Ticks(Cell<u64>, Option<Nanoseconds<u64>>);
+
+    impl TimeSource for Ticks {
+        fn now(&self) -> Nanoseconds<u64> {
+            self.0.set(self.0.get() + 1);
+            Nanoseconds(self.0.get())
+        }
+
+        fn resolution(&self) -> Option<Nanoseconds<u64>> {
+            self.1
+        }
+    }
+
+    /// Collects samples with a fake clock where each sample of `i` iterations
+    /// takes `elapsed(i)` nanoseconds.
+    fn collect(options: &Options, elapsed: impl Fn(u64) -> u64) -> (Vec<Sample>, u64) {
+        let clock = Cell::new(0);
+        let stopwatch = Stopwatch::new(|| Nanoseconds(clock.get()));
+        let mut f = |i| {
+            clock.set(clock.get() + elapsed(i));
+            Some((Nanoseconds(elapsed(i)), ()))
+        };
+        let callback = &mut |_: &Sample| ControlFlow::Continue(());
+        let (samples, _) = collect_impl(options, None, &stopwatch, &mut f, callback);
+        (samples.into_iter().map(|(s, _)| s).collect(), clock.get())
+    }
+
+    fn iterations(samples: &[Sample]) -> Vec<u64> {
+        samples.iter().map(|s| s.iterations).collect()
+    }
+
+    fn samples(data: &[(u64, u64)]) -> Vec<Sample> {
+        data.iter().map(|&(i, e)| Sample { iterations: i, elapsed: Nanoseconds(e) }).collect()
+    }
+
+    #[test]
+    fn test_effect_size() {
+        // Samples with equal iteration counts (for which OLS is undefined).
+        let a = samples(&[(10, 100), (10, 110), (10, 90), (10, 100)]);
+        let b = samples(&[(10, 120), (10, 130), (10, 110), (10, 120)]);
+        let d = effect_size(&a, &b);
+        let expected = 2.0 / (2.0f64 / 3.0).sqrt();
+        assert!((d - expected).abs() < 1e-9, "{} != {}", d, expected);
+        assert!((effect_size(&b, &a) + expected).abs() < 1e-9);
+
+        // Samples from a geometric schedule.
+        let a = samples(&[(1, 12), (2, 21), (4, 40), (8, 81)]);
+        let b = samples(&[(1, 6), (2, 11), (4, 20), (8, 41)]);
+        assert!(effect_size(&a, &b) < -0.8);
+
+        assert!(effect_size(&a, &a[..1]).is_nan());
+    }
+
+    #[test]
+    fn test_analysis_prediction() {
+        let analysis = Analysis::new(&samples(&[(1, 11), (2, 19), (4, 41), (8, 79), (16, 161), (32, 319)]));
+        let model = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0].iter()
+            .zip([11.0, 19.0, 41.0, 79.0, 161.0, 319.0].iter())
+            .map(|(x, y)| (*x, *y))
+            .collect::<Model>();
+        let prediction = analysis.prediction(0.95).unwrap();
+        assert!((prediction.0 - model.prediction(10.5, 0.95).unwrap() / 10.5).abs() < 1e-9);
+        assert!(prediction.0 > analysis.interval(0.95).unwrap().0);
+
+        assert!(Analysis::new(&samples(&[(1, 10), (2, 20)])).prediction(0.95).is_none());
+    }
+
+    #[test]
+    fn test_comparison() {
+        // The minimum per-iteration time of the second samples is lower but the
+        // OLS slope (which the comparison is based on) is higher.
+        let a = samples(&[(1, 11), (2, 19), (4, 41), (8, 79), (16, 161), (32, 319)]);
+        let b = samples(&[(1, 2), (2, 25), (4, 47), (8, 97), (16, 191), (32, 385)]);
+        let (aanalysis, banalysis) = (Analysis::new(&a), Analysis::new(&b));
+        let options = Options::default().estimator(Estimator::Minimum);
+        assert!(Estimator::Minimum.estimate(&b).0 < Estimator::Minimum.estimate(&a).0);
+
+        let comparison = Comparison::new(&options, (&a, &aanalysis), (&b, &banalysis));
+        assert_eq!(comparison.ratio, banalysis.beta.0 / aanalysis.beta.0);
+        assert!(comparison.ratio > 1.0);
+        assert!(comparison.relative > 0.0);
+        assert!(comparison.faster < 0.5);
+        assert_eq!(comparison.verdict, Verdict::Slower);
+    }
+
+    #[test]
+    fn test_format_effect() {
+        let nan = f64::NAN;
+        assert_eq!(format_effect(0.015, (0.012, 0.018), 0.62).as_deref(), Some("+1.50% [+1.20%, +1.80%], d = 0.62 (medium)"));
+        assert_eq!(format_effect(0.015, (nan, nan), nan).as_deref(), Some("+1.50%"));
+        assert_eq!(format_effect(nan, (nan, nan), -1.5).as_deref(), Some("d = -1.50 (large)"));
+        assert_eq!(format_effect(nan, (nan, nan), nan), None);
+    }
+
+    #[test]
+    fn test_resolution() {
+        let options = Options::default().time(Duration::from_millis(10)).max_samples(5);
+
+        // Samples timed with a source of unknown resolution are never too short.
+        let samples = measure_with_source(&options, Ticks(Cell::new(0), None), || { });
+        let iterations = samples.iter().map(|s| s.iterations).collect::<Vec<_>>();
+        assert_eq!(iterations, &[1, 2, 3, 4, 5]);
+        assert!(samples.iter().all(|s| s.elapsed == Nanoseconds(1)));
+
+        // Samples shorter than 10 ticks of the source are discarded.
+        let samples = measure_with_source(&options, Ticks(Cell::new(0), Some(Nanoseconds(1))), || { });
+        assert!(samples.is_empty());
+    }
+
+    #[test]
+    fn test_collect_schedules() {
+        let options = Options::default().time(Duration::from_secs(1));
+
+        let repeat = options.schedule(Schedule::Repeat { iterations: 4, count: 3 });
+        let (samples, elapsed) = collect(&repeat, |i| i);
+        assert_eq!(iterations(&samples), [4, 4, 4]);
+        assert_eq!(elapsed, 12);
+
+        let fixed = options.schedule(Schedule::Fixed(&[1, 5, 3, 5]));
+        assert_eq!(iterations(&collect(&fixed, |i| i).0), [1, 5, 3, 5]);
+        let mut shuffled = iterations(&collect(&fixed.shuffle(true), |i| i).0);
+        shuffled.sort();
+        assert_eq!(shuffled, [1, 3, 5, 5]);
+
+        // The time budget is checked before each sample.
+        let repeat = repeat.time(Duration::from_nanos(10));
+        assert_eq!(iterations(&collect(&repeat, |i| i).0), [4, 4, 4]);
+        let fixed = fixed.time(Duration::from_nanos(6));
+        assert_eq!(iterations(&collect(&fixed, |i| i).0), [1, 5]);
+    }
+
+    #[test]
+    fn test_collect_min_samples() {
+        let schedule = Schedule::Repeat { iterations: 1, count: 100 };
+        let options = Options::default().time(Duration::from_nanos(10)).schedule(schedule);
+
+        // Each sample takes longer than the entire time budget.
+        let (samples, elapsed) = collect(&options.min_samples(0), |_| 100);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(elapsed, 100);
+        let (samples, elapsed) = collect(&options.min_samples(5), |_| 100);
+        assert_eq!(samples.len(), 5);
+        assert_eq!(elapsed, 500);
+
+        // The time is only extended as far as the schedule and maximum allow.
+        assert_eq!(collect(&options.min_samples(5).max_samples(3), |_| 100).0.len(), 3);
+        let fixed = options.schedule(Schedule::Fixed(&[1, 2])).min_samples(5);
+        assert_eq!(iterations(&collect(&fixed, |_| 100).0), [1, 2]);
+    }
+
+    #[test]
+    fn test_collect_deadline() {
+        let schedule = Schedule::Fixed(&[1, 2, 4, 8, 16, 5]);
+        let options = Options::default().time(Duration::from_secs(1)).schedule(schedule);
+        let superlinear = |i: u64| i * i;
+
+        let (samples, elapsed) = collect(&options, superlinear);
+        assert_eq!(iterations(&samples), [1, 2, 4, 8, 16, 5]);
+        assert_eq!(elapsed, 366);
+
+        // Samples predicted to exceed the deadline (8 and 16 iterations at the
+        // last observed rate) are skipped without being executed and samples
+        // that do exceed the deadline (5 iterations) are executed but discarded.
+        let (samples, elapsed) = collect(&options.deadline(Duration::from_nanos(20)), superlinear);
+        assert_eq!(iterations(&samples), [1, 2, 4]);
+        assert!(samples.iter().all(|s| s.elapsed.0 <= 20));
+        assert_eq!(elapsed, 46);
+    }
+
+    #[test]
+    fn test_budgets() {
+        // Every schedule is limited by the time budget.
+        let schedule = Schedule::Repeat { iterations: 1, count: u64::MAX };
+        let options = Options::default().time(Duration::from_millis(10)).schedule(schedule);
+        assert!(!measure(&options, || { }).is_empty());
+
+        // Samples that take one nanosecond are always unstable.
+        let options = Options::default().time(Duration::from_millis(1)).max_samples(5).retries(3);
+        let ticks = || Ticks(Cell::new(0), None);
+        assert_eq!(measure_with_source_impl(&options, ticks(), || { }).1, 3);
+        let options = options.verification(Duration::ZERO);
+        assert_eq!(measure_with_source_impl(&options, ticks(), || { }).1, 0);
+    }
+
+    #[test]
+    fn test_measure_with_attempts() {
+        // Every set of samples is unstable with a maximum instability of zero.
+        let options = Options::default().time(Duration::from_secs(1)).max_samples(5).retries(2).stability(0.0);
+        let mut attempts = vec![vec![]; 3];
+        let samples = measure_with(&options, || thread::sleep(Duration::from_micros(10)), |s, a| {
+            attempts[a as usize].push((s.iterations, s.elapsed));
+            ControlFlow::Continue(())
+        });
+        assert!(attempts.iter().all(|a| a.len() == 5));
+        let samples = samples.iter().map(|s| (s.iterations, s.elapsed)).collect::<Vec<_>>();
+        assert!(attempts.contains(&samples));
+    }
+}
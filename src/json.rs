@@ -0,0 +1,195 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal JSON parser.
+
+use std::iter::{Peekable};
+use std::str::{Chars};
+
+/// A JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Parses the supplied JSON text.
+    pub fn parse(text: &str) -> Result<Value, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(c) => Err(format!("unexpected character `{}`", c)),
+            None => Ok(value),
+        }
+    }
+
+    /// Returns the value of the supplied key if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|e| e.0 == key).map(|e| &e.1),
+            _ => None,
+        }
+    }
+
+    /// Returns this value as a number (`null` is treated as `NaN`).
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Null => Some(f64::NAN),
+            _ => None,
+        }
+    }
+
+    /// Returns this value as a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: &str) -> Result<(), String> {
+    for e in expected.chars() {
+        if chars.next() != Some(e) {
+            return Err(format!("expected `{}`", expected));
+        }
+    }
+    Ok(())
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('n') => expect(chars, "null").map(|_| Value::Null),
+        Some('t') => expect(chars, "true").map(|_| Value::Bool(true)),
+        Some('f') => expect(chars, "false").map(|_| Value::Bool(false)),
+        Some('"') => parse_string(chars).map(Value::String),
+        Some('[') => {
+            chars.next();
+            let mut values = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Value::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Value::Array(values)),
+                    _ => return Err("expected `,` or `]`".into()),
+                }
+            }
+        },
+        Some('{') => {
+            chars.next();
+            let mut entries = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Value::Object(entries));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                expect(chars, ":")?;
+                entries.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Value::Object(entries)),
+                    _ => return Err("expected `,` or `}`".into()),
+                }
+            }
+        },
+        Some(c) if *c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() || "+-.eE".contains(c) {
+                    number.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            number.parse().map(Value::Number).map_err(|_| format!("invalid number `{}`", number))
+        },
+        Some(c) => Err(format!("unexpected character `{}`", c)),
+        None => Err("unexpected end of input".into()),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, "\"")?;
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('"') => string.push('"'),
+                Some('\\') => string.push('\\'),
+                Some('/') => string.push('/'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('u') => {
+                    let hex = (0..4).filter_map(|_| chars.next()).collect::<String>();
+                    let code = u32::from_str_radix(&hex, 16).map_err(|_| "invalid escape")?;
+                    string.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                },
+                _ => return Err("invalid escape".into()),
+            },
+            Some(c) => string.push(c),
+            None => return Err("unterminated string".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = Value::parse(r#" {"a": [1, -2.5e3, null], "b": "x\"A", "c": true} "#);
+        assert_eq!(value, Ok(Value::Object(vec![
+            ("a".into(), Value::Array(vec![
+                Value::Number(1.0), Value::Number(-2500.0), Value::Null,
+            ])),
+            ("b".into(), Value::String("x\"A".into())),
+            ("c".into(), Value::Bool(true)),
+        ])));
+
+        assert!(Value::parse("{").is_err());
+        assert!(Value::parse("[1,]").is_err());
+        assert!(Value::parse("1 2").is_err());
+    }
+}
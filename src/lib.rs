@@ -38,6 +38,7 @@
 //! # Example
 //!
 //! ```
+//! # #[cfg(feature="std")] {
 //! use microbench::{self, Options};
 //!
 //! fn fibonacci_iterative(n: u64) -> u64 {
@@ -57,100 +58,135 @@
 //! let options = Options::default();
 //! microbench::bench(&options, "iterative_16", || fibonacci_iterative(16));
 //! microbench::bench(&options, "recursive_16", || fibonacci_recursive(16));
+//! # }
 //! ```
 //!
 //! Example output:
 //!
 //! ```console
-//! iterative_16 (5.0s) ...                  281.733 ns/iter (0.998 R²)
-//! recursive_16 (5.0s) ...                9_407.020 ns/iter (0.997 R²)
+//! iterative_16 (5.0s) ...                  281.733 ns/iter ± 4.215 (0.998 R²)
+//! recursive_16 (5.0s) ...                9_407.020 ns/iter ± 152.904 (0.997 R²)
 //! ```
+//!
+//! # Tracing and logging
+//!
+//! When the `tracing` crate feature is enabled, each benchmark is run in an
+//! `INFO` span named `benchmark` (with a `name` field) and a `DEBUG` event is
+//! emitted for each sample (with `iterations` and `elapsed` fields) so that
+//! benchmark runs can be correlated with the traces of the code being
+//! benchmarked.
+//!
+//! When the `log` crate feature is enabled, diagnostics that explain unexpected
+//! results (e.g., samples discarded for exceeding the deadline, memory limits
+//! being reached, and calibration values) are logged at the `DEBUG` level.
+//!
+//! # `no_std`
+//!
+//! Almost all of this crate requires the standard library (the `std` crate
+//! feature which is enabled by default). Without the standard library, the
+//! execution time of code can still be measured with
+//! [`measure_with_clock`](fn.measure_with_clock.html) using a clock supplied by
+//! the caller (e.g., a hardware timer) and analyzed with
+//! [`OnlineModel`](statistics/struct.OnlineModel.html) which makes this crate
+//! usable on embedded targets.
 
-#![cfg_attr(feature="nightly", feature(test))]
+#![cfg_attr(not(feature="std"), no_std)]
 
 #![warn(missing_copy_implementations, missing_debug_implementations, missing_docs)]
 
-mod utility;
-pub mod statistics;
-pub mod time;
-
-use std::cmp;
-use std::mem;
-use std::time::{Duration};
-
-use crate::statistics::{Model};
-use crate::time::{Nanoseconds, Stopwatch};
-use crate::utility::{GeometricSequence, black_box, format_number};
-
-/// The maximum number of benchmark sample iterations.
-const ITERATIONS: u64 = 1_000_000_000_000_000;
-
-/// A number of bytes.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Bytes(pub u64);
+/// Registers the annotated function as a benchmark.
+///
+/// The annotated function must not take any arguments and will be benchmarked
+/// with [`bench`](fn.bench.html) by [`run_all`](fn.run_all.html) (which runs
+/// every function annotated with this attribute in the program). This attribute
+/// requires the `macros` crate feature.
+///
+/// ```no_run
+/// use microbench::{microbench, Options};
+///
+/// #[microbench]
+/// fn sum() -> u64 {
+///     (0..1000u64).sum()
+/// }
+///
+/// fn main() {
+///     microbench::run_all(&Options::default());
+/// }
+/// ```
+#[cfg(feature="macros")]
+pub use microbench_macros::microbench;
 
-impl Bytes {
-    /// Returns the number of bytes in the supplied number of kibibytes (2¹⁰ bytes).
-    pub fn kibibytes(kibibytes: u64) -> Self {
-        Bytes(kibibytes * 1024)
-    }
+#[cfg(feature="macros")]
+#[doc(hidden)]
+pub use inventory;
 
-    /// Returns the number of bytes in the supplied number of mebibytes (2²⁰ bytes).
-    pub fn mebibytes(mebibytes: u64) -> Self {
-        Bytes(mebibytes * 1024 * 1024)
-    }
+pub use crate::utility::{GeometricSequence};
 
-    /// Returns the number of bytes in the supplied number of gibibytes (2³⁰ bytes).
-    pub fn gibibytes(gibibytes: u64) -> Self {
-        Bytes(gibibytes * 1024 * 1024 * 1024)
-    }
-}
+#[cfg(feature="std")]
+pub use crate::hosted::*;
 
-/// A set of benchmarking options.
-#[derive(Copy, Clone, Debug)]
-pub struct Options {
-    factor: f64,
-    memory: Bytes,
-    time: Nanoseconds<u64>,
+/// Logs a diagnostic message at the debug level (if the `log` crate feature is
+/// enabled).
+#[cfg(feature="std")]
+macro_rules! debug {
+    ($($argument:tt)*) => {{
+        #[cfg(feature="log")]
+        log::debug!($($argument)*);
+        #[cfg(not(feature="log"))]
+        { let _ = format_args!($($argument)*); }
+    }};
 }
 
-impl Options {
-    /// Sets the geometric growth factor for benchmark sample iterations.
-    ///
-    /// **Default:** `1.01`
-    pub fn factor(mut self, factor: f64) -> Self {
-        self.factor = factor;
-        self
-    }
+#[cfg(feature="std")]
+mod config;
+#[cfg(feature="std")]
+mod hosted;
+#[cfg(feature="std")]
+mod json;
+mod utility;
+#[cfg(feature="std")]
+pub mod allocation;
+#[cfg(feature="std")]
+pub mod baseline;
+#[cfg(all(feature="std", feature="callgrind"))]
+pub mod callgrind;
+#[cfg(feature="std")]
+pub mod cli;
+#[cfg(feature="std")]
+pub mod criterion;
+#[cfg(all(target_arch="arm", feature="dwt"))]
+pub mod dwt;
+#[cfg(feature="std")]
+pub mod group;
+#[cfg(all(feature="std", feature="dhat"))]
+pub mod heap;
+#[cfg(feature="std")]
+pub mod history;
+#[cfg(feature="std")]
+pub mod metadata;
+#[cfg(all(feature="std", target_os="linux", feature="rapl"))]
+pub mod energy;
+#[cfg(all(feature="std", target_os="linux", feature="perf"))]
+pub mod perf;
+#[cfg(feature="std")]
+pub mod profiler;
+#[cfg(feature="std")]
+pub mod registry;
+pub mod statistics;
+#[cfg(feature="std")]
+pub mod suite;
+#[cfg(feature="std")]
+pub mod system;
+pub mod time;
 
-    /// Sets the maximum amount of memory benchmarks will allocate.
-    ///
-    /// **Default:** `Bytes::mebibytes(512)`
-    pub fn memory(mut self, memory: Bytes) -> Self {
-        self.memory = memory;
-        self
-    }
+use core::time::{Duration};
 
-    /// Sets the maximum amount of time benchmarks will run for.
-    ///
-    /// **Default:** `Duration::new(5, 0)`
-    pub fn time(mut self, time: Duration) -> Self {
-        self.time = time.into();
-        self
-    }
-}
-
-impl Default for Options {
-    fn default() -> Self {
-        let factor = 1.01;
-        let memory = Bytes::mebibytes(512);
-        let time = Duration::new(5, 0).into();
-        Options { factor, memory, time }
-    }
-}
+use crate::time::{Nanoseconds};
+use crate::utility::{black_box};
 
 /// A sample of the execution time of a function.
-#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default)]
 pub struct Sample {
     /// The number of times the function was executed.
     pub iterations: u64,
@@ -158,155 +194,108 @@ pub struct Sample {
     pub elapsed: Nanoseconds<u64>,
 }
 
-/// A statistical analysis of a set of execution time samples.
-#[derive(Copy, Clone, Debug)]
-pub struct Analysis {
-    /// The y-intercept of the simple linear regression model function.
-    pub alpha: Nanoseconds<f64>,
-    /// The slope of the simple linear regression model function.
-    pub beta: Nanoseconds<f64>,
-    /// The goodness of fit of the simple linear regression model function.
-    pub r2: f64,
-}
-
-impl Analysis {
-    /// Returns a new analysis for the supplied samples.
-    fn new(samples: &[Sample]) -> Self {
-        let Model { alpha, beta, r2 } = samples.iter()
-            .map(|m| (m.iterations as f64, m.elapsed.0 as f64))
-            .collect::<Model>();
-        Self { alpha: Nanoseconds(alpha), beta: Nanoseconds(beta), r2 }
-    }
-}
-
-/// Benchmarks the supplied function and prints the results.
-pub fn bench<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
-    bench_impl(name, move || measure(options, f));
-}
-
-/// Benchmarks the supplied function ignoring drop time and prints the results.
+/// Measures the execution time of the supplied function using the supplied
+/// clock and stores the samples in the supplied buffer.
 ///
-/// See [`measure_drop`](fn.measure_drop.html) for more information.
-pub fn bench_drop<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
-    bench_impl(name, move || measure_drop(options, f));
-}
-
-/// Benchmarks the supplied function ignoring setup time and prints the results.
+/// The supplied clock should return the number of nanoseconds that have
+/// elapsed since an unspecified starting point (e.g., as read from a hardware
+/// timer). Samples are collected with iteration counts from a geometric
+/// sequence with the supplied growth factor until the buffer is full or the
+/// samples have taken the supplied amount of time in total. Returns the
+/// collected samples (which can be analyzed with
+/// [`OnlineModel`](statistics/struct.OnlineModel.html)).
 ///
-/// See [`measure_setup`](fn.measure_setup.html) for more information.
-pub fn bench_setup<I, T>(
-    options: &Options,
-    name: &str,
-    setup: impl FnMut() -> I,
-    f: impl FnMut(I) -> T,
-) {
-    bench_impl(name, move || measure_setup(options, setup, f));
-}
-
-/// Measures the execution time of the supplied function.
-pub fn measure<T>(
-    options: &Options, mut f: impl FnMut() -> T
-) -> Vec<Sample> {
-    measure_impl(options, |iterations| {
-        let stopwatch = Stopwatch::default();
-        for _ in 0..iterations { retain(f()); }
-        Some(stopwatch.elapsed())
-    })
-}
-
-/// Measures the execution time of the supplied function ignoring drop time.
+/// Unlike the other measuring functions, this function does not require the
+/// standard library (see the `std` crate feature) and does not allocate so it
+/// can be used on embedded targets.
 ///
-/// This function does not include the time it takes to drop the values returned
-/// by the supplied function in the measurements. This can be useful when you
-/// want to exclude the running time of a slow implementation of `Drop` from
-/// your benchmark. However, it should be noted that this function introduces a
-/// very small amount of overhead which will be reflected in the measurements
-/// (typically of the order of a few nanoseconds).
+/// ```
+/// use microbench::{Sample, measure_with_clock};
+/// use microbench::statistics::{OnlineModel};
+/// use microbench::time::{Nanoseconds};
+/// use std::time::{Duration, Instant};
 ///
-/// **Warning:** This function can potentially allocate very large amounts of
-/// memory. The `memory` option controls the maximum amount of memory this
-/// function is allowed to allocate.
-pub fn measure_drop<T>(
-    options: &Options, mut f: impl FnMut() -> T
-) -> Vec<Sample> {
-    measure_impl(options, |iterations| {
-        let size = cmp::max(1, mem::size_of::<T>() as u64);
-        if options.memory < Bytes(iterations * size) {
-            return None;
+/// let mut buffer = [Sample::default(); 32];
+/// let start = Instant::now();
+/// let clock = || Nanoseconds(start.elapsed().as_nanos() as u64);
+/// let time = Duration::from_millis(100);
+/// let samples = measure_with_clock(clock, time, 1.5, &mut buffer, || (0..100u64).sum::<u64>());
+/// let model = samples.iter()
+///     .map(|s| (s.iterations as f64, s.elapsed.0 as f64))
+///     .collect::<OnlineModel>()
+///     .model();
+/// println!("{} ns/iter", model.beta);
+/// ```
+pub fn measure_with_clock<T>(
+    mut clock: impl FnMut() -> Nanoseconds<u64>,
+    time: Duration,
+    factor: f64,
+    samples: &mut [Sample],
+    mut f: impl FnMut() -> T,
+) -> &[Sample] {
+    let time = Nanoseconds::from(time);
+    let mut total = 0;
+    let mut count = 0;
+    for (sample, iterations) in samples.iter_mut().zip(GeometricSequence::new(1, factor)) {
+        if total >= time.0 {
+            break;
         }
 
-        let mut outputs = Vec::with_capacity(iterations as usize);
-        let stopwatch = Stopwatch::default();
-        for _ in 0..iterations { outputs.push(f()); }
-        let elapsed = stopwatch.elapsed();
-        mem::drop(outputs);
-        Some(elapsed)
-    })
+        let start = clock();
+        for _ in 0..iterations { retain_ref(&f()); }
+        let elapsed = Nanoseconds(clock().0.saturating_sub(start.0));
+        *sample = Sample { iterations, elapsed };
+        total += elapsed.0;
+        count += 1;
+    }
+    &samples[..count]
 }
 
-/// Measures the execution time of the supplied function ignoring setup time.
-///
-/// This function does not include the time it takes to execute the setup
-/// function in the measurements. This can be useful when you want to exclude
-/// the running time of some non-trivial setup which is needed for every
-/// execution of the supplied function. However, it should be noted that this
-/// function introduces a very small amount of overhead which will be reflected
-/// in the measurements (typically of the order of a few nanoseconds).
+/// A function that prevents the optimizer from eliminating the supplied value.
 ///
-/// **Warning:** This function can potentially allocate very large amounts of
-/// memory. The `memory` option controls the maximum amount of memory this
-/// function is allowed to allocate.
-pub fn measure_setup<I, T>(
-    options: &Options,
-    mut setup: impl FnMut() -> I,
-    mut f: impl FnMut(I) -> T,
-) -> Vec<Sample> {
-    measure_impl(options, |iterations| {
-        let size = cmp::max(1, mem::size_of::<I>() as u64);
-        if options.memory < Bytes(iterations * size) {
-            return None;
-        }
-
-        let inputs = retain((0..iterations).map(|_| setup()).collect::<Vec<_>>());
-        let stopwatch = Stopwatch::default();
-        for input in inputs { retain(f(input)); }
-        Some(stopwatch.elapsed())
-    })
+/// This function uses `core::hint::black_box` which is a best-effort hint (the
+/// optimizer is not guaranteed to treat the value as opaque).
+pub fn retain<T>(value: T) -> T {
+    black_box(value)
 }
 
-/// A function that prevents the optimizer from eliminating the supplied value.
+/// A function that prevents the optimizer from eliminating the value behind the
+/// supplied reference.
 ///
-/// This function may not operate correctly or may have poor performance on the
-/// stable and beta channels of Rust. If you are using a nightly release of
-/// Rust, enable the `nightly` crate feature to enable a superior implementation
-/// of this function.
-pub fn retain<T>(value: T) -> T {
+/// Unlike [`retain`](fn.retain.html), only the reference is passed to
+/// `core::hint::black_box` so the value is not moved or copied. This is useful
+/// for large values where copying the value would dominate the measurements.
+///
+/// ```
+/// use microbench::{retain_ref};
+///
+/// let buffer = [0u8; 4096];
+/// retain_ref(&buffer);
+/// ```
+pub fn retain_ref<T: ?Sized>(value: &T) -> &T {
     black_box(value)
 }
 
-/// Prints an analysis of the samples produced by the supplied function.
-fn bench_impl(name: &str, f: impl FnOnce() -> Vec<Sample>) {
-    let stopwatch = Stopwatch::default();
-    let samples = f();
-    let elapsed = stopwatch.elapsed();
-    let analysis = Analysis::new(&samples);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let prefix = format!("{} ({}) ...", name, elapsed);
-    if samples.len() < 2 || analysis.beta.0 < 0.0 {
-        println!("{:<32} {:>15}", prefix, "           not enough samples");
-    } else {
-        let beta = format_number(analysis.beta.0, 3, '_');
-        println!("{:<32} {:>15} ns/iter ({:.3} R²)", prefix, beta, analysis.r2);
-    }
-}
+    #[test]
+    fn test_measure_with_clock() {
+        let mut now = 0;
+        let mut clock = || { now += 100; Nanoseconds(now) };
+        let mut calls = 0;
+        let mut buffer = [Sample::default(); 32];
+        let time = Duration::from_nanos(1_000);
+        let samples = measure_with_clock(&mut clock, time, 2.0, &mut buffer, || calls += 1);
+        assert_eq!(samples.len(), 10);
+        assert_eq!(samples[0].iterations, 1);
+        assert_eq!(samples[9].iterations, 512);
+        assert!(samples.iter().all(|s| s.elapsed == Nanoseconds(100)));
+        assert_eq!(calls, 1_023);
 
-/// Collects samples produced by the supplied function.
-fn measure_impl(
-    options: &Options, mut f: impl FnMut(u64) -> Option<Nanoseconds<u64>>
-) -> Vec<Sample> {
-    let stopwatch = Stopwatch::default();
-    GeometricSequence::new(1, options.factor)
-        .take_while(|i| *i <= ITERATIONS && stopwatch.elapsed() < options.time)
-        .filter_map(|i| Some(Sample { iterations: i, elapsed: f(i)? }))
-        .collect()
+        let mut buffer = [Sample::default(); 4];
+        let samples = measure_with_clock(&mut clock, time, 2.0, &mut buffer, || ());
+        assert_eq!(samples.len(), 4);
+    }
 }
@@ -0,0 +1,130 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metadata tags.
+//!
+//! Tags are key-value pairs (e.g., `machine=ci-large` or `dataset=small`) that
+//! are attached to the results of benchmarks in structured output (e.g.,
+//! [`Format::Json`](../enum.Format.html#variant.Json)), baselines, and
+//! histories so that results from different configurations can be
+//! distinguished. Tags can be attached to every benchmark in a run (see
+//! [`set_run_tag`](fn.set_run_tag.html)) or to the benchmarks run in a scope
+//! (see [`with_tags`](fn.with_tags.html)).
+
+use std::cell::{RefCell};
+use std::mem;
+use std::sync::{Mutex};
+
+use crate::json::{Value};
+use crate::utility::{json_string};
+
+/// A list of key-value pairs.
+pub type Tags = Vec<(String, String)>;
+
+/// The tags attached to every benchmark in the run.
+static RUN: Mutex<Tags> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// The tags attached to the benchmarks run in the current scope.
+    static SCOPED: RefCell<Tags> = const { RefCell::new(Vec::new()) };
+}
+
+/// Attaches the supplied tag to every benchmark in the run (replacing any
+/// existing run tag with the same key).
+pub fn set_run_tag(key: impl Into<String>, value: impl Into<String>) {
+    insert(&mut RUN.lock().unwrap(), key.into(), value.into());
+}
+
+/// Attaches the supplied tags to every benchmark run by the supplied function
+/// (on the current thread).
+///
+/// Scoped tags take precedence over run tags with the same keys and scopes can
+/// be nested. The previous scoped tags are restored even if the supplied
+/// function panics.
+pub fn with_tags<T>(tags: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+    /// A guard that restores the previous scoped tags when dropped.
+    struct Restore(Tags);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = mem::take(&mut self.0);
+            SCOPED.with(|s| *s.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = SCOPED.with(|s| {
+        let previous = s.borrow().clone();
+        for (key, value) in tags {
+            insert(&mut s.borrow_mut(), (*key).into(), (*value).into());
+        }
+        Restore(previous)
+    });
+
+    f()
+}
+
+/// Returns the tags attached to benchmarks run at this point (the run tags and
+/// the scoped tags).
+pub fn current() -> Tags {
+    let mut tags = RUN.lock().unwrap().clone();
+    SCOPED.with(|s| s.borrow().iter().for_each(|(k, v)| insert(&mut tags, k.clone(), v.clone())));
+    tags
+}
+
+/// Inserts the supplied tag (replacing any existing tag with the same key).
+fn insert(tags: &mut Tags, key: String, value: String) {
+    match tags.iter_mut().find(|t| t.0 == key) {
+        Some(tag) => tag.1 = value,
+        None => tags.push((key, value)),
+    }
+}
+
+/// Returns the supplied tags as a JSON object.
+pub(crate) fn to_json(tags: &[(String, String)]) -> String {
+    let tags = tags.iter().map(|(k, v)| format!("{}:{}", json_string(k), json_string(v)));
+    format!("{{{}}}", tags.collect::<Vec<_>>().join(","))
+}
+
+/// Returns the supplied JSON object as tags (ignoring non-string values).
+pub(crate) fn from_json(value: Option<&Value>) -> Tags {
+    match value {
+        Some(Value::Object(entries)) => entries.iter()
+            .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.into())))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic;
+
+    #[test]
+    fn test_with_tags() {
+        let scoped = || SCOPED.with(|s| s.borrow().clone());
+        with_tags(&[("a", "1")], || {
+            with_tags(&[("a", "2"), ("b", "3")], || {
+                assert_eq!(scoped(), [("a".into(), "2".into()), ("b".into(), "3".into())]);
+            });
+            assert_eq!(scoped(), [("a".into(), "1".into())]);
+
+            let result = panic::catch_unwind(|| with_tags(&[("c", "4")], || panic!()));
+            assert!(result.is_err());
+            assert_eq!(scoped(), [("a".into(), "1".into())]);
+        });
+        assert!(scoped().is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hardware performance counters (Linux only).
+//!
+//! This module is only available on Linux when the `perf` crate feature is
+//! enabled. The counters are collected using `perf_event_open` which may
+//! require lowering `/proc/sys/kernel/perf_event_paranoid`.
+
+use std::fs::{File};
+use std::io::{self, Read};
+use std::mem;
+use std::ops::{ControlFlow};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+use crate::{Options, Sample, bench_impl, format_quantity, note, retain_ref, sample_impl};
+use crate::statistics::{Model};
+use crate::time::{Stopwatch, TimeSource};
+
+/// The `perf_event_attr` struct (`PERF_ATTR_SIZE_VER0`).
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+struct Attributes {
+    kind: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+const PERF_FLAG_DISABLED: u64 = 1;
+const PERF_FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const PERF_FLAG_EXCLUDE_HV: u64 = 1 << 6;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 8;
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+const PERF_IOC_FLAG_GROUP: libc::c_ulong = 1;
+
+/// A hardware performance counter.
+#[derive(Debug)]
+struct Counter(File);
+
+impl Counter {
+    /// Opens a new counter for the supplied event in the supplied group.
+    fn open(config: u64, group: Option<&Counter>) -> io::Result<Self> {
+        let mut flags = PERF_FLAG_EXCLUDE_KERNEL | PERF_FLAG_EXCLUDE_HV;
+        if group.is_none() {
+            flags |= PERF_FLAG_DISABLED;
+        }
+
+        let attributes = Attributes {
+            kind: PERF_TYPE_HARDWARE,
+            size: mem::size_of::<Attributes>() as u32,
+            config,
+            flags,
+            ..Default::default()
+        };
+
+        let group = group.map_or(-1, |g| g.0.as_raw_fd());
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open, &attributes, 0, -1, group, PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+
+        if fd >= 0 {
+            Ok(Counter(unsafe { File::from_raw_fd(fd as libc::c_int) }))
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Performs the supplied operation on all of the counters in this group.
+    fn control(&self, request: libc::c_ulong) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.0.as_raw_fd(), request as _, PERF_IOC_FLAG_GROUP) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Returns the current value of this counter.
+    fn read(&self) -> io::Result<u64> {
+        let mut buffer = [0; 8];
+        (&self.0).read_exact(&mut buffer)?;
+        Ok(u64::from_ne_bytes(buffer))
+    }
+}
+
+/// The hardware performance counter values for a sample.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// The number of instructions retired.
+    pub instructions: u64,
+    /// The number of last-level cache misses.
+    pub cache_misses: u64,
+    /// The number of mispredicted branches.
+    pub branch_misses: u64,
+}
+
+/// Benchmarks the supplied function and prints the results (including the
+/// hardware performance counter values per iteration).
+///
+/// See [`measure_counters`](fn.measure_counters.html) for more information.
+pub fn bench_counters<T>(options: &Options, name: &str, f: impl FnMut() -> T) {
+    let mut result = Ok(vec![]);
+    bench_impl(options, name, || match measure_counters_impl(options, f) {
+        Ok((samples, retries)) => {
+            let (samples, counts): (Vec<Sample>, Vec<Counts>) = samples.into_iter().unzip();
+            result = Ok(samples.iter().map(|s| s.iterations).zip(counts).collect());
+            (samples, retries)
+        },
+        Err(error) => {
+            result = Err(error);
+            (vec![], 0)
+        },
+    });
+
+    match result {
+        Ok(counts) if counts.len() >= 2 => {
+            let regress = |f: fn(&(u64, Counts)) -> u64| {
+                let model = counts.iter().map(|c| (c.0 as f64, f(c) as f64)).collect::<Model>();
+                format_quantity(options, model.beta)
+            };
+            note(options, format_args!(
+                "    {} instructions/iter, {} cache misses/iter, {} branch misses/iter",
+                regress(|c| c.1.instructions),
+                regress(|c| c.1.cache_misses),
+                regress(|c| c.1.branch_misses),
+            ));
+        },
+        Ok(_) => { },
+        Err(error) => note(options, format_args!(
+            "    warning: failed to open performance counters ({})", error,
+        )),
+    }
+}
+
+/// Measures the execution time and hardware performance counter values of the
+/// supplied function.
+///
+/// The instructions retired, last-level cache misses, and mispredicted branches
+/// (excluding the kernel and hypervisor) are counted for each sample so that
+/// they can be regressed against the number of iterations just like execution
+/// time. These counts are typically far less sensitive to noise than execution
+/// time.
+pub fn measure_counters<T>(
+    options: &Options, f: impl FnMut() -> T
+) -> io::Result<Vec<(Sample, Counts)>> {
+    measure_counters_impl(options, f).map(|(s, _)| s)
+}
+
+/// Measures the execution time and hardware performance counter values of the
+/// supplied function and also returns the number of retries.
+fn measure_counters_impl<T>(
+    options: &Options, mut f: impl FnMut() -> T
+) -> io::Result<(Vec<(Sample, Counts)>, u32)> {
+    let instructions = Counter::open(PERF_COUNT_HW_INSTRUCTIONS, None)?;
+    let cache_misses = Counter::open(PERF_COUNT_HW_CACHE_MISSES, Some(&instructions))?;
+    let branch_misses = Counter::open(PERF_COUNT_HW_BRANCH_MISSES, Some(&instructions))?;
+
+    let mut error = None;
+    let (samples, retries) = sample_impl(options, options.clock.resolution(), |iterations| {
+        let mut sample = || {
+            instructions.control(PERF_EVENT_IOC_RESET)?;
+            instructions.control(PERF_EVENT_IOC_ENABLE)?;
+            let stopwatch = Stopwatch::new(options.clock);
+            for _ in 0..iterations { retain_ref(&f()); }
+            let elapsed = stopwatch.elapsed();
+            instructions.control(PERF_EVENT_IOC_DISABLE)?;
+            let counts = Counts {
+                instructions: instructions.read()?,
+                cache_misses: cache_misses.read()?,
+                branch_misses: branch_misses.read()?,
+            };
+            Ok((elapsed, counts))
+        };
+
+        sample().map_err(|e| error = Some(e)).ok()
+    }, |_, _| ControlFlow::Continue(()));
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok((samples, retries)),
+    }
+}
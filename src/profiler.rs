@@ -0,0 +1,86 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Profiler hooks.
+//!
+//! A profiler (see [`set_profiler`](fn.set_profiler.html)) is notified when
+//! each sample of a benchmark starts and stops so that profiling integrations
+//! (e.g., a `perf` control file descriptor or the VTune ITT API) can limit
+//! their collection to the measured code. Warmup and pilot iterations are not
+//! included.
+//!
+//! ```
+//! use microbench::profiler::{self, Profiler};
+//!
+//! struct Trace;
+//!
+//! impl Profiler for Trace {
+//!     fn start(&self, name: &str) { eprintln!("start {}", name); }
+//!     fn stop(&self, name: &str) { eprintln!("stop {}", name); }
+//! }
+//!
+//! profiler::set_profiler(Some(Box::new(Trace)));
+//! ```
+
+use std::cell::{RefCell};
+use std::sync::{Arc, Mutex};
+
+/// The profiler notified about the samples of every benchmark, if any.
+static PROFILER: Mutex<Option<Arc<dyn Profiler>>> = Mutex::new(None);
+
+thread_local! {
+    /// The profiler and name of the benchmark being run on the current thread.
+    static CURRENT: RefCell<Option<(Arc<dyn Profiler>, String)>> = const { RefCell::new(None) };
+}
+
+/// A type that is notified when the samples of benchmarks start and stop.
+pub trait Profiler: Send + Sync {
+    /// Called immediately before a sample of the benchmark with the supplied
+    /// name is collected.
+    fn start(&self, name: &str);
+
+    /// Called immediately after a sample of the benchmark with the supplied
+    /// name is collected.
+    fn stop(&self, name: &str);
+}
+
+/// Sets the profiler notified about the samples of every benchmark (replacing
+/// any existing profiler).
+///
+/// Only samples collected by the benchmarking functions (e.g.,
+/// [`bench`](../fn.bench.html)) are reported since the measuring functions
+/// (e.g., [`measure`](../fn.measure.html)) are not associated with a name.
+pub fn set_profiler(profiler: Option<Box<dyn Profiler>>) {
+    *PROFILER.lock().unwrap() = profiler.map(Arc::from);
+}
+
+/// Notifies the profiler (if any) about the samples collected by the supplied
+/// function as samples of the benchmark with the supplied name.
+pub(crate) fn scope<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let profiler = PROFILER.lock().unwrap().clone();
+    let previous = CURRENT.with(|c| c.replace(profiler.map(|p| (p, name.into()))));
+    let result = f();
+    CURRENT.with(|c| *c.borrow_mut() = previous);
+    result
+}
+
+/// Notifies the profiler (if any) that a sample is starting.
+pub(crate) fn start() {
+    CURRENT.with(|c| if let Some((profiler, name)) = &*c.borrow() { profiler.start(name) });
+}
+
+/// Notifies the profiler (if any) that a sample has stopped.
+pub(crate) fn stop() {
+    CURRENT.with(|c| if let Some((profiler, name)) = &*c.borrow() { profiler.stop(name) });
+}
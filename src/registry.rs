@@ -0,0 +1,118 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Registries of benchmarks.
+
+use std::vec;
+
+use crate::{Options};
+use crate::group::{Group};
+
+/// A description of a benchmark in a registry.
+#[derive(Copy, Clone, Debug)]
+pub struct Description<'r> {
+    /// The name of the group that contains the benchmark.
+    pub group: &'r str,
+    /// The name of the benchmark (without the group name).
+    pub name: &'r str,
+    /// The benchmarking options the benchmark will be run with by the
+    /// command-line interface (i.e., the options shared by the benchmarks in
+    /// the group with the per-benchmark overrides, the configuration file, and
+    /// the environment variables applied) excluding any command-line arguments.
+    pub options: Options,
+}
+
+impl Description<'_> {
+    /// Returns the full name of the benchmark (e.g., `group/name`).
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.group, self.name)
+    }
+}
+
+/// A collection of groups of benchmarks that can be enumerated without running
+/// the benchmarks.
+///
+/// ```
+/// use microbench::{Options, bench_group, retain};
+/// use microbench::registry::{Registry};
+///
+/// bench_group!(sum, Options::default();
+///     "iter" => || (0..retain(1024u64)).sum::<u64>(),
+///     "fold" => || (0..retain(1024u64)).fold(0, |a, b| a + b),
+/// );
+///
+/// let registry = Registry::new().group(sum());
+/// let names = registry.benchmarks().map(|b| b.full_name()).collect::<Vec<_>>();
+/// assert_eq!(names, &["sum/iter", "sum/fold"]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Registry<'a> {
+    groups: Vec<Group<'a>>,
+}
+
+impl<'a> Registry<'a> {
+    /// Constructs a new empty `Registry`.
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Constructs a new `Registry` that contains the functions registered with
+    /// the [`microbench`](../attr.microbench.html) attribute (grouped by module
+    /// path and benchmarked with the default options).
+    #[cfg(feature="macros")]
+    pub fn registered() -> Registry<'static> {
+        let mut benchmarks = inventory::iter::<crate::Benchmark>.into_iter().collect::<Vec<_>>();
+        benchmarks.sort_by_key(|b| (b.module, b.name));
+
+        let mut registry = Registry::new();
+        for benchmark in benchmarks {
+            if registry.groups.last().map(|g| g.name()) != Some(benchmark.module) {
+                registry.groups.push(Group::new(benchmark.module, Options::default()));
+            }
+
+            let group = registry.groups.pop().unwrap();
+            let function = benchmark.function;
+            registry.groups.push(group.custom(benchmark.name, function));
+        }
+        registry
+    }
+
+    /// Adds the supplied group of benchmarks to this registry.
+    pub fn group(mut self, group: Group<'a>) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// Returns the groups of benchmarks in this registry.
+    pub fn groups(&self) -> &[Group<'a>] {
+        &self.groups
+    }
+
+    /// Returns descriptions of the benchmarks in this registry.
+    pub fn benchmarks(&self) -> impl Iterator<Item=Description<'_>> {
+        self.groups.iter().flat_map(|g| {
+            g.benchmarks(Options::with_overrides)
+                .map(move |(name, options)| Description { group: g.name(), name, options })
+        })
+    }
+}
+
+impl<'a> IntoIterator for Registry<'a> {
+    type Item = Group<'a>;
+    type IntoIter = vec::IntoIter<Group<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.groups.into_iter()
+    }
+}
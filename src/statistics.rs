@@ -14,35 +14,17 @@
 
 //! Statistics-related utilities.
 
-use std::iter::{FromIterator};
-
-/// A collection of floating-point numbers that supports Kahan summation.
-pub trait Kahan {
-    /// Returns the mean of the numbers in this collection using the Kahan
-    /// summation algorithm.
-    fn kahan_mean(self) -> f64;
-    /// Returns the sum of the numbers in this collection using the Kahan
-    /// summation algorithm.
-    fn kahan_sum(self) -> f64;
-}
+use core::iter::{FromIterator};
 
-impl<I> Kahan for I where I: ExactSizeIterator<Item=f64> {
-    fn kahan_mean(self) -> f64 {
-        let size = self.len() as f64;
-        self.kahan_sum() / size
-    }
+#[cfg(feature="std")]
+mod hosted;
 
-    fn kahan_sum(self) -> f64 {
-        self.fold((0.0, 0.0), |(sum, correction), f| {
-            let y = f - correction;
-            let t = sum + y;
-            (t, (t - sum) - y)
-        }).0
-    }
-}
+#[cfg(feature="std")]
+pub use self::hosted::*;
 
 /// A simple linear regression model.
 #[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub struct Model {
     /// The y-intercept of the model function.
     pub alpha: f64,
@@ -50,33 +32,125 @@ pub struct Model {
     pub beta: f64,
     /// The goodness of fit of the model function.
     pub r2: f64,
+    /// The standard error of the slope of the model function.
+    pub error: f64,
+    /// The residual degrees of freedom of the model function.
+    pub dof: f64,
+    /// The standard deviation of the residuals of the model function (i.e.,
+    /// the residual standard error).
+    pub deviation: f64,
+    /// The mean of the x-values the model function was fitted to.
+    pub xmean: f64,
+    /// The sum of the squared deviations of the x-values the model function
+    /// was fitted to from their mean.
+    pub sxx: f64,
+}
+
+/// A running floating-point sum that uses the Kahan summation algorithm.
+#[derive(Copy, Clone, Debug, Default)]
+struct KahanSum {
+    sum: f64,
+    correction: f64,
+}
+
+impl KahanSum {
+    /// Adds the supplied number to this sum.
+    fn add(&mut self, f: f64) {
+        let y = f - self.correction;
+        let t = self.sum + y;
+        self.correction = (t - self.sum) - y;
+        self.sum = t;
+    }
+}
+
+/// A simple linear regression model that is updated incrementally.
+///
+/// Unlike [`Model`](struct.Model.html), this model does not store the data it
+/// is built from. The means and co-moments of the data are maintained using
+/// Welford's algorithm (with Kahan summation) so that the model can be updated
+/// and evaluated in constant memory.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OnlineModel {
+    n: u64,
+    xmean: KahanSum,
+    ymean: KahanSum,
+    sxx: KahanSum,
+    sxy: KahanSum,
+    syy: KahanSum,
+}
+
+impl OnlineModel {
+    /// Adds the supplied data point to this model.
+    pub fn add(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        let n = self.n as f64;
+
+        let dx = x - self.xmean.sum;
+        let dy = y - self.ymean.sum;
+        self.xmean.add(dx / n);
+        self.ymean.add(dy / n);
+
+        self.sxx.add(dx * (x - self.xmean.sum));
+        self.sxy.add(dx * (y - self.ymean.sum));
+        self.syy.add(dy * (y - self.ymean.sum));
+    }
+
+    /// Returns the number of data points that have been added to this model.
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+
+    /// Returns the OLS linear regression model for the data points that have
+    /// been added to this model.
+    pub fn model(&self) -> Model {
+        let beta = self.sxy.sum / self.sxx.sum;
+        let alpha = self.ymean.sum - (beta * self.xmean.sum);
+        let r2 = (beta * self.sxy.sum) / self.syy.sum;
+        let dof = self.n as f64 - 2.0;
+        let residuals = (self.syy.sum - (beta * self.sxy.sum)).max(0.0);
+        let (xmean, sxx) = (self.xmean.sum, self.sxx.sum);
+        let error = sqrt(residuals / dof / sxx);
+        let deviation = sqrt(residuals / dof);
+        Model { alpha, beta, r2, error, dof, deviation, xmean, sxx }
+    }
+}
+
+/// Returns the square root of the supplied number.
+#[cfg(feature="std")]
+fn sqrt(x: f64) -> f64 {
+    x.sqrt()
 }
 
-impl Model {
-    /// Returns a new model for the supplied data using OLS linear regression.
-    fn new(data: &[(f64, f64)]) -> Self {
-        let xmean = data.iter().map(|d| d.0).kahan_mean();
-        let ymean = data.iter().map(|d| d.1).kahan_mean();
-
-        // OLS linear regression.
-        let numerator = data.iter().map(|m| (m.0 - xmean) * (m.1 - ymean)).kahan_sum();
-        let denominator = data.iter().map(|m| (m.0 - xmean).powf(2.0)).kahan_sum();
-        let beta = numerator / denominator;
-        let alpha = ymean - (beta * xmean);
-        let estimator = |x| (beta * x) + alpha;
-
-        // OLS goodness of fit.
-        let numerator = data.iter().map(|m| (estimator(m.0) - ymean).powf(2.0)).kahan_sum();
-        let denominator = data.iter().map(|m| (m.1 - ymean).powf(2.0)).kahan_sum();
-        let r2 = numerator / denominator;
-
-        Self { alpha, beta, r2 }
+/// Returns the square root of the supplied number.
+///
+/// `f64::sqrt` is not available without the standard library so this uses
+/// Newton's method starting from an estimate derived from the exponent.
+#[cfg(not(feature="std"))]
+fn sqrt(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    } else if x == 0.0 || x.is_infinite() {
+        return x;
+    }
+
+    let mut root = f64::from_bits((x.to_bits() >> 1) + (1023 << 51));
+    for _ in 0..8 {
+        root = 0.5 * (root + (x / root));
+    }
+    root
+}
+
+impl Extend<(f64, f64)> for OnlineModel {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item=(f64, f64)> {
+        for (x, y) in iter { self.add(x, y); }
     }
 }
 
-impl FromIterator<(f64, f64)> for Model {
+impl FromIterator<(f64, f64)> for OnlineModel {
     fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=(f64, f64)> {
-        Model::new(&iter.into_iter().collect::<Vec<_>>())
+        let mut model = OnlineModel::default();
+        model.extend(iter);
+        model
     }
 }
 
@@ -85,13 +159,7 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_kahan() {
-        let numbers: &[f64] = &[10000.0, 3.14159, 2.71828];
-        assert_eq!(numbers.iter().cloned().kahan_sum(), 10005.85987);
-    }
-
-    #[test]
-    fn test_model() {
+    fn test_online_model() {
         let data: &[(f64, f64)] = &[
             (1.47, 52.21), (1.50, 53.12), (1.52, 54.48),
             (1.55, 55.84), (1.57, 57.20), (1.60, 58.57),
@@ -100,9 +168,12 @@ mod tests {
             (1.78, 69.92), (1.80, 72.19), (1.83, 74.46),
         ];
 
-        let model = data.iter().cloned().collect::<Model>();
-        assert_eq!(model.alpha, -39.06195591884393);
-        assert_eq!(model.beta, 61.27218654211062);
-        assert_eq!(model.r2, 0.989196922445796);
+        let online = data.iter().cloned().collect::<OnlineModel>();
+        assert_eq!(online.count(), 15);
+        let model = online.model();
+        assert!((model.alpha - -39.06195591884393).abs() < 1e-9);
+        assert!((model.beta - 61.27218654211062).abs() < 1e-9);
+        assert!((model.r2 - 0.989196922445796).abs() < 1e-9);
+        assert!((model.error - 1.775_922_752).abs() < 1e-9);
     }
 }
@@ -0,0 +1,796 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The parts of this module that require the standard library.
+
+use std::fmt;
+use std::iter::{FromIterator};
+
+use super::{Model};
+
+/// A collection of floating-point numbers that supports Kahan summation.
+pub trait Kahan {
+    /// Returns the geometric mean of the numbers in this collection using the
+    /// Kahan summation algorithm (on the logarithms of the numbers).
+    ///
+    /// This is the appropriate mean for aggregating ratios (e.g., speedups).
+    fn kahan_geometric_mean(self) -> f64;
+    /// Returns the harmonic mean of the numbers in this collection using the
+    /// Kahan summation algorithm (on the reciprocals of the numbers).
+    ///
+    /// This is the appropriate mean for aggregating rates (e.g., throughputs).
+    fn kahan_harmonic_mean(self) -> f64;
+    /// Returns the mean of the numbers in this collection using the Kahan
+    /// summation algorithm.
+    fn kahan_mean(self) -> f64;
+    /// Returns the sum of the numbers in this collection using the Kahan
+    /// summation algorithm.
+    fn kahan_sum(self) -> f64;
+}
+
+impl<I> Kahan for I where I: ExactSizeIterator<Item=f64> {
+    fn kahan_geometric_mean(self) -> f64 {
+        self.map(f64::ln).kahan_mean().exp()
+    }
+
+    fn kahan_harmonic_mean(self) -> f64 {
+        let size = self.len() as f64;
+        size / self.map(f64::recip).kahan_sum()
+    }
+
+    fn kahan_mean(self) -> f64 {
+        let size = self.len() as f64;
+        self.kahan_sum() / size
+    }
+
+    fn kahan_sum(self) -> f64 {
+        self.fold((0.0, 0.0), |(sum, correction), f| {
+            let y = f - correction;
+            let t = sum + y;
+            (t, (t - sum) - y)
+        }).0
+    }
+}
+
+/// Returns the autocorrelation of the supplied numbers at the supplied lag.
+pub fn autocorrelation(data: &[f64], lag: usize) -> f64 {
+    let mean = data.iter().cloned().kahan_mean();
+    let numerator = data.iter().zip(data.iter().skip(lag))
+        .map(|(a, b)| (a - mean) * (b - mean))
+        .kahan_sum();
+    let denominator = data.iter().map(|d| (d - mean).powi(2)).kahan_sum();
+    numerator / denominator
+}
+
+/// Returns the supplied quantile (e.g., `0.5` for the median) of the supplied
+/// sorted numbers using linear interpolation.
+pub fn quantile(sorted: &[f64], quantile: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+
+    let index = quantile * (sorted.len() - 1) as f64;
+    let (lower, upper) = (index.floor() as usize, index.ceil() as usize);
+    sorted[lower] + ((sorted[upper] - sorted[lower]) * index.fract())
+}
+
+/// Returns Cohen's d for the supplied two groups of numbers (i.e., the
+/// difference between the mean of the second group and the mean of the first
+/// group divided by the pooled standard deviation of the groups).
+///
+/// Returns `NaN` if either group contains fewer than two numbers.
+pub fn cohens_d(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() < 2 || b.len() < 2 {
+        return f64::NAN;
+    }
+
+    let (amean, bmean) = (a.iter().cloned().kahan_mean(), b.iter().cloned().kahan_mean());
+    let squares = |data: &[f64], mean: f64| data.iter().map(|d| (d - mean).powi(2)).kahan_sum();
+    let dof = (a.len() + b.len() - 2) as f64;
+    let pooled = ((squares(a, amean) + squares(b, bmean)) / dof).sqrt();
+    (bmean - amean) / pooled
+}
+
+/// The moments of a collection of floating-point numbers.
+#[derive(Copy, Clone, Debug)]
+pub struct Moments {
+    /// The mean of the numbers.
+    pub mean: f64,
+    /// The (population) variance of the numbers.
+    pub variance: f64,
+    /// The skewness of the numbers.
+    pub skewness: f64,
+    /// The excess kurtosis of the numbers.
+    pub kurtosis: f64,
+}
+
+impl Moments {
+    /// Returns the moments of the supplied numbers.
+    fn new(data: &[f64]) -> Self {
+        let mean = data.iter().cloned().kahan_mean();
+        let moment = |k| data.iter().map(|d| (d - mean).powi(k)).kahan_mean();
+        let variance = moment(2);
+        let skewness = moment(3) / variance.powf(1.5);
+        let kurtosis = (moment(4) / variance.powi(2)) - 3.0;
+        Self { mean, variance, skewness, kurtosis }
+    }
+}
+
+impl FromIterator<f64> for Moments {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=f64> {
+        Moments::new(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl Model {
+    /// Returns a new model for the supplied data using OLS linear regression.
+    fn new(data: &[(f64, f64)]) -> Self {
+        let xmean = data.iter().map(|d| d.0).kahan_mean();
+        let ymean = data.iter().map(|d| d.1).kahan_mean();
+
+        // OLS linear regression.
+        let numerator = data.iter().map(|m| (m.0 - xmean) * (m.1 - ymean)).kahan_sum();
+        let denominator = data.iter().map(|m| (m.0 - xmean).powf(2.0)).kahan_sum();
+        let beta = numerator / denominator;
+        let alpha = ymean - (beta * xmean);
+        let estimator = |x| (beta * x) + alpha;
+
+        // OLS goodness of fit.
+        let numerator = data.iter().map(|m| (estimator(m.0) - ymean).powf(2.0)).kahan_sum();
+        let denominator = data.iter().map(|m| (m.1 - ymean).powf(2.0)).kahan_sum();
+        let r2 = numerator / denominator;
+
+        // OLS standard error of the slope.
+        let dof = data.len() as f64 - 2.0;
+        let residuals = data.iter().map(|m| (m.1 - estimator(m.0)).powf(2.0)).kahan_sum();
+        let sxx = data.iter().map(|m| (m.0 - xmean).powf(2.0)).kahan_sum();
+        let error = (residuals / dof / sxx).sqrt();
+        let deviation = (residuals / dof).sqrt();
+
+        Self { alpha, beta, r2, error, dof, deviation, xmean, sxx }
+    }
+
+    /// Returns the half-width of the confidence interval around the slope of
+    /// the model function with the supplied confidence level (e.g., `0.95`).
+    ///
+    /// This is a confidence interval for the slope (i.e., the interval contains
+    /// the true slope with the supplied probability), not a prediction interval
+    /// for new data points. Returns `None` if the model has no residual degrees
+    /// of freedom (i.e., it was built from fewer than three data points).
+    pub fn interval(&self, level: f64) -> Option<f64> {
+        if self.dof.is_nan() || self.dof <= 0.0 {
+            return None;
+        }
+
+        let t = StudentT { location: 0.0, scale: self.error, dof: self.dof };
+        Some(t.quantile(1.0 - ((1.0 - level) / 2.0)))
+    }
+
+    /// Returns the half-width of the prediction interval around the value of
+    /// the model function at the supplied x-value with the supplied confidence
+    /// level (e.g., `0.95`).
+    ///
+    /// The interval contains a new data point at the x-value with the supplied
+    /// probability (i.e., it accounts for the residual variance in addition to
+    /// the uncertainty of the model function). Returns `None` if the model has
+    /// no residual degrees of freedom.
+    pub fn prediction(&self, x: f64, level: f64) -> Option<f64> {
+        if self.dof.is_nan() || self.dof <= 0.0 {
+            return None;
+        }
+
+        let size = self.dof + 2.0;
+        let scale = self.deviation * (1.0 + (1.0 / size) + ((x - self.xmean).powi(2) / self.sxx)).sqrt();
+        let t = StudentT { location: 0.0, scale, dof: self.dof };
+        Some(t.quantile(1.0 - ((1.0 - level) / 2.0)))
+    }
+}
+
+impl FromIterator<(f64, f64)> for Model {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=(f64, f64)> {
+        Model::new(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// A power law model (`y = cxᵏ`) fitted with log-log OLS linear regression.
+///
+/// When fitted to pairs of input sizes and execution times, the exponent of
+/// this model estimates the polynomial order of the complexity of the code.
+#[derive(Copy, Clone, Debug)]
+pub struct PowerModel {
+    /// The coefficient (`c`) of the model function.
+    pub coefficient: f64,
+    /// The exponent (`k`) of the model function.
+    pub exponent: f64,
+    /// The goodness of fit of the model function (in log-log space).
+    pub r2: f64,
+}
+
+impl PowerModel {
+    /// Returns a new model for the supplied data using log-log OLS linear
+    /// regression.
+    fn new(data: &[(f64, f64)]) -> Self {
+        let model = data.iter().map(|d| (d.0.ln(), d.1.ln())).collect::<Model>();
+        Self { coefficient: model.alpha.exp(), exponent: model.beta, r2: model.r2 }
+    }
+}
+
+impl FromIterator<(f64, f64)> for PowerModel {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=(f64, f64)> {
+        PowerModel::new(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// A common algorithmic complexity class.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Complexity {
+    /// `O(1)`
+    Constant,
+    /// `O(n)`
+    Linear,
+    /// `O(n log n)`
+    Linearithmic,
+    /// `O(n²)`
+    Quadratic,
+}
+
+impl Complexity {
+    /// The complexity classes in order of increasing growth.
+    pub const ALL: [Complexity; 4] = [
+        Complexity::Constant,
+        Complexity::Linear,
+        Complexity::Linearithmic,
+        Complexity::Quadratic,
+    ];
+
+    /// Returns the value of the growth function of this complexity class for
+    /// the supplied input size.
+    pub fn apply(self, n: f64) -> f64 {
+        match self {
+            Complexity::Constant => 1.0,
+            Complexity::Linear => n,
+            Complexity::Linearithmic => n * n.max(1.0).log2(),
+            Complexity::Quadratic => n * n,
+        }
+    }
+}
+
+impl fmt::Display for Complexity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Complexity::Constant => write!(f, "O(1)"),
+            Complexity::Linear => write!(f, "O(n)"),
+            Complexity::Linearithmic => write!(f, "O(n log n)"),
+            Complexity::Quadratic => write!(f, "O(n²)"),
+        }
+    }
+}
+
+/// A complexity model (`y = cf(x)`) selected from the common complexity
+/// classes.
+///
+/// The coefficient for each complexity class is fitted with least squares
+/// regression on the relative residuals (so that the execution times for small
+/// input sizes are not dwarfed by those for large input sizes) and the
+/// complexity class with the smallest residuals is selected.
+#[derive(Copy, Clone, Debug)]
+pub struct ComplexityModel {
+    /// The complexity class (`f`) of the model function.
+    pub complexity: Complexity,
+    /// The coefficient (`c`) of the model function.
+    pub coefficient: f64,
+    /// The root mean square of the relative residuals of the model function.
+    pub error: f64,
+}
+
+impl ComplexityModel {
+    /// Returns a new model for the supplied data.
+    fn new(data: &[(f64, f64)]) -> Self {
+        let fit = |complexity: Complexity| {
+            let ratio = |&(x, y): &(f64, f64)| complexity.apply(x) / y;
+            let numerator = data.iter().map(ratio).kahan_sum();
+            let denominator = data.iter().map(|d| ratio(d).powi(2)).kahan_sum();
+            let coefficient = numerator / denominator;
+            let error = data.iter().map(|d| (1.0 - coefficient * ratio(d)).powi(2)).kahan_mean();
+            Self { complexity, coefficient, error: error.sqrt() }
+        };
+
+        // The errors are compared without their signs so that `NaN` errors
+        // (which may have either sign) are ordered after every other error.
+        Complexity::ALL.iter()
+            .map(|&c| fit(c))
+            .min_by(|a, b| a.error.abs().total_cmp(&b.error.abs()))
+            .unwrap()
+    }
+}
+
+impl FromIterator<(f64, f64)> for ComplexityModel {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=(f64, f64)> {
+        ComplexityModel::new(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// A simple linear quantile regression model.
+///
+/// Unlike OLS linear regression, which models the conditional mean of the
+/// dependent variable, quantile regression models a conditional quantile of the
+/// dependent variable (e.g., the 10th percentile). Low quantiles are much more
+/// robust than the mean to the one-sided contamination (e.g., interrupts) that
+/// is typical of execution time measurements.
+#[derive(Copy, Clone, Debug)]
+pub struct QuantileModel {
+    /// The y-intercept of the model function.
+    pub alpha: f64,
+    /// The slope of the model function.
+    pub beta: f64,
+}
+
+impl QuantileModel {
+    /// Returns a new model for the supplied data and quantile (e.g., `0.1` for
+    /// the 10th percentile).
+    pub fn new(data: &[(f64, f64)], quantile: f64) -> Self {
+        // The optimal line passes through two of the data points so the optimal
+        // slope is bounded by the slopes between points with adjacent x values.
+        let mut sorted = data.to_vec();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut groups: Vec<(f64, f64, f64)> = vec![];
+        for &(x, y) in &sorted {
+            match groups.last_mut() {
+                Some(group) if group.0 == x => {
+                    group.1 = group.1.min(y);
+                    group.2 = group.2.max(y);
+                },
+                _ => groups.push((x, y, y)),
+            }
+        }
+
+        let (mut lower, mut upper) = (f64::INFINITY, f64::NEG_INFINITY);
+        for window in groups.windows(2) {
+            let dx = window[1].0 - window[0].0;
+            lower = lower.min((window[1].1 - window[0].2) / dx);
+            upper = upper.max((window[1].2 - window[0].1) / dx);
+        }
+
+        if !lower.is_finite() || !upper.is_finite() {
+            lower = 0.0;
+            upper = 0.0;
+        }
+
+        // The loss is convex in the slope when the y-intercept is optimal for
+        // that slope so the optimal slope can be found with a golden-section
+        // search.
+        let fit = |beta: f64| {
+            let mut residuals = data.iter().map(|d| d.1 - (beta * d.0)).collect::<Vec<_>>();
+            residuals.sort_by(f64::total_cmp);
+            let index = ((quantile * residuals.len() as f64).ceil() as usize).max(1) - 1;
+            let alpha = residuals.get(index).cloned().unwrap_or(f64::NAN);
+            let loss = residuals.iter()
+                .map(|r| r - alpha)
+                .map(|u| if u < 0.0 { u * (quantile - 1.0) } else { u * quantile })
+                .kahan_sum();
+            (alpha, loss)
+        };
+
+        let ratio = (5.0f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..256 {
+            let width = upper - lower;
+            if width <= f64::EPSILON * lower.abs().max(upper.abs()) {
+                break;
+            }
+
+            let a = upper - (ratio * width);
+            let b = lower + (ratio * width);
+            if fit(a).1 <= fit(b).1 {
+                upper = b;
+            } else {
+                lower = a;
+            }
+        }
+
+        let beta = (lower + upper) / 2.0;
+        Self { alpha: fit(beta).0, beta }
+    }
+}
+
+/// Returns the natural logarithm of the gamma function for the supplied number.
+fn ln_gamma(x: f64) -> f64 {
+    use std::f64::consts::PI;
+
+    // Lanczos approximation (g = 7, n = 9).
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let a = COEFFICIENTS.iter().enumerate().skip(1)
+            .fold(COEFFICIENTS[0], |a, (i, c)| a + (c / (x + i as f64)));
+        let t = x + 7.5;
+        (0.5 * (2.0 * PI).ln()) + ((x + 0.5) * t.ln()) - t + a.ln()
+    }
+}
+
+/// Returns the regularized incomplete beta function for the supplied numbers.
+fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    } else if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + (a * x.ln()) + (b * (1.0 - x).ln())).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * incomplete_beta_fraction(x, a, b) / a
+    } else {
+        1.0 - (front * incomplete_beta_fraction(1.0 - x, b, a) / b)
+    }
+}
+
+/// Evaluates the continued fraction for the regularized incomplete beta
+/// function using the modified Lentz's method.
+fn incomplete_beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let clamp = |f: f64| if f.abs() < TINY { TINY } else { f };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - ((a + b) * x / (a + 1.0)));
+    let mut h = d;
+    for m in 1..=1000 {
+        let m = m as f64;
+        let numerator = m * (b - m) * x / ((a + (2.0 * m) - 1.0) * (a + (2.0 * m)));
+        d = 1.0 / clamp(1.0 + (numerator * d));
+        c = clamp(1.0 + (numerator / c));
+        h *= d * c;
+
+        let numerator = -(a + m) * (a + b + m) * x / ((a + (2.0 * m)) * (a + (2.0 * m) + 1.0));
+        d = 1.0 / clamp(1.0 + (numerator * d));
+        c = clamp(1.0 + (numerator / c));
+        h *= d * c;
+
+        if ((d * c) - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+/// A (location-scale) Student's t-distribution.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StudentT {
+    /// The location of the distribution.
+    pub location: f64,
+    /// The scale of the distribution.
+    pub scale: f64,
+    /// The degrees of freedom of the distribution.
+    pub dof: f64,
+}
+
+impl StudentT {
+    /// Returns the probability density of this distribution at the supplied value.
+    pub fn pdf(&self, x: f64) -> f64 {
+        use std::f64::consts::PI;
+        let t = (x - self.location) / self.scale;
+        let ln = ln_gamma((self.dof + 1.0) / 2.0) - ln_gamma(self.dof / 2.0)
+            - (0.5 * (self.dof * PI).ln())
+            - (((self.dof + 1.0) / 2.0) * (1.0 + (t * t / self.dof)).ln());
+        ln.exp() / self.scale
+    }
+
+    /// Returns the cumulative probability of this distribution at the supplied value.
+    pub fn cdf(&self, x: f64) -> f64 {
+        let t = (x - self.location) / self.scale;
+        let tail = 0.5 * incomplete_beta(self.dof / (self.dof + (t * t)), self.dof / 2.0, 0.5);
+        if t > 0.0 { 1.0 - tail } else { tail }
+    }
+
+    /// Returns the value at which the cumulative probability of this
+    /// distribution is the supplied probability.
+    pub fn quantile(&self, p: f64) -> f64 {
+        let standard = StudentT { location: 0.0, scale: 1.0, dof: self.dof };
+        let (mut lower, mut upper) = (-1.0, 1.0);
+        while standard.cdf(lower) > p && lower > -1e300 { lower *= 2.0; }
+        while standard.cdf(upper) < p && upper < 1e300 { upper *= 2.0; }
+        for _ in 0..256 {
+            let middle = (lower + upper) / 2.0;
+            if middle == lower || middle == upper {
+                break;
+            } else if standard.cdf(middle) < p {
+                lower = middle;
+            } else {
+                upper = middle;
+            }
+        }
+        self.location + (self.scale * (lower + upper) / 2.0)
+    }
+
+    /// Returns the central interval that contains the supplied proportion of
+    /// the probability mass of this distribution (e.g., `0.95`).
+    pub fn interval(&self, level: f64) -> (f64, f64) {
+        let tail = (1.0 - level) / 2.0;
+        (self.quantile(tail), self.quantile(1.0 - tail))
+    }
+
+    /// Returns the probability of drawing a value from this distribution that is
+    /// at least as far from the location of this distribution as the supplied
+    /// value (i.e., the two-sided p-value of the supplied value).
+    pub fn p_value(&self, x: f64) -> f64 {
+        let tail = self.cdf(x);
+        (2.0 * tail.min(1.0 - tail)).min(1.0)
+    }
+
+    /// Returns the (approximate) distribution of the difference between a value
+    /// drawn from this distribution and a value drawn independently from the
+    /// supplied distribution.
+    ///
+    /// When the distributions are the sampling distributions of two estimates,
+    /// this is the distribution used by Welch's t-test (with the degrees of
+    /// freedom approximated by the Welch–Satterthwaite equation).
+    pub fn difference(&self, other: &StudentT) -> StudentT {
+        let (a, b) = (self.scale * self.scale, other.scale * other.scale);
+        let dof = (a + b) * (a + b) / ((a * a / self.dof) + (b * b / other.dof));
+        StudentT { location: self.location - other.location, scale: (a + b).sqrt(), dof }
+    }
+
+    /// Returns the probability that a value drawn from this distribution is
+    /// less than a value drawn independently from the supplied distribution.
+    pub fn probability_less(&self, other: &StudentT) -> f64 {
+        const STEPS: usize = 1000;
+        (0..STEPS)
+            .map(|i| 1.0 - other.cdf(self.quantile((i as f64 + 0.5) / STEPS as f64)))
+            .kahan_mean()
+    }
+}
+
+/// A normal-inverse-gamma prior for a Bayesian simple linear regression model.
+///
+/// The default prior is the (improper) non-informative prior.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Prior {
+    /// The prior means of the y-intercept and slope.
+    pub mean: (f64, f64),
+    /// The prior precisions of the y-intercept and slope.
+    pub precision: (f64, f64),
+    /// The shape of the prior distribution of the noise variance.
+    pub shape: f64,
+    /// The rate of the prior distribution of the noise variance.
+    pub rate: f64,
+}
+
+/// A Bayesian simple linear regression model.
+///
+/// The model uses a conjugate normal-inverse-gamma prior which means that the
+/// marginal posterior distributions of the y-intercept and slope are Student's
+/// t-distributions.
+#[derive(Copy, Clone, Debug)]
+pub struct BayesianModel {
+    /// The posterior distribution of the y-intercept of the model function.
+    pub alpha: StudentT,
+    /// The posterior distribution of the slope of the model function.
+    pub beta: StudentT,
+}
+
+impl BayesianModel {
+    /// Returns a new model for the supplied data and prior.
+    pub fn new(data: &[(f64, f64)], prior: Prior) -> Self {
+        let n = data.len() as f64;
+        let sx = data.iter().map(|d| d.0).kahan_sum();
+        let sx2 = data.iter().map(|d| d.0 * d.0).kahan_sum();
+        let ols = Model::new(data);
+        let xmean = sx / n;
+        let sxx = data.iter().map(|d| (d.0 - xmean).powi(2)).kahan_sum();
+
+        // The posterior precision matrix is `XᵀX + Λ₀` (`nSxx` is used in
+        // place of `nΣx² - (Σx)²` to avoid catastrophic cancellation).
+        let (la, lb) = prior.precision;
+        let determinant = (n * sxx) + (la * sx2) + (lb * (n + la));
+        let inverse = ((sx2 + lb) / determinant, -sx / determinant, (n + la) / determinant);
+
+        // The posterior mean is the OLS estimate shifted towards the prior mean.
+        let (da, db) = (la * (prior.mean.0 - ols.alpha), lb * (prior.mean.1 - ols.beta));
+        let alpha = ols.alpha + (inverse.0 * da) + (inverse.1 * db);
+        let beta = ols.beta + (inverse.1 * da) + (inverse.2 * db);
+
+        let residuals = data.iter().map(|d| (d.1 - alpha - (beta * d.0)).powi(2)).kahan_sum();
+        let penalty = (la * (alpha - prior.mean.0).powi(2)) + (lb * (beta - prior.mean.1).powi(2));
+        let shape = prior.shape + (n / 2.0);
+        let rate = prior.rate + (0.5 * (residuals + penalty));
+
+        let variance = rate / shape;
+        let dof = 2.0 * shape;
+        Self {
+            alpha: StudentT { location: alpha, scale: (variance * inverse.0).sqrt(), dof },
+            beta: StudentT { location: beta, scale: (variance * inverse.2).sqrt(), dof },
+        }
+    }
+}
+
+impl FromIterator<(f64, f64)> for BayesianModel {
+    fn from_iter<I>(iter: I) -> Self where I: IntoIterator<Item=(f64, f64)> {
+        BayesianModel::new(&iter.into_iter().collect::<Vec<_>>(), Prior::default())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::approx_constant)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kahan() {
+        let numbers: &[f64] = &[10000.0, 3.14159, 2.71828];
+        assert_eq!(numbers.iter().cloned().kahan_sum(), 10005.85987);
+    }
+
+    #[test]
+    fn test_kahan_means() {
+        let numbers: &[f64] = &[1.0, 2.0, 4.0];
+        assert!((numbers.iter().cloned().kahan_geometric_mean() - 2.0).abs() < 1e-12);
+        assert!((numbers.iter().cloned().kahan_harmonic_mean() - (12.0 / 7.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_autocorrelation() {
+        let numbers: &[f64] = &[1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(autocorrelation(numbers, 1), 0.4);
+        let numbers: &[f64] = &[1.0, -1.0, 1.0, -1.0, 1.0, -1.0];
+        assert!(autocorrelation(numbers, 1) < -0.8);
+    }
+
+    #[test]
+    fn test_quantile() {
+        let numbers: &[f64] = &[1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(quantile(numbers, 0.0), 1.0);
+        assert_eq!(quantile(numbers, 0.5), 3.0);
+        assert_eq!(quantile(numbers, 0.9), 4.6);
+        assert_eq!(quantile(numbers, 1.0), 5.0);
+    }
+
+    #[test]
+    fn test_cohens_d() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = [3.0, 4.0, 5.0, 6.0, 7.0];
+        assert!((cohens_d(&a, &b) - (2.0 / 2.5f64.sqrt())).abs() < 1e-12);
+        assert!((cohens_d(&b, &a) + (2.0 / 2.5f64.sqrt())).abs() < 1e-12);
+        assert!(cohens_d(&a, &[1.0]).is_nan());
+    }
+
+    #[test]
+    fn test_moments() {
+        let numbers: &[f64] = &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let moments = numbers.iter().cloned().collect::<Moments>();
+        assert_eq!(moments.mean, 5.0);
+        assert_eq!(moments.variance, 4.0);
+        assert_eq!(moments.skewness, 0.65625);
+        assert_eq!(moments.kurtosis, -0.21875);
+    }
+
+    #[test]
+    fn test_model() {
+        let data: &[(f64, f64)] = &[
+            (1.47, 52.21), (1.50, 53.12), (1.52, 54.48),
+            (1.55, 55.84), (1.57, 57.20), (1.60, 58.57),
+            (1.63, 59.93), (1.65, 61.29), (1.68, 63.11),
+            (1.70, 64.47), (1.73, 66.28), (1.75, 68.10),
+            (1.78, 69.92), (1.80, 72.19), (1.83, 74.46),
+        ];
+
+        let model = data.iter().cloned().collect::<Model>();
+        assert_eq!(model.alpha, -39.06195591884393);
+        assert_eq!(model.beta, 61.27218654211062);
+        assert_eq!(model.r2, 0.989196922445796);
+        assert!((model.error - 1.775_922_752).abs() < 1e-9);
+        assert!((model.interval(0.95).unwrap() - (2.160_368_656 * model.error)).abs() < 1e-8);
+        assert!((model.deviation.powi(2) - 0.5762).abs() < 1e-4);
+        let prediction = 2.160_368_656 * model.deviation * (16.0f64 / 15.0).sqrt();
+        assert!((model.prediction(model.xmean, 0.95).unwrap() - prediction).abs() < 1e-8);
+        assert!(model.prediction(1.47, 0.95).unwrap() > prediction);
+
+        let model = [(1.0, 2.0), (2.0, 4.0)].iter().cloned().collect::<Model>();
+        assert_eq!(model.dof, 0.0);
+        assert_eq!(model.interval(0.95), None);
+        assert_eq!(model.prediction(1.5, 0.95), None);
+    }
+
+    #[test]
+    fn test_power_model() {
+        let model = (1..=10)
+            .map(|x| (x as f64 * 100.0, 3.0 * (x as f64 * 100.0).powi(2)))
+            .collect::<PowerModel>();
+        assert!((model.coefficient - 3.0).abs() < 1e-9);
+        assert!((model.exponent - 2.0).abs() < 1e-12);
+        assert!((model.r2 - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_complexity_model() {
+        let sizes = [16.0, 64.0, 256.0, 1024.0, 4096.0];
+        for &complexity in &Complexity::ALL {
+            let model = sizes.iter()
+                .map(|&n| (n, 3.0 * complexity.apply(n) + 10.0))
+                .collect::<ComplexityModel>();
+            assert_eq!(model.complexity, complexity);
+        }
+
+        let model = sizes.iter().map(|&n| (n, 3.0 * n * n.log2())).collect::<ComplexityModel>();
+        assert!((model.coefficient - 3.0).abs() < 1e-9);
+        assert!(model.error < 1e-9);
+
+        let model = [(16.0, f64::NAN), (64.0, 1.0)].iter().cloned().collect::<ComplexityModel>();
+        assert!(model.error.is_nan());
+    }
+
+    #[test]
+    fn test_quantile_model() {
+        let mut data = (1..=20).map(|x| (x as f64, (2.0 * x as f64) + 1.0)).collect::<Vec<_>>();
+        data[3].1 += 50.0;
+        data[11].1 += 75.0;
+        data[17].1 += 100.0;
+
+        let model = QuantileModel::new(&data, 0.1);
+        assert!((model.alpha - 1.0).abs() < 1e-6);
+        assert!((model.beta - 2.0).abs() < 1e-6);
+
+        data[5].1 = f64::NAN;
+        QuantileModel::new(&data, 0.1);
+    }
+
+    #[test]
+    fn test_student_t() {
+        let cauchy = StudentT { location: 0.0, scale: 1.0, dof: 1.0 };
+        assert!((cauchy.cdf(1.0) - 0.75).abs() < 1e-12);
+        assert!((cauchy.pdf(0.0) - std::f64::consts::FRAC_1_PI).abs() < 1e-12);
+
+        let t = StudentT { location: 1.0, scale: 2.0, dof: 10.0 };
+        assert!((t.quantile(0.975) - (1.0 + (2.0 * 2.228_138_851_986_274))).abs() < 1e-9);
+        assert!((t.probability_less(&t) - 0.5).abs() < 1e-9);
+        assert!((t.p_value(1.0 + (2.0 * 2.228_138_851_986_274)) - 0.05).abs() < 1e-9);
+        assert!((cauchy.p_value(-1.0) - 0.5).abs() < 1e-12);
+
+        let a = StudentT { location: 10.0, scale: 3.0, dof: 10.0 };
+        let b = StudentT { location: 4.0, scale: 4.0, dof: 10.0 };
+        let difference = a.difference(&b);
+        assert_eq!((difference.location, difference.scale), (6.0, 5.0));
+        assert!((difference.dof - (625.0 / 33.7)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bayesian_model() {
+        let data: &[(f64, f64)] = &[
+            (1.47, 52.21), (1.50, 53.12), (1.52, 54.48),
+            (1.55, 55.84), (1.57, 57.20), (1.60, 58.57),
+            (1.63, 59.93), (1.65, 61.29), (1.68, 63.11),
+            (1.70, 64.47), (1.73, 66.28), (1.75, 68.10),
+            (1.78, 69.92), (1.80, 72.19), (1.83, 74.46),
+        ];
+
+        let model = data.iter().cloned().collect::<BayesianModel>();
+        assert!((model.alpha.location - -39.06195591884393).abs() < 1e-9);
+        assert!((model.beta.location - 61.27218654211062).abs() < 1e-9);
+        assert_eq!(model.beta.dof, 15.0);
+
+        let faster = StudentT { location: 60.0, ..model.beta };
+        assert!(faster.probability_less(&model.beta) > 0.5);
+    }
+}
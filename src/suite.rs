@@ -0,0 +1,419 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Programmatic collection of benchmark results.
+
+use std::fmt::{self, Write};
+
+use crate::{Analysis, Options, Sample, SkipReason, Teardown, bench_impl, measure_with_source_impl, skip};
+use crate::metadata;
+use crate::system;
+use crate::statistics::{self, Moments};
+use crate::utility::{json_number, json_string};
+
+/// A function that is run before or after the benchmarks in a suite.
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// The result of a benchmark in a suite.
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    /// The name of the benchmark.
+    pub name: String,
+    /// The analysis of the samples.
+    pub analysis: Analysis,
+    /// The samples.
+    pub samples: Vec<Sample>,
+}
+
+/// A collection of benchmark results.
+///
+/// Unlike [`bench`](../fn.bench.html) and the other benchmarking functions, a
+/// suite keeps the results of the benchmarks it runs so that they can be
+/// processed (e.g., sorted, aggregated, or exported) after the benchmarks have
+/// been run.
+///
+/// ```no_run
+/// use microbench::{Options, retain};
+/// use microbench::suite::{Suite};
+///
+/// let mut suite = Suite::new(Options::default()).quiet(true);
+/// suite.bench("iter", || (0..retain(1024u64)).sum::<u64>());
+/// suite.bench("fold", || (0..retain(1024u64)).fold(0, |a, b| a + b));
+///
+/// let mut measurements = suite.finish();
+/// measurements.sort_by(|a, b| a.analysis.beta.0.partial_cmp(&b.analysis.beta.0).unwrap());
+/// println!("fastest: {}", measurements[0].name);
+/// ```
+pub struct Suite {
+    options: Options,
+    quiet: bool,
+    measurements: Vec<Measurement>,
+    skipped: Vec<(String, SkipReason)>,
+    setup: Option<Hook>,
+    teardown: Option<Hook>,
+    guard: Option<Teardown<Hook>>,
+}
+
+impl Suite {
+    /// Constructs a new empty `Suite`.
+    pub fn new(options: Options) -> Self {
+        Suite {
+            options,
+            quiet: false,
+            measurements: vec![],
+            skipped: vec![],
+            setup: None,
+            teardown: None,
+            guard: None,
+        }
+    }
+
+    /// Sets whether the results of benchmarks are not printed.
+    ///
+    /// **Default:** `false`
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Sets the function that is run once before the first benchmark in this
+    /// suite is run.
+    ///
+    /// This is useful for expensive preparation shared by the benchmarks in this
+    /// suite (e.g., starting a database server or warming a file cache) which is
+    /// not included in the measurements.
+    pub fn setup(mut self, f: impl FnOnce() + Send + 'static) -> Self {
+        self.setup = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the function that is run once after the last benchmark in this
+    /// suite is run (when this suite is finished or dropped).
+    ///
+    /// The supplied function is only run if a benchmark in this suite was run.
+    /// It is also run if a benchmark in this suite panics or if the process is
+    /// interrupted (see
+    /// [`handle_interrupts`](../system/fn.handle_interrupts.html)).
+    pub fn teardown(mut self, f: impl FnOnce() + Send + 'static) -> Self {
+        self.teardown = Some(Box::new(f));
+        self
+    }
+
+    /// Returns the benchmarking options used by this suite.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Returns the results of the benchmarks that have been run so far.
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    /// Benchmarks the supplied function, records the results, and prints the
+    /// results (unless quiet).
+    ///
+    /// See [`bench`](../fn.bench.html) for more information.
+    pub fn bench<T>(&mut self, name: &str, f: impl FnMut() -> T) -> &Measurement {
+        self.bench_with(name, |o| o, f)
+    }
+
+    /// Benchmarks the supplied function with some of the benchmarking options
+    /// overridden, records the results, and prints the results (unless quiet).
+    ///
+    /// The supplied configuration function is passed the benchmarking options
+    /// used by this suite and should return the benchmarking options for the
+    /// benchmark (e.g., `|o| o.time(Duration::from_secs(10))`).
+    pub fn bench_with<T>(
+        &mut self, name: &str, configure: impl FnOnce(Options) -> Options, f: impl FnMut() -> T
+    ) -> &Measurement {
+        let options = configure(self.options);
+        self.record(&options, name, move |o| measure_with_source_impl(o, o.clock, f))
+    }
+
+    /// Records and prints (unless quiet) the results of the samples collected
+    /// by the supplied function.
+    ///
+    /// The supplied function is passed the benchmarking options used by this
+    /// suite and can use any of the measuring functions (e.g.,
+    /// [`measure_setup`](../fn.measure_setup.html)).
+    pub fn bench_samples(
+        &mut self, name: &str, f: impl FnOnce(&Options) -> Vec<Sample>
+    ) -> &Measurement {
+        let options = self.options;
+        self.record(&options, name, |o| (f(o), 0))
+    }
+
+    /// Records and prints (unless quiet) the results of the samples collected
+    /// by the supplied function with the supplied options.
+    ///
+    /// The supplied function should also return the number of times the
+    /// samples were recollected.
+    fn record(
+        &mut self, options: &Options, name: &str, f: impl FnOnce(&Options) -> (Vec<Sample>, u32)
+    ) -> &Measurement {
+        if let Some(setup) = self.setup.take() {
+            setup();
+        }
+        if let Some(teardown) = self.teardown.take() {
+            self.guard = Some(Teardown::new(teardown));
+        }
+
+        let (samples, analysis) = if self.quiet {
+            let (samples, _) = f(options);
+            let analysis = Analysis::new(&samples);
+            (samples, analysis)
+        } else {
+            bench_impl(options, name, || f(options))
+        };
+
+        self.measurements.push(Measurement { name: name.into(), analysis, samples });
+        if system::interrupted() {
+            self.guard = None;
+        }
+        self.measurements.last().unwrap()
+    }
+
+    /// Returns the names of the benchmarks that have been skipped so far and
+    /// the reasons they were skipped.
+    pub fn skipped(&self) -> &[(String, SkipReason)] {
+        &self.skipped
+    }
+
+    /// Records that the benchmark with the supplied name was skipped for the
+    /// supplied reason and prints this (unless quiet).
+    ///
+    /// Skipped benchmarks are not included in the results returned by
+    /// [`finish`](#method.finish). See [`skip`](../fn.skip.html) for more
+    /// information.
+    pub fn skip(&mut self, name: &str, reason: SkipReason) {
+        if !self.quiet {
+            skip(&self.options, name, &reason);
+        }
+        self.skipped.push((name.into(), reason));
+    }
+
+    /// Returns the results of the benchmarks that have been run so far in the
+    /// Prometheus text exposition format.
+    ///
+    /// Each result is a sample of each of the following gauges labeled with the
+    /// name of the benchmark (`benchmark`) and the run tags (see
+    /// [`set_run_tag`](../metadata/fn.set_run_tag.html)):
+    ///
+    /// * `microbench_time_per_iteration_nanoseconds` (the estimated execution
+    ///   time per iteration)
+    /// * `microbench_interval_nanoseconds` (the half-width of the interval
+    ///   around the estimated execution time, see
+    ///   [`Options::confidence`](../struct.Options.html#method.confidence))
+    /// * `microbench_r2` (the goodness of fit)
+    /// * `microbench_samples` (the number of samples)
+    ///
+    /// Run tags with keys that are not valid label names have the invalid
+    /// characters replaced with underscores and run tags with label names that
+    /// collide with other labels (e.g., `benchmark`) are prefixed with `tag_`.
+    ///
+    /// The output can be pushed to a Prometheus Pushgateway (e.g., with
+    /// `curl --data-binary @results.prom <pushgateway>/metrics/job/<job>`).
+    pub fn to_prometheus(&self) -> String {
+        type Value = fn(&Suite, &Measurement) -> f64;
+        let gauges: [(&str, &str, Value); 4] = [
+            (
+                "microbench_time_per_iteration_nanoseconds",
+                "The estimated execution time per iteration.",
+                |_, m| m.analysis.beta.0,
+            ),
+            (
+                "microbench_interval_nanoseconds",
+                "The half-width of the interval around the estimated execution time.",
+                |s, m| m.analysis.interval(s.options.confidence).map_or(f64::NAN, |i| i.0),
+            ),
+            ("microbench_r2", "The goodness of fit of the estimated execution time.", |_, m| m.analysis.r2),
+            ("microbench_samples", "The number of samples.", |_, m| m.samples.len() as f64),
+        ];
+
+        // Tags with label names that would collide with other labels (or are
+        // reserved by Prometheus) are prefixed until they are unique.
+        let mut names = vec!["benchmark".to_string()];
+        let tags = metadata::current().iter()
+            .map(|(k, v)| {
+                let mut name = prometheus_name(k);
+                while names.contains(&name) || name.starts_with("__") {
+                    name.insert_str(0, "tag_");
+                }
+                names.push(name.clone());
+                format!(",{}={}", name, prometheus_string(v))
+            })
+            .collect::<String>();
+
+        let mut output = String::new();
+        for (name, help, value) in &gauges {
+            writeln!(output, "# HELP {} {}", name, help).unwrap();
+            writeln!(output, "# TYPE {} gauge", name).unwrap();
+            for measurement in &self.measurements {
+                let benchmark = prometheus_string(&measurement.name);
+                let value = prometheus_number(value(self, measurement));
+                writeln!(output, "{}{{benchmark={}{}}} {}", name, benchmark, tags, value).unwrap();
+            }
+        }
+        output
+    }
+
+    /// Returns the results of the benchmarks that have been run so far in the
+    /// JSON format exported by `hyperfine` (`hyperfine --export-json`).
+    ///
+    /// Each benchmark is a result with the name of the benchmark as the
+    /// command and the execution times per iteration of the samples (in
+    /// seconds) as the times. The mean, standard deviation, median, minimum,
+    /// and maximum are those of these times (so the mean may differ from the
+    /// estimated execution time). User and system times and exit codes are not
+    /// included since they are not measured.
+    ///
+    /// This allows the scripts written for `hyperfine` results (e.g.,
+    /// `plot_whisker.py`) to be used with the results of a suite.
+    pub fn to_hyperfine(&self) -> String {
+        let results = self.measurements.iter().map(|m| {
+            let mut times = m.samples.iter()
+                .map(|s| s.elapsed.0 as f64 / s.iterations as f64 / 1e9)
+                .collect::<Vec<_>>();
+            let Moments { mean, variance, .. } = times.iter().cloned().collect::<Moments>();
+            let n = times.len() as f64;
+            let json = times.iter().map(|t| json_number(*t)).collect::<Vec<_>>().join(",");
+            times.sort_by(f64::total_cmp);
+            format!(
+                concat!(
+                    r#"{{"command":{},"mean":{},"stddev":{},"median":{},"min":{},"max":{},"#,
+                    r#""times":[{}]}}"#,
+                ),
+                json_string(&m.name),
+                json_number(mean),
+                json_number((variance * n / (n - 1.0)).sqrt()),
+                json_number(statistics::quantile(&times, 0.5)),
+                json_number(times.first().cloned().unwrap_or(f64::NAN)),
+                json_number(times.last().cloned().unwrap_or(f64::NAN)),
+                json,
+            )
+        });
+        format!(r#"{{"results":[{}]}}"#, results.collect::<Vec<_>>().join(","))
+    }
+
+    /// Returns the results of the benchmarks that have been run.
+    pub fn finish(self) -> Vec<Measurement> {
+        self.measurements
+    }
+}
+
+impl fmt::Debug for Suite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Suite")
+            .field("options", &self.options)
+            .field("quiet", &self.quiet)
+            .field("measurements", &self.measurements)
+            .field("skipped", &self.skipped)
+            .finish()
+    }
+}
+
+/// Returns the supplied string as a valid Prometheus label name.
+fn prometheus_name(name: &str) -> String {
+    let mut name = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Returns the supplied string as a Prometheus label value.
+fn prometheus_string(string: &str) -> String {
+    let escaped = string.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/// Returns the supplied floating-point number as a Prometheus sample value.
+fn prometheus_number(number: f64) -> String {
+    match number {
+        n if n.is_nan() => "NaN".into(),
+        n if n == f64::INFINITY => "+Inf".into(),
+        n if n == f64::NEG_INFINITY => "-Inf".into(),
+        n => n.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::time::{Nanoseconds};
+
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration};
+
+    fn measurement(name: &str) -> Measurement {
+        let samples = (1..=4)
+            .map(|i| Sample { iterations: i, elapsed: Nanoseconds(i * 10) })
+            .collect::<Vec<_>>();
+        Measurement { name: name.into(), analysis: Analysis::new(&samples), samples }
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let mut suite = Suite::new(Options::default());
+        suite.measurements.push(measurement("sum/iter"));
+        suite.measurements.push(measurement("say \"hi\"\\\n"));
+
+        let tags = [("benchmark", "x"), ("tag_benchmark", "y"), ("git-sha", "abc"), ("__name__", "z")];
+        let output = metadata::with_tags(&tags, || suite.to_prometheus());
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4 * 4);
+        let gauge = "microbench_time_per_iteration_nanoseconds";
+        assert_eq!(lines[0], format!("# HELP {} The estimated execution time per iteration.", gauge));
+        assert_eq!(lines[1], format!("# TYPE {} gauge", gauge));
+
+        let labels = r#"tag_benchmark="x",tag_tag_benchmark="y",git_sha="abc",tag___name__="z""#;
+        assert_eq!(lines[2], format!(r#"{}{{benchmark="sum/iter",{}}} 10"#, gauge, labels));
+        assert_eq!(lines[3], format!(r#"{}{{benchmark="say \"hi\"\\\n",{}}} 10"#, gauge, labels));
+        assert_eq!(lines[14], format!(r#"microbench_samples{{benchmark="sum/iter",{}}} 4"#, labels));
+    }
+
+    #[test]
+    fn test_hooks() {
+        let options = Options::default().time(Duration::from_millis(1)).warmup(Duration::ZERO);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hooks = |suite: Suite| {
+            let (setup, teardown) = (calls.clone(), calls.clone());
+            suite
+                .setup(move || { setup.fetch_add(1, Ordering::SeqCst); })
+                .teardown(move || { teardown.fetch_add(10, Ordering::SeqCst); })
+        };
+
+        let suite = hooks(Suite::new(options).quiet(true));
+        drop(suite);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let mut suite = hooks(Suite::new(options).quiet(true));
+        suite.bench("a", || ());
+        suite.bench("b", || ());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        suite.finish();
+        assert_eq!(calls.load(Ordering::SeqCst), 11);
+
+        let mut suite = hooks(Suite::new(options).quiet(true));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| { suite.bench("a", || panic!()); }));
+        assert!(result.is_err());
+        drop(suite);
+        assert_eq!(calls.load(Ordering::SeqCst), 22);
+    }
+}
@@ -0,0 +1,803 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! System-related utilities.
+
+use std::env;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration};
+
+use crate::{Bytes};
+use crate::json::{Value};
+use crate::utility::{json_string};
+
+/// Whether the current process has been interrupted (e.g., with Ctrl-C).
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Returns the resident set size (RSS) of the current process.
+///
+/// Returns `None` if the RSS could not be determined on this platform.
+pub fn rss() -> Option<Bytes> {
+    memory().map(|m| m.0)
+}
+
+/// Returns the peak resident set size (RSS) of the current process.
+///
+/// On Linux, the peak RSS can be reset with
+/// [`reset_peak_rss`](fn.reset_peak_rss.html). On other platforms, the peak
+/// RSS is the peak RSS over the lifetime of the process.
+///
+/// Returns `None` if the peak RSS could not be determined on this platform.
+pub fn peak_rss() -> Option<Bytes> {
+    memory().map(|m| m.1)
+}
+
+/// Resets the peak resident set size (RSS) of the current process to the
+/// current RSS of the current process.
+///
+/// Returns whether the peak RSS could be reset on this platform (only Linux is
+/// supported).
+pub fn reset_peak_rss() -> bool {
+    #[cfg(target_os="linux")]
+    { std::fs::write("/proc/self/clear_refs", "5").is_ok() }
+    #[cfg(not(target_os="linux"))]
+    { false }
+}
+
+/// Installs a handler for interrupts (e.g., Ctrl-C) that stops benchmarks
+/// gracefully.
+///
+/// After an interrupt, the benchmark that is running stops collecting samples
+/// and prints the results for the samples it has already collected and then the
+/// process exits with exit code `130`. Functions that only measure (e.g.,
+/// [`measure`](../fn.measure.html)) return the samples they have collected
+/// instead and [`interrupted`](fn.interrupted.html) can be used to check for
+/// an interrupt. A second interrupt exits the process immediately.
+///
+/// Returns whether the handler could be installed on this platform (only Unix
+/// and Windows are supported).
+pub fn handle_interrupts() -> bool {
+    #[cfg(unix)]
+    unsafe {
+        extern "C" fn handler(_: libc::c_int) {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                unsafe { libc::_exit(130) };
+            }
+        }
+
+        let handler = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::signal(libc::SIGINT, handler) != libc::SIG_ERR
+    }
+    #[cfg(windows)]
+    unsafe {
+        extern "system" fn handler(_: u32) -> i32 {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+            1
+        }
+
+        windows::SetConsoleCtrlHandler(Some(handler), 1) != 0
+    }
+    #[cfg(not(any(unix, windows)))]
+    { false }
+}
+
+/// Returns whether the current process has been interrupted since the interrupt
+/// handler was installed.
+///
+/// See [`handle_interrupts`](fn.handle_interrupts.html) for more information.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// A guard that restores the previous CPU affinity of the current thread when
+/// dropped.
+///
+/// See [`pin_to_core`](fn.pin_to_core.html) for more information.
+#[derive(Debug)]
+pub struct Pinned {
+    #[cfg(target_os="linux")]
+    previous: libc::cpu_set_t,
+    #[cfg(windows)]
+    previous: usize,
+}
+
+impl Drop for Pinned {
+    fn drop(&mut self) {
+        #[cfg(target_os="linux")]
+        unsafe {
+            let size = std::mem::size_of::<libc::cpu_set_t>();
+            libc::sched_setaffinity(0, size, &self.previous);
+        }
+        #[cfg(windows)]
+        unsafe {
+            windows::SetThreadAffinityMask(windows::GetCurrentThread(), self.previous);
+        }
+    }
+}
+
+/// Restricts the current thread to running on the supplied CPU core until the
+/// returned guard is dropped.
+///
+/// Returns `None` if the current thread could not be pinned to the supplied CPU
+/// core (e.g., the core does not exist) or if pinning is not supported on this
+/// platform (only Linux and Windows are supported).
+pub fn pin_to_core(core: usize) -> Option<Pinned> {
+    #[cfg(target_os="linux")]
+    unsafe {
+        let size = std::mem::size_of::<libc::cpu_set_t>();
+        let mut previous = std::mem::zeroed::<libc::cpu_set_t>();
+        if core >= libc::CPU_SETSIZE as usize || libc::sched_getaffinity(0, size, &mut previous) != 0 {
+            return None;
+        }
+
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, size, &set) == 0 {
+            Some(Pinned { previous })
+        } else {
+            None
+        }
+    }
+    #[cfg(windows)]
+    unsafe {
+        if core >= usize::BITS as usize {
+            return None;
+        }
+
+        let previous = windows::SetThreadAffinityMask(windows::GetCurrentThread(), 1 << core);
+        if previous != 0 { Some(Pinned { previous }) } else { None }
+    }
+    #[cfg(not(any(target_os="linux", windows)))]
+    { let _ = core; None }
+}
+
+/// A guard that restores the previous scheduling priority of the current
+/// thread when dropped.
+///
+/// See [`elevate_priority`](fn.elevate_priority.html) for more information.
+#[derive(Debug)]
+pub struct Elevated {
+    #[cfg(any(unix, windows))]
+    previous: i32,
+}
+
+impl Drop for Elevated {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::setpriority(libc::PRIO_PROCESS as _, 0, self.previous);
+        }
+        #[cfg(windows)]
+        unsafe {
+            windows::SetThreadPriority(windows::GetCurrentThread(), self.previous);
+        }
+    }
+}
+
+/// Raises the scheduling priority of the current thread (or process on some
+/// Unix platforms) to the highest priority until the returned guard is
+/// dropped.
+///
+/// Returns `None` if the scheduling priority could not be raised (e.g., on
+/// Unix platforms, raising the priority usually requires elevated privileges)
+/// or if this is not supported on this platform.
+pub fn elevate_priority() -> Option<Elevated> {
+    #[cfg(unix)]
+    unsafe {
+        // `getpriority` can legitimately return `-1` so errors are not checked.
+        let previous = libc::getpriority(libc::PRIO_PROCESS as _, 0);
+        if libc::setpriority(libc::PRIO_PROCESS as _, 0, -20) == 0 {
+            Some(Elevated { previous })
+        } else {
+            None
+        }
+    }
+    #[cfg(windows)]
+    unsafe {
+        let thread = windows::GetCurrentThread();
+        let previous = windows::GetThreadPriority(thread);
+        if previous != windows::THREAD_PRIORITY_ERROR_RETURN
+            && windows::SetThreadPriority(thread, windows::THREAD_PRIORITY_HIGHEST) != 0
+        {
+            Some(Elevated { previous })
+        } else {
+            None
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    { None }
+}
+
+/// Returns whether this crate (and so, almost always, the benchmarks using it)
+/// was compiled with optimizations.
+///
+/// Benchmarks compiled without optimizations (e.g., with `cargo run` instead of
+/// `cargo run --release`) are often orders of magnitude slower than optimized
+/// code and their results are rarely meaningful.
+pub fn optimized() -> bool {
+    !cfg!(microbench_unoptimized)
+}
+
+/// Information about the system benchmarks are run on.
+///
+/// This information is recorded along with results (e.g., in baselines) so
+/// that results recorded on different systems can be identified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Info {
+    /// The architecture of the CPU (e.g., `x86_64`).
+    pub arch: String,
+    /// The number of logical CPU cores available to this process.
+    pub cores: usize,
+    /// The model name of the CPU, if known.
+    pub cpu: Option<String>,
+    /// The optimization level this crate was compiled with (e.g., `3`).
+    pub opt_level: String,
+    /// The operating system (e.g., `linux`).
+    pub os: String,
+}
+
+impl Info {
+    /// Returns the supplied JSON object as system information.
+    pub(crate) fn from_json(value: &Value) -> Option<Self> {
+        let string = |key| value.get(key).and_then(Value::as_str).map(String::from);
+        Some(Info {
+            arch: string("arch")?,
+            cores: value.get("cores").and_then(Value::as_f64)? as usize,
+            cpu: string("cpu"),
+            opt_level: string("opt_level")?,
+            os: string("os")?,
+        })
+    }
+
+    /// Returns this system information as a JSON object.
+    pub(crate) fn to_json(&self) -> String {
+        let option = |o: &Option<String>| o.as_deref().map_or("null".into(), json_string);
+        format!(
+            r#"{{"arch":{},"cores":{},"cpu":{},"opt_level":{},"os":{}}}"#,
+            json_string(&self.arch),
+            self.cores,
+            option(&self.cpu),
+            json_string(&self.opt_level),
+            json_string(&self.os),
+        )
+    }
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cpu = self.cpu.as_deref().unwrap_or("unknown CPU");
+        write!(
+            f, "{} ({} cores), {} {}, opt-level {}",
+            cpu, self.cores, self.os, self.arch, self.opt_level,
+        )
+    }
+}
+
+/// Returns information about the system benchmarks are run on.
+pub fn info() -> &'static Info {
+    static INFO: OnceLock<Info> = OnceLock::new();
+    INFO.get_or_init(|| Info {
+        arch: env::consts::ARCH.into(),
+        cores: thread::available_parallelism().map_or(1, |c| c.get()),
+        cpu: cpu(),
+        opt_level: env!("MICROBENCH_OPT_LEVEL").into(),
+        os: env::consts::OS.into(),
+    })
+}
+
+/// A CPU frequency scaling configuration that is likely to make the execution
+/// times of benchmarks vary from run to run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrequencyScaling {
+    /// A CPU frequency scaling governor other than `performance` is in use
+    /// (Linux).
+    Governor(String),
+    /// A power plan other than High performance or Ultimate Performance is
+    /// active (Windows).
+    PowerPlan(String),
+    /// Turbo boost is enabled.
+    Turbo,
+}
+
+impl fmt::Display for FrequencyScaling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrequencyScaling::Governor(governor) => write!(
+                f, "the `{}` CPU frequency scaling governor is in use (use `performance` instead)", governor,
+            ),
+            FrequencyScaling::PowerPlan(plan) => write!(
+                f, "the `{}` power plan is active (use `High performance` instead)", plan,
+            ),
+            FrequencyScaling::Turbo => write!(f, "turbo boost is enabled (disable it for stable results)"),
+        }
+    }
+}
+
+/// Returns the CPU frequency scaling configurations on this machine that are
+/// likely to make the execution times of benchmarks unstable.
+///
+/// On Linux, the scaling governors of the CPU cores and the turbo boost state
+/// (for the `intel_pstate` and `acpi-cpufreq` drivers) are checked. On Windows,
+/// the active power plan and its processor performance boost mode are checked.
+/// Nothing is checked on other platforms.
+pub fn frequency_scaling() -> Vec<FrequencyScaling> {
+    #[cfg(target_os="linux")]
+    {
+        use std::fs;
+
+        let read = |path: &str| fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+
+        let mut governors = cpu_files("cpufreq/scaling_governor");
+        governors.retain(|g| g != "performance");
+        governors.sort();
+        governors.dedup();
+
+        let mut scaling = governors.into_iter().map(FrequencyScaling::Governor).collect::<Vec<_>>();
+        let turbo = read("/sys/devices/system/cpu/intel_pstate/no_turbo").as_deref() == Some("0")
+            || read("/sys/devices/system/cpu/cpufreq/boost").as_deref() == Some("1");
+        if turbo {
+            scaling.push(FrequencyScaling::Turbo);
+        }
+        scaling
+    }
+    #[cfg(windows)]
+    {
+        use std::process::{Command};
+
+        // The GUIDs of the High performance and Ultimate Performance plans.
+        const PERFORMANCE: &[&str] = &["8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c", "e9a42b02-d5df-448d-aa00-03f14749eb61"];
+
+        let powercfg = |arguments: &[&str]| {
+            let output = Command::new("powercfg").args(arguments).output().ok()?;
+            if output.status.success() { String::from_utf8(output.stdout).ok() } else { None }
+        };
+
+        let mut scaling = vec![];
+        if let Some(scheme) = powercfg(&["/getactivescheme"]) {
+            let scheme = scheme.to_ascii_lowercase();
+            if !PERFORMANCE.iter().any(|g| scheme.contains(g)) {
+                let name = scheme.rsplit_once('(').and_then(|(_, n)| n.split_once(')')).map(|(n, _)| n);
+                scaling.push(FrequencyScaling::PowerPlan(name.unwrap_or("unknown").into()));
+            }
+        }
+
+        // The first index is the current AC (i.e., plugged in) setting.
+        let boost = powercfg(&["/query", "scheme_current", "sub_processor", "perfboostmode"]);
+        let index = boost.as_deref().and_then(|b| b.split_whitespace().find(|w| w.starts_with("0x")));
+        if index.and_then(|i| u32::from_str_radix(&i[2..], 16).ok()).is_some_and(|i| i != 0) {
+            scaling.push(FrequencyScaling::Turbo);
+        }
+        scaling
+    }
+    #[cfg(not(any(target_os="linux", windows)))]
+    { vec![] }
+}
+
+/// A virtualized or containerized environment, which is likely to make the
+/// execution times of benchmarks noisy (e.g., because of unreliable timers or
+/// time stolen by other virtual machines).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Virtualization {
+    /// A hypervisor is present (with the supplied vendor, if known).
+    Hypervisor(Option<String>),
+    /// A container runtime is in use (e.g., `docker`).
+    Container(String),
+    /// The CPU time available is limited to the supplied number of cores (e.g.,
+    /// by a cgroup).
+    CpuLimit(f64),
+}
+
+impl fmt::Display for Virtualization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Virtualization::Hypervisor(Some(vendor)) => write!(
+                f, "running under a hypervisor ({}), timers and steal time may make results noisy", vendor,
+            ),
+            Virtualization::Hypervisor(None) => write!(
+                f, "running under a hypervisor, timers and steal time may make results noisy",
+            ),
+            Virtualization::Container(runtime) => write!(
+                f, "running in a container ({}), other containers may make results noisy", runtime,
+            ),
+            Virtualization::CpuLimit(cores) => write!(
+                f, "CPU time is limited to {:.2} cores, throttling may make results noisy", cores,
+            ),
+        }
+    }
+}
+
+/// Returns the virtualized or containerized environments this process is
+/// running in.
+///
+/// On x86 and x86-64 targets, the hypervisor bit reported by `CPUID` (and the
+/// hypervisor vendor) is checked. On Linux, the container runtime (Docker,
+/// Podman, Kubernetes, or LXC) and the CPU limits of the cgroup of this process
+/// (v1 or v2) are also checked.
+pub fn virtualization() -> Vec<Virtualization> {
+    let mut virtualization = vec![];
+    if let Some(vendor) = hypervisor() {
+        virtualization.push(Virtualization::Hypervisor(vendor));
+    }
+
+    #[cfg(target_os="linux")]
+    {
+        use std::fs;
+        use std::path::{Path};
+
+        let cgroup = fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+        let runtime = if Path::new("/.dockerenv").exists() {
+            Some("docker")
+        } else if Path::new("/run/.containerenv").exists() {
+            Some("podman")
+        } else {
+            let runtimes = [("kubepods", "kubernetes"), ("docker", "docker"), ("lxc", "lxc")];
+            runtimes.iter().find(|(p, _)| cgroup.contains(p)).map(|(_, r)| *r)
+        };
+        if let Some(runtime) = runtime {
+            virtualization.push(Virtualization::Container(runtime.into()));
+        }
+
+        // cgroup v2 (`<quota> <period>`) or cgroup v1 (separate files).
+        let read = |path: &str| fs::read_to_string(path).ok();
+        let limit = match read("/sys/fs/cgroup/cpu.max") {
+            Some(max) => {
+                let mut fields = max.split_whitespace().map(|f| f.parse::<f64>().ok());
+                fields.next().flatten().zip(fields.next().flatten())
+            },
+            None => {
+                let quota = read("/sys/fs/cgroup/cpu/cpu.cfs_quota_us").and_then(|q| q.trim().parse::<f64>().ok());
+                let period = read("/sys/fs/cgroup/cpu/cpu.cfs_period_us").and_then(|p| p.trim().parse::<f64>().ok());
+                quota.filter(|q| *q > 0.0).zip(period)
+            },
+        };
+        if let Some((quota, period)) = limit.filter(|(_, p)| *p > 0.0) {
+            virtualization.push(Virtualization::CpuLimit(quota / period));
+        }
+    }
+
+    virtualization
+}
+
+/// Returns the CPU core the current thread is running on.
+///
+/// Returns `None` if the core could not be determined on this platform
+/// (currently only supported on Linux). Unless the current thread is pinned to
+/// a core (see [`pin_to_core`](fn.pin_to_core.html)), the thread may be moved
+/// to another core at any time.
+pub fn current_core() -> Option<usize> {
+    #[cfg(target_os="linux")]
+    {
+        let core = unsafe { libc::sched_getcpu() };
+        if core >= 0 { Some(core as usize) } else { None }
+    }
+    #[cfg(not(target_os="linux"))]
+    { None }
+}
+
+/// Returns the current frequency of the supplied CPU core (in MHz).
+///
+/// Returns `None` if the frequency could not be determined on this platform
+/// (currently only supported on Linux with a CPU frequency scaling driver).
+pub fn cpu_frequency(core: usize) -> Option<f64> {
+    #[cfg(target_os="linux")]
+    {
+        let frequency = cpu_file(core, "cpufreq/scaling_cur_freq")?.parse::<u64>().ok()?;
+        Some(frequency as f64 / 1000.0)
+    }
+    #[cfg(not(target_os="linux"))]
+    { let _ = core; None }
+}
+
+/// Returns the number of times the supplied CPU core (and its package) has
+/// been thermally throttled since the system started.
+///
+/// Returns `None` if the count could not be determined on this platform
+/// (currently only supported on Linux for Intel CPUs).
+pub fn throttle_count(core: usize) -> Option<u64> {
+    #[cfg(target_os="linux")]
+    {
+        let count = |file| cpu_file(core, file).and_then(|c| c.parse::<u64>().ok());
+        let package = count("thermal_throttle/package_throttle_count").unwrap_or(0);
+        Some(count("thermal_throttle/core_throttle_count")? + package)
+    }
+    #[cfg(not(target_os="linux"))]
+    { let _ = core; None }
+}
+
+/// Returns the contents (without surrounding whitespace) of the supplied file
+/// in the `sysfs` directory of the supplied CPU core.
+#[cfg(target_os="linux")]
+fn cpu_file(core: usize, file: &str) -> Option<String> {
+    let path = format!("/sys/devices/system/cpu/cpu{}/{}", core, file);
+    std::fs::read_to_string(path).ok().map(|c| c.trim().into())
+}
+
+/// Returns the contents (without surrounding whitespace) of the supplied file
+/// in the `sysfs` directory of each of the CPU cores that have the file.
+#[cfg(target_os="linux")]
+fn cpu_files(file: &str) -> Vec<String> {
+    std::fs::read_dir("/sys/devices/system/cpu").into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let core = e.file_name().to_str().and_then(|n| n.strip_prefix("cpu")).map(str::parse::<u32>);
+            core.is_some_and(|c| c.is_ok())
+        })
+        .filter_map(|e| std::fs::read_to_string(e.path().join(file)).ok())
+        .map(|c| c.trim().into())
+        .collect()
+}
+
+/// The CPU frequencies observed (and thermal throttling that occurred) while a
+/// [`FrequencyMonitor`](struct.FrequencyMonitor.html) was running.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frequencies {
+    /// The CPU frequencies sampled (in MHz).
+    pub samples: Vec<f64>,
+    /// The number of times the CPU was thermally throttled, if known.
+    pub throttles: Option<u64>,
+}
+
+impl Frequencies {
+    /// Returns the mean CPU frequencies (in MHz) sampled during the first half
+    /// and during the second half of the monitored period.
+    ///
+    /// Returns `None` if fewer than four frequencies were sampled.
+    pub fn halves(&self) -> Option<(f64, f64)> {
+        if self.samples.len() < 4 {
+            return None;
+        }
+
+        let mean = |s: &[f64]| s.iter().sum::<f64>() / s.len() as f64;
+        let (first, second) = self.samples.split_at(self.samples.len() / 2);
+        Some((mean(first), mean(second)))
+    }
+
+    /// Returns the relative drop in the mean CPU frequency from the first half
+    /// to the second half of the monitored period (e.g., `0.1` for a 10% drop).
+    ///
+    /// Returns `None` if fewer than four frequencies were sampled.
+    pub fn drop(&self) -> Option<f64> {
+        self.halves().map(|(first, second)| 1.0 - (second / first))
+    }
+}
+
+/// Samples the frequency of a CPU core on a background thread (e.g., while a
+/// benchmark is run on that core) to detect thermal throttling.
+///
+/// See [`cpu_frequency`](fn.cpu_frequency.html) and
+/// [`throttle_count`](fn.throttle_count.html) for the supported platforms. No
+/// background thread is started on other platforms. The background thread is
+/// stopped when the monitor is stopped or dropped.
+#[derive(Debug)]
+pub struct FrequencyMonitor {
+    core: usize,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Vec<f64>>>,
+    throttles: Option<u64>,
+}
+
+impl FrequencyMonitor {
+    /// Starts sampling the frequency of the supplied CPU core at the supplied
+    /// interval.
+    pub fn start(core: usize, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = cpu_frequency(core).map(|first| {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut samples = vec![first];
+                while !stop.load(Ordering::Relaxed) {
+                    thread::park_timeout(interval);
+                    samples.extend(cpu_frequency(core));
+                }
+                samples
+            })
+        });
+        FrequencyMonitor { core, stop, thread, throttles: throttle_count(core) }
+    }
+
+    /// Stops sampling the CPU frequency and returns the sampled frequencies.
+    pub fn stop(mut self) -> Frequencies {
+        let samples = self.join();
+        let throttles = throttle_count(self.core).zip(self.throttles).map(|(a, b)| a.saturating_sub(b));
+        Frequencies { samples, throttles }
+    }
+
+    /// Stops the background thread (if it is running) and returns the sampled
+    /// frequencies.
+    fn join(&mut self) -> Vec<f64> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.take().map_or(vec![], |t| {
+            t.thread().unpark();
+            t.join().unwrap_or_default()
+        })
+    }
+}
+
+impl Drop for FrequencyMonitor {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// Returns the vendor of the hypervisor (if known) if a hypervisor is present.
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+fn hypervisor() -> Option<Option<String>> {
+    // The hypervisor bit (bit 31 of `ECX` for leaf 1) is reserved for use by
+    // hypervisors and is always zero on physical CPUs.
+    if cpuid(1)[2] & (1 << 31) == 0 {
+        return None;
+    }
+
+    let [_, ebx, ecx, edx] = cpuid(0x4000_0000);
+    let vendor = cpuid_string(&[ebx, ecx, edx]);
+    Some(if vendor.is_empty() { None } else { Some(vendor) })
+}
+
+/// Returns the vendor of the hypervisor (if known) if a hypervisor is present.
+#[cfg(not(any(target_arch="x86", target_arch="x86_64")))]
+fn hypervisor() -> Option<Option<String>> {
+    None
+}
+
+/// Returns the model name of the CPU.
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+fn cpu() -> Option<String> {
+    // The brand string is returned by leaves `0x80000002` to `0x80000004`.
+    if cpuid(0x8000_0000)[0] < 0x8000_0004 {
+        return None;
+    }
+
+    let registers = (0x8000_0002..=0x8000_0004).flat_map(cpuid).collect::<Vec<_>>();
+    Some(cpuid_string(&registers)).filter(|b| !b.is_empty())
+}
+
+/// Returns the model name of the CPU.
+#[cfg(all(target_os="linux", not(any(target_arch="x86", target_arch="x86_64"))))]
+fn cpu() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines()
+        .filter_map(|l| l.split_once(':'))
+        .find(|(k, _)| ["model name", "Model", "Hardware"].contains(&k.trim()))
+        .map(|(_, v)| v.trim().into())
+}
+
+/// Returns the model name of the CPU.
+#[cfg(all(target_os="macos", not(any(target_arch="x86", target_arch="x86_64"))))]
+fn cpu() -> Option<String> {
+    let output = std::process::Command::new("sysctl").args(["-n", "machdep.cpu.brand_string"]).output().ok()?;
+    Some(String::from_utf8(output.stdout).ok()?.trim().into()).filter(|c: &String| !c.is_empty())
+}
+
+/// Returns the model name of the CPU.
+#[cfg(not(any(target_arch="x86", target_arch="x86_64", target_os="linux", target_os="macos")))]
+fn cpu() -> Option<String> {
+    None
+}
+
+/// Returns the `EAX`, `EBX`, `ECX`, and `EDX` registers returned by `CPUID` for
+/// the supplied leaf.
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+fn cpuid(leaf: u32) -> [u32; 4] {
+    #[cfg(target_arch="x86")]
+    use std::arch::x86::{__cpuid};
+    #[cfg(target_arch="x86_64")]
+    use std::arch::x86_64::{__cpuid};
+
+    #[allow(unused_unsafe)]
+    let result = unsafe { __cpuid(leaf) };
+    [result.eax, result.ebx, result.ecx, result.edx]
+}
+
+/// Returns the ASCII string contained in the supplied `CPUID` registers.
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+fn cpuid_string(registers: &[u32]) -> String {
+    let bytes = registers.iter().flat_map(|r| r.to_le_bytes()).collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).trim_matches(|c: char| c == '\0' || c.is_whitespace()).into()
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::{c_void};
+
+    extern "system" {
+        pub fn GetCurrentThread() -> *mut c_void;
+        pub fn SetThreadAffinityMask(thread: *mut c_void, mask: usize) -> usize;
+        pub fn GetThreadPriority(thread: *mut c_void) -> i32;
+        pub fn SetThreadPriority(thread: *mut c_void, priority: i32) -> i32;
+        pub fn SetConsoleCtrlHandler(
+            handler: Option<extern "system" fn(u32) -> i32>, add: i32
+        ) -> i32;
+    }
+
+    pub const THREAD_PRIORITY_ERROR_RETURN: i32 = 0x7FFF_FFFF;
+    pub const THREAD_PRIORITY_HIGHEST: i32 = 2;
+}
+
+/// Returns the current and peak RSS of the current process.
+#[cfg(target_os="linux")]
+fn memory() -> Option<(Bytes, Bytes)> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| {
+        let line = status.lines().find(|l| l.starts_with(name))?;
+        let kibibytes = line[name.len()..].trim().trim_end_matches("kB").trim();
+        kibibytes.parse().ok().map(Bytes::kibibytes)
+    };
+    Some((field("VmRSS:")?, field("VmHWM:")?))
+}
+
+/// Returns the current and peak RSS of the current process.
+#[cfg(all(unix, not(target_os="linux")))]
+fn memory() -> Option<(Bytes, Bytes)> {
+    let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    // `ru_maxrss` is in bytes on macOS and kibibytes on other platforms.
+    let peak = if cfg!(any(target_os="macos", target_os="ios")) {
+        Bytes(usage.ru_maxrss as u64)
+    } else {
+        Bytes::kibibytes(usage.ru_maxrss as u64)
+    };
+
+    // The current RSS is not available so the peak RSS is used instead.
+    Some((peak, peak))
+}
+
+/// Returns the current and peak RSS of the current process.
+#[cfg(windows)]
+fn memory() -> Option<(Bytes, Bytes)> {
+    use std::ffi::{c_void};
+    use std::mem;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Counters {
+        cb: u32,
+        page_fault_count: u32,
+        peak_working_set_size: usize,
+        working_set_size: usize,
+        quota_peak_paged_pool_usage: usize,
+        quota_paged_pool_usage: usize,
+        quota_peak_non_paged_pool_usage: usize,
+        quota_non_paged_pool_usage: usize,
+        pagefile_usage: usize,
+        peak_pagefile_usage: usize,
+    }
+
+    extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn K32GetProcessMemoryInfo(process: *mut c_void, counters: *mut Counters, cb: u32) -> i32;
+    }
+
+    let mut counters = Counters { cb: mem::size_of::<Counters>() as u32, ..Default::default() };
+    if unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, counters.cb) } != 0 {
+        let current = Bytes(counters.working_set_size as u64);
+        let peak = Bytes(counters.peak_working_set_size as u64);
+        Some((current, peak))
+    } else {
+        None
+    }
+}
+
+/// Returns the current and peak RSS of the current process.
+#[cfg(not(any(unix, windows)))]
+fn memory() -> Option<(Bytes, Bytes)> {
+    None
+}
@@ -13,41 +13,319 @@
 // limitations under the License.
 
 //! Time-related utilities.
+//!
+//! On `wasm32` targets, `std::time::Instant` is not available so the `wasm`
+//! crate feature must be enabled to benchmark in a browser (or another
+//! JavaScript environment), in which case the wall clock uses
+//! `performance.now()` instead.
 
-use std::fmt;
-use std::time::{Duration, Instant};
+use core::convert::{TryFrom};
+use core::fmt;
+use core::iter::{Sum};
+use core::ops::{Add, Sub};
+use core::time::{Duration, TryFromFloatSecsError};
+
+use crate::{utility};
+
+#[cfg(feature="std")]
+mod hosted;
+
+#[cfg(feature="std")]
+pub use self::hosted::*;
 
 /// A number of nanoseconds.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// ```
+/// use microbench::time::{Nanoseconds};
+///
+/// assert_eq!(Nanoseconds(5_000_000_000u64).to_string(), "5.0s");
+/// assert_eq!(format!("{:.3}", Nanoseconds(281.7334)), "281.733ns");
+/// assert_eq!(Nanoseconds(0.2013).to_string(), "201.3ps");
+///
+/// use std::convert::{TryFrom};
+/// use std::time::{Duration};
+///
+/// assert_eq!(Duration::from(Nanoseconds(1_500u64)), Duration::from_nanos(1_500));
+/// assert_eq!(Duration::try_from(Nanoseconds(1_499.6)), Ok(Duration::from_nanos(1_500)));
+/// assert!(Duration::try_from(Nanoseconds(-1.0)).is_err());
+/// ```
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Nanoseconds<T>(pub T);
 
+impl Nanoseconds<u64> {
+    /// Returns the difference between these nanoseconds and the supplied
+    /// nanoseconds or `None` if the supplied nanoseconds are greater.
+    pub fn checked_sub(self, other: Nanoseconds<u64>) -> Option<Nanoseconds<u64>> {
+        self.0.checked_sub(other.0).map(Nanoseconds)
+    }
+}
+
+impl<T: Add<Output=T>> Add for Nanoseconds<T> {
+    type Output = Nanoseconds<T>;
+
+    fn add(self, other: Nanoseconds<T>) -> Nanoseconds<T> {
+        Nanoseconds(self.0 + other.0)
+    }
+}
+
+impl<T: Sub<Output=T>> Sub for Nanoseconds<T> {
+    type Output = Nanoseconds<T>;
+
+    fn sub(self, other: Nanoseconds<T>) -> Nanoseconds<T> {
+        Nanoseconds(self.0 - other.0)
+    }
+}
+
+impl<T: Sum> Sum for Nanoseconds<T> {
+    fn sum<I>(iter: I) -> Self where I: Iterator<Item=Nanoseconds<T>> {
+        Nanoseconds(iter.map(|n| n.0).sum())
+    }
+}
+
 impl fmt::Display for Nanoseconds<u64> {
+    /// Formats these nanoseconds in the largest unit (`ns`, `µs`, `ms`, or `s`)
+    /// in which they are at least one (e.g., `5.0s`).
+    ///
+    /// The precision defaults to one decimal place.
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        format_scaled(self.0 as f64, formatter)
+    }
+}
+
+impl fmt::Display for Nanoseconds<f64> {
+    /// Formats these nanoseconds in the largest unit (`ps`, `ns`, `µs`, `ms`, or
+    /// `s`) in which they are at least one (e.g., `281.7ns`).
+    ///
+    /// The precision defaults to one decimal place.
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{:.1}s", self.0 as f64 / 1_000_000_000.0)
+        format_scaled(self.0, formatter)
     }
 }
 
 impl From<Duration> for Nanoseconds<u64> {
+    /// Converts the supplied duration into nanoseconds (saturating at
+    /// `u64::MAX` nanoseconds, i.e., about 584 years).
     fn from(duration: Duration) -> Nanoseconds<u64> {
         let nanos = u64::from(duration.subsec_nanos());
-        Nanoseconds((duration.as_secs() * 1_000_000_000) + nanos)
+        let total = duration.as_secs().checked_mul(1_000_000_000).and_then(|n| n.checked_add(nanos));
+        Nanoseconds(total.unwrap_or(u64::MAX))
+    }
+}
+
+impl From<Duration> for Nanoseconds<f64> {
+    fn from(duration: Duration) -> Nanoseconds<f64> {
+        let nanos = f64::from(duration.subsec_nanos());
+        Nanoseconds((duration.as_secs() as f64 * 1e9) + nanos)
+    }
+}
+
+impl From<Nanoseconds<u64>> for Duration {
+    fn from(nanoseconds: Nanoseconds<u64>) -> Duration {
+        Duration::from_nanos(nanoseconds.0)
+    }
+}
+
+impl TryFrom<Nanoseconds<f64>> for Duration {
+    type Error = TryFromFloatSecsError;
+
+    /// Converts the supplied nanoseconds into a duration (rounding fractional
+    /// nanoseconds to the nearest nanosecond).
+    ///
+    /// Fails if the nanoseconds are negative, not finite, or too large to be
+    /// represented by a duration.
+    fn try_from(nanoseconds: Nanoseconds<f64>) -> Result<Duration, Self::Error> {
+        let seconds = Duration::try_from_secs_f64(nanoseconds.0 / 1e9)?.as_secs();
+        let nanos = (nanoseconds.0 - (seconds as f64 * 1e9)).max(0.0);
+        Ok(Duration::from_secs(seconds) + Duration::from_nanos((nanos + 0.5) as u64))
+    }
+}
+
+/// Formats the supplied number of nanoseconds in the largest unit in which the
+/// magnitude of the number is at least one.
+fn format_scaled(nanoseconds: f64, formatter: &mut fmt::Formatter) -> fmt::Result {
+    let magnitude = if nanoseconds < 0.0 { -nanoseconds } else { nanoseconds };
+    let (value, unit) = match magnitude {
+        m if m >= 1e9 => (nanoseconds / 1e9, "s"),
+        m if m >= 1e6 => (nanoseconds / 1e6, "ms"),
+        m if m >= 1e3 => (nanoseconds / 1e3, "µs"),
+        m if m >= 1.0 || m == 0.0 || m.is_nan() => (nanoseconds, "ns"),
+        _ => (nanoseconds * 1e3, "ps"),
+    };
+    write!(formatter, "{:.*}{}", formatter.precision().unwrap_or(1), value, unit)
+}
+
+/// A source of time.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Clock {
+    /// A monotonic wall clock.
+    Wall,
+    /// A clock that measures the CPU time consumed by the current thread.
+    ///
+    /// This clock is not affected by other threads or processes running on the
+    /// same machine but it may have a lower resolution than the wall clock.
+    /// This clock is not supported on all platforms and falls back to the wall
+    /// clock on unsupported platforms.
+    Thread,
+    /// A clock that measures the CPU time consumed by the current process.
+    ///
+    /// This clock is not affected by other processes running on the same
+    /// machine but it may have a lower resolution than the wall clock. This
+    /// clock is not supported on all platforms and falls back to the wall clock
+    /// on unsupported platforms.
+    Process,
+    /// A clock that counts CPU cycles using the time-stamp counter (TSC).
+    ///
+    /// This clock has a much higher resolution than the wall clock which makes
+    /// it useful for benchmarking very short functions. Readings are converted
+    /// to nanoseconds using the TSC frequency as calibrated against the wall
+    /// clock (see [`cycles_per_nanosecond`](fn.cycles_per_nanosecond.html)).
+    /// This clock is only supported on x86 and x86-64 and falls back to the
+    /// wall clock on other architectures.
+    Cycles,
+}
+
+/// Parses a non-negative duration (e.g., `500ms`, `2.5s`, or `2m`).
+///
+/// The supported units are `ns`, `us` (or `µs`), `ms`, `s`, `m`, and `h`. A
+/// number without a unit is a number of seconds. Durations that cannot be
+/// represented as a `u64` number of nanoseconds (about 584 years) are rejected.
+///
+/// ```
+/// use std::time::{Duration};
+///
+/// use microbench::time::{parse_duration};
+///
+/// assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+/// assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+/// assert_eq!(parse_duration("2.5"), Some(Duration::from_millis(2500)));
+/// assert_eq!(parse_duration("soon"), None);
+/// ```
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let seconds = utility::parse_quantity(value, |unit| match unit {
+        "ns" => Some(1e-9),
+        "us" | "µs" => Some(1e-6),
+        "ms" => Some(1e-3),
+        "" | "s" => Some(1.0),
+        "m" => Some(60.0),
+        "h" => Some(3600.0),
+        _ => None,
+    })?;
+    duration_from_secs(seconds)
+}
+
+/// Returns the supplied non-negative number of seconds as a duration if it can
+/// be represented as a `u64` number of nanoseconds.
+pub(crate) fn duration_from_secs(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok().filter(|d| d.as_nanos() <= u64::MAX as u128)
+}
+
+/// A source of time that can be read by a stopwatch.
+///
+/// This is implemented for [`Clock`](enum.Clock.html) (i.e., the monotonic
+/// wall clock, the CPU-time clocks, and the cycle counter) and for functions
+/// that return the number of nanoseconds that have elapsed since an unspecified
+/// starting point so that measurements can be made with other sources of time
+/// (e.g., a hardware timer) with the same measuring code.
+///
+/// ```
+/// use microbench::time::{Nanoseconds, TimeSource};
+///
+/// let timer = || Nanoseconds(42);
+/// assert_eq!(timer.now(), Nanoseconds(42));
+/// ```
+pub trait TimeSource {
+    /// Returns the number of nanoseconds that have elapsed on this source of
+    /// time since an unspecified starting point.
+    fn now(&self) -> Nanoseconds<u64>;
+
+    /// Returns the resolution of this source of time (if known).
+    ///
+    /// The resolution is used to avoid collecting samples that are too short to
+    /// be measured accurately. If the resolution is unknown (the default), no
+    /// samples are avoided.
+    fn resolution(&self) -> Option<Nanoseconds<u64>> {
+        None
+    }
+}
+
+impl<F: Fn() -> Nanoseconds<u64>> TimeSource for F {
+    fn now(&self) -> Nanoseconds<u64> {
+        self()
     }
 }
 
-/// A high-precision stopwatch.
-#[derive(Clone, Copy, Debug)]
-pub struct Stopwatch(Instant);
+/// A unit of time used to print execution times.
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Unit {
+    /// Nanoseconds (`ns`).
+    Nanos,
+    /// Microseconds (`µs`).
+    Micros,
+    /// Milliseconds (`ms`).
+    Millis,
+    /// Seconds (`s`).
+    Seconds,
+}
+
+impl Unit {
+    /// Returns the supplied number of nanoseconds in this unit.
+    pub fn convert(self, nanoseconds: f64) -> f64 {
+        match self {
+            Unit::Nanos => nanoseconds,
+            Unit::Micros => nanoseconds / 1e3,
+            Unit::Millis => nanoseconds / 1e6,
+            Unit::Seconds => nanoseconds / 1e9,
+        }
+    }
+}
 
-impl Stopwatch {
-    /// Returns the number of nanoseconds that have elapsed since this stopwatch
-    /// was last constructed or reset.
-    pub fn elapsed(self) -> Nanoseconds<u64> {
-        (Instant::now() - self.0).into()
+impl fmt::Display for Unit {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Nanos => write!(formatter, "ns"),
+            Unit::Micros => write!(formatter, "µs"),
+            Unit::Millis => write!(formatter, "ms"),
+            Unit::Seconds => write!(formatter, "s"),
+        }
     }
 }
 
-impl Default for Stopwatch {
-    fn default() -> Self {
-        Stopwatch(Instant::now())
+/// The observed resolution and overhead of a clock.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Probe {
+    /// The smallest nonzero difference observed between consecutive readings.
+    pub resolution: Nanoseconds<u64>,
+    /// The average time taken to read the clock.
+    pub overhead: Nanoseconds<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("3µs"), Some(Duration::from_micros(3)));
+        assert_eq!(parse_duration("3us"), Some(Duration::from_micros(3)));
+        assert_eq!(parse_duration("1e3"), Some(Duration::from_secs(1000)));
+        assert_eq!(parse_duration("1.5e3ms"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("100000000000000000000000"), None);
+        assert_eq!(parse_duration("1e30h"), None);
+        assert_eq!(parse_duration("1e11"), None);
+        assert_eq!(parse_duration("1e7h"), None);
+        assert!(parse_duration("1e10").is_some());
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("1d"), None);
+    }
+
+    #[test]
+    fn test_nanoseconds_from_duration() {
+        assert_eq!(Nanoseconds::<u64>::from(Duration::new(2, 5)), Nanoseconds(2_000_000_005));
+        assert_eq!(Nanoseconds::<u64>::from(Duration::from_secs(u64::MAX)), Nanoseconds(u64::MAX));
+        assert_eq!(Nanoseconds::<u64>::from(Duration::new(18_446_744_073, 999_999_999)), Nanoseconds(u64::MAX));
     }
 }
@@ -0,0 +1,330 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The parts of this module that require the standard library.
+
+use std::sync::{OnceLock};
+use std::time::{Duration};
+#[cfg(not(all(target_arch="wasm32", feature="wasm")))]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{retain};
+
+use super::{Clock, Nanoseconds, Probe, TimeSource};
+
+impl Clock {
+    /// Returns the number of nanoseconds that have elapsed on this clock since
+    /// an unspecified starting point.
+    pub fn now(self) -> Nanoseconds<u64> {
+        match self {
+            Clock::Wall => wall(),
+            Clock::Thread => cpu(CpuClock::Thread).unwrap_or_else(wall),
+            Clock::Process => cpu(CpuClock::Process).unwrap_or_else(wall),
+            Clock::Cycles => match (tsc(), cycles_per_nanosecond()) {
+                (Some(cycles), Some(frequency)) => Nanoseconds((cycles as f64 / frequency) as u64),
+                _ => wall(),
+            },
+        }
+    }
+}
+
+impl TimeSource for Clock {
+    fn now(&self) -> Nanoseconds<u64> {
+        Clock::now(*self)
+    }
+
+    fn resolution(&self) -> Option<Nanoseconds<u64>> {
+        Some(probe(*self).resolution)
+    }
+}
+
+/// Returns the observed resolution and overhead of the supplied clock.
+///
+/// The clock is probed the first time this function is called for the clock
+/// which takes approximately a millisecond (more for clocks with a low
+/// resolution). Execution times that are not much larger than the resolution
+/// and overhead of the clock used to measure them (e.g., sub-nanosecond
+/// estimates) should be treated with suspicion. The resolution is also used to
+/// avoid collecting samples that are too short to be measured accurately.
+///
+/// ```
+/// use microbench::time::{self, Clock};
+///
+/// let probe = time::probe(Clock::Wall);
+/// println!("resolution: {}, overhead: {}", probe.resolution, probe.overhead);
+/// ```
+pub fn probe(clock: Clock) -> Probe {
+    static PROBES: [OnceLock<Probe>; 4] = [OnceLock::new(), OnceLock::new(), OnceLock::new(), OnceLock::new()];
+    *PROBES[clock as usize].get_or_init(|| {
+        const READINGS: u64 = 1_000;
+
+        let mut resolution = u64::MAX;
+        for _ in 0..100 {
+            let start = clock.now();
+            let mut now = start;
+            while now <= start { now = clock.now(); }
+            resolution = resolution.min(now.0 - start.0);
+        }
+
+        let stopwatch = Stopwatch::new(clock);
+        for _ in 0..READINGS { retain(clock.now()); }
+        let overhead = stopwatch.elapsed().0 as f64 / READINGS as f64;
+
+        let probe = Probe { resolution: Nanoseconds(resolution), overhead: Nanoseconds(overhead) };
+        debug!("probed {:?} clock: resolution {}, overhead {:.1}", clock, probe.resolution, probe.overhead);
+        probe
+    })
+}
+
+/// Returns the number of TSC cycles per nanosecond (i.e., the TSC frequency in
+/// GHz) or `None` if the TSC is not supported on this architecture.
+///
+/// The TSC frequency is calibrated against the wall clock the first time this
+/// function is called which takes approximately ten milliseconds.
+pub fn cycles_per_nanosecond() -> Option<f64> {
+    static FREQUENCY: OnceLock<Option<f64>> = OnceLock::new();
+    *FREQUENCY.get_or_init(|| {
+        let (cycles, start) = (tsc()?, wall());
+        while wall().0 - start.0 < 10_000_000 { }
+        let (cycles, elapsed) = (tsc()? - cycles, wall().0 - start.0);
+        let frequency = cycles as f64 / elapsed as f64;
+        debug!("calibrated TSC frequency: {:.3} GHz", frequency);
+        Some(frequency)
+    })
+}
+
+/// Returns the current value of the TSC using serializing instructions.
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+fn tsc() -> Option<u64> {
+    #[cfg(target_arch="x86")]
+    use std::arch::x86::{_mm_lfence, _rdtsc};
+    #[cfg(target_arch="x86_64")]
+    use std::arch::x86_64::{_mm_lfence, _rdtsc};
+    unsafe {
+        _mm_lfence();
+        let cycles = _rdtsc();
+        _mm_lfence();
+        Some(cycles)
+    }
+}
+
+/// Returns the current value of the TSC using serializing instructions.
+#[cfg(not(any(target_arch="x86", target_arch="x86_64")))]
+fn tsc() -> Option<u64> {
+    None
+}
+
+/// Returns the number of nanoseconds that have elapsed on the wall clock since
+/// this function was first called.
+#[cfg(not(all(target_arch="wasm32", feature="wasm")))]
+fn wall() -> Nanoseconds<u64> {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().into()
+}
+
+/// Returns the number of nanoseconds that have elapsed on the wall clock since
+/// this function was first called.
+///
+/// `performance.now()` may have a reduced resolution in browsers (e.g., 5 µs
+/// or 100 µs) as a mitigation for timing attacks.
+#[cfg(all(target_arch="wasm32", feature="wasm"))]
+fn wall() -> Nanoseconds<u64> {
+    use js_sys::{Function, Reflect};
+    use js_sys::wasm_bindgen::{JsValue};
+
+    thread_local! {
+        /// The `performance` object and the `performance.now()` function.
+        static PERFORMANCE: (JsValue, Function) = {
+            let performance = Reflect::get(&js_sys::global(), &"performance".into())
+                .expect("`performance` is not available");
+            let now = Reflect::get(&performance, &"now".into())
+                .expect("`performance.now()` is not available");
+            (performance, Function::from(now))
+        };
+        /// The value of `performance.now()` when this function was first called.
+        static EPOCH: f64 = now();
+    }
+
+    fn now() -> f64 {
+        PERFORMANCE.with(|(p, n)| n.call0(p).ok().and_then(|v| v.as_f64()).unwrap_or(0.0))
+    }
+
+    let milliseconds = now() - EPOCH.with(|e| *e);
+    Nanoseconds((milliseconds.max(0.0) * 1_000_000.0) as u64)
+}
+
+/// Returns the amount of time that has elapsed since the Unix epoch on the
+/// system clock.
+#[cfg(not(all(target_arch="wasm32", feature="wasm")))]
+pub(crate) fn since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Returns the amount of time that has elapsed since the Unix epoch on the
+/// system clock.
+#[cfg(all(target_arch="wasm32", feature="wasm"))]
+pub(crate) fn since_epoch() -> Duration {
+    Duration::from_secs_f64(js_sys::Date::now().max(0.0) / 1_000.0)
+}
+
+/// A CPU time clock.
+#[derive(Copy, Clone, Debug)]
+enum CpuClock {
+    Thread,
+    Process,
+}
+
+/// Returns the number of nanoseconds of CPU time consumed by the current thread
+/// or process.
+#[cfg(unix)]
+fn cpu(clock: CpuClock) -> Option<Nanoseconds<u64>> {
+    let id = match clock {
+        CpuClock::Thread => libc::CLOCK_THREAD_CPUTIME_ID,
+        CpuClock::Process => libc::CLOCK_PROCESS_CPUTIME_ID,
+    };
+
+    let mut timespec = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    if unsafe { libc::clock_gettime(id, &mut timespec) } == 0 {
+        Some(Nanoseconds((timespec.tv_sec as u64 * 1_000_000_000) + timespec.tv_nsec as u64))
+    } else {
+        None
+    }
+}
+
+/// Returns the number of nanoseconds of CPU time consumed by the current thread
+/// or process.
+#[cfg(windows)]
+fn cpu(clock: CpuClock) -> Option<Nanoseconds<u64>> {
+    use std::ffi::{c_void};
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Default)]
+    struct FileTime(u32, u32);
+
+    type Times = unsafe extern "system" fn(
+        *mut c_void, *mut FileTime, *mut FileTime, *mut FileTime, *mut FileTime
+    ) -> i32;
+
+    extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn GetCurrentThread() -> *mut c_void;
+        fn GetProcessTimes(
+            handle: *mut c_void,
+            creation: *mut FileTime,
+            exit: *mut FileTime,
+            kernel: *mut FileTime,
+            user: *mut FileTime,
+        ) -> i32;
+        fn GetThreadTimes(
+            handle: *mut c_void,
+            creation: *mut FileTime,
+            exit: *mut FileTime,
+            kernel: *mut FileTime,
+            user: *mut FileTime,
+        ) -> i32;
+    }
+
+    let (handle, times): (_, Times) = unsafe {
+        match clock {
+            CpuClock::Thread => (GetCurrentThread(), GetThreadTimes),
+            CpuClock::Process => (GetCurrentProcess(), GetProcessTimes),
+        }
+    };
+
+    let mut filetimes = [FileTime::default(); 4];
+    let [creation, exit, kernel, user] = &mut filetimes;
+    if unsafe { times(handle, creation, exit, kernel, user) } != 0 {
+        let hundreds = |f: &FileTime| (u64::from(f.1) << 32) | u64::from(f.0);
+        Some(Nanoseconds((hundreds(&filetimes[2]) + hundreds(&filetimes[3])) * 100))
+    } else {
+        None
+    }
+}
+
+/// Returns the number of nanoseconds of CPU time consumed by the current thread
+/// or process.
+#[cfg(not(any(unix, windows)))]
+fn cpu(_: CpuClock) -> Option<Nanoseconds<u64>> {
+    None
+}
+
+/// A high-precision stopwatch.
+///
+/// ```
+/// use std::time::{Duration};
+///
+/// use microbench::{Options, measure_custom, retain};
+/// use microbench::time::{Clock, Stopwatch};
+///
+/// let options = Options::default().time(Duration::from_millis(10));
+/// let samples = measure_custom(&options, |iterations| {
+///     let mut stopwatch = Stopwatch::new(Clock::Wall);
+///     let mut elapsed = Duration::ZERO;
+///     for _ in 0..iterations {
+///         let data = retain(vec![1u64; 64]);
+///         stopwatch.lap();
+///         retain(data.iter().sum::<u64>());
+///         elapsed += Duration::from(stopwatch.lap());
+///     }
+///     elapsed
+/// });
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Stopwatch<C: TimeSource = Clock> {
+    clock: C,
+    lap: Nanoseconds<u64>,
+    start: Nanoseconds<u64>,
+}
+
+impl<C: TimeSource> Stopwatch<C> {
+    /// Constructs a new `Stopwatch` that uses the supplied source of time.
+    pub fn new(clock: C) -> Self {
+        let start = clock.now();
+        Stopwatch { clock, lap: start, start }
+    }
+
+    /// Returns the number of nanoseconds that have elapsed since this stopwatch
+    /// was last constructed or reset.
+    pub fn elapsed(&self) -> Nanoseconds<u64> {
+        Nanoseconds(self.clock.now().0.saturating_sub(self.start.0))
+    }
+
+    /// Returns the amount of time that has elapsed since this stopwatch was
+    /// last constructed or reset.
+    pub fn elapsed_duration(&self) -> Duration {
+        self.elapsed().into()
+    }
+
+    /// Returns the number of nanoseconds that have elapsed since the last lap
+    /// (or since this stopwatch was last constructed or reset) and starts a new
+    /// lap.
+    pub fn lap(&mut self) -> Nanoseconds<u64> {
+        let now = self.clock.now();
+        let lap = Nanoseconds(now.0.saturating_sub(self.lap.0));
+        self.lap = now;
+        lap
+    }
+
+    /// Resets this stopwatch (and starts a new lap).
+    pub fn reset(&mut self) {
+        self.start = self.clock.now();
+        self.lap = self.start;
+    }
+}
+
+impl Default for Stopwatch<Clock> {
+    fn default() -> Self {
+        Stopwatch::new(Clock::Wall)
+    }
+}
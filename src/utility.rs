@@ -14,17 +14,53 @@
 
 //! Miscellaneous utilities.
 
+#[cfg(feature="std")]
+mod hosted;
+
+#[cfg(feature="std")]
+pub use self::hosted::*;
+
+use core::hint;
+
 /// Generates unique values from a geometric sequence.
+///
+/// The values are the distinct integer parts of `start × factorⁿ` in increasing
+/// order (e.g., `1, 2, 3, 5, 7, 11, ...` for a factor of `1.5`). Each value is
+/// greater than the previous value even if the factor is not greater than one
+/// (in which case the values are consecutive). The sequence ends after the
+/// upper bound (see [`end`](#method.end)) or `u64::MAX` is reached.
+///
+/// This is the sequence of iteration counts used to collect samples and can be
+/// used to collect samples with a custom schedule (e.g., with
+/// [`measure_custom`](fn.measure_custom.html)).
+///
+/// ```
+/// use microbench::{GeometricSequence};
+///
+/// let values = GeometricSequence::new(1, 1.5).end(20).collect::<Vec<_>>();
+/// assert_eq!(values, &[1, 2, 3, 5, 7, 11, 17]);
+/// ```
 #[derive(Copy, Clone, Debug)]
 pub struct GeometricSequence {
     current: f64,
+    end: u64,
     factor: f64,
+    next: Option<u64>,
 }
 
 impl GeometricSequence {
-    /// Constructs a new `GeometricSequence`.
+    /// Constructs a new `GeometricSequence` that starts at the supplied value
+    /// and grows by the supplied factor.
     pub fn new(start: u64, factor: f64) -> Self {
-        GeometricSequence { current: start as f64, factor }
+        GeometricSequence { current: start as f64, end: u64::MAX, factor, next: Some(start) }
+    }
+
+    /// Sets the largest value in this sequence.
+    ///
+    /// **Default:** `u64::MAX`
+    pub fn end(mut self, end: u64) -> Self {
+        self.end = end;
+        self
     }
 }
 
@@ -32,47 +68,99 @@ impl Iterator for GeometricSequence {
     type Item = u64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let start = self.current as u64;
-        while self.current as u64 == start { self.current *= self.factor; }
-        Some(start)
+        let value = self.next.filter(|v| *v <= self.end)?;
+
+        // The next value must be larger even if the factor is too small to
+        // increase the current value (or the current value is imprecise).
+        self.next = value.checked_add(1).map(|minimum| {
+            if self.factor.is_nan() || self.factor <= 1.0 {
+                return minimum;
+            }
+
+            while (self.current as u64) < minimum && self.current > 0.0 { self.current *= self.factor; }
+            let next = (self.current as u64).max(minimum);
+            self.current = self.current.max(next as f64);
+            next
+        });
+        Some(value)
     }
 }
 
 /// A function that prevents the optimizer from eliminating the supplied value.
-#[cfg(feature="nightly")]
 pub fn black_box<T>(dummy: T) -> T {
-    use std::hint;
     hint::black_box(dummy)
 }
 
-/// A function that prevents the optimizer from eliminating the supplied value.
-#[cfg(not(feature="nightly"))]
-pub fn black_box<T>(dummy: T) -> T {
-    use std::mem;
-    use std::ptr;
-    unsafe {
-        let value = ptr::read_volatile(&dummy);
-        mem::forget(dummy);
-        value
-    }
+/// Parses a non-negative number followed by an optional unit (e.g., `2.5ms`)
+/// and returns the number multiplied by the scale of the unit as returned by
+/// the supplied function.
+pub fn parse_quantity(value: &str, scale: impl FnOnce(&str) -> Option<f64>) -> Option<f64> {
+    // The unit starts at the first letter that is not an exponent (e.g., `1e3`).
+    let value = value.trim();
+    let exponent = |i: usize, c: char| {
+        let next = value[i + c.len_utf8()..].chars().next();
+        i != 0 && (c == 'e' || c == 'E') && next.is_some_and(|n| n.is_ascii_digit() || n == '+' || n == '-')
+    };
+    let index = value.char_indices()
+        .find(|&(i, c)| c.is_alphabetic() && !exponent(i, c))
+        .map_or(value.len(), |(i, _)| i);
+    let (number, unit) = value.split_at(index);
+    let quantity = number.trim().parse::<f64>().ok()? * scale(unit.trim())?;
+    if quantity.is_finite() && quantity >= 0.0 { Some(quantity) } else { None }
 }
 
-/// Returns the supplied floating-point number formatted with the supplied
-/// precision and thousands separator.
-pub fn format_number(number: f64, precision: usize, separator: char) -> String {
-    let mut integral = String::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometric_sequence() {
+        let sequence = |start, factor| GeometricSequence::new(start, factor);
+        assert!(sequence(1, 1.5).take(7).eq([1, 2, 3, 5, 7, 11, 17]));
+        assert!(sequence(10, 2.0).take(4).eq([10, 20, 40, 80]));
 
-    let mut counter = 0;
-    for digit in (number as u64).to_string().chars().rev() {
-        if counter == 3 {
-            integral.insert(0, separator);
-            counter = 0;
-        }
+        // The values are consecutive if the factor is too small to grow them.
+        assert!(sequence(1, 1.0).take(4).eq([1, 2, 3, 4]));
+        assert!(sequence(1, 0.5).take(4).eq([1, 2, 3, 4]));
+        assert!(sequence(1, -2.0).take(4).eq([1, 2, 3, 4]));
+        assert!(sequence(1, f64::NAN).take(4).eq([1, 2, 3, 4]));
+        assert!(sequence(1, 1.01).take(4).eq([1, 2, 3, 4]));
 
-        counter += 1;
-        integral.insert(0, digit);
+        assert!(sequence(0, 2.0).take(5).eq([0, 1, 2, 4, 8]));
+        assert!(sequence(0, 1.0).take(3).eq([0, 1, 2]));
+
+        // The sequence ends after `u64::MAX` without overflowing.
+        assert!(sequence(u64::MAX - 2, 1.0).eq([u64::MAX - 2, u64::MAX - 1, u64::MAX]));
+        assert!(sequence(u64::MAX, 2.0).eq([u64::MAX]));
+        assert_eq!(sequence(1 << 62, 2.0).count(), 3);
+        assert_eq!(sequence(1, 1.5).last(), Some(u64::MAX));
+
+        assert!(sequence(1, 2.0).end(8).eq([1, 2, 4, 8]));
+        assert!(sequence(1, 2.0).end(7).eq([1, 2, 4]));
+        assert!(sequence(1, 2.0).end(1).eq([1]));
+        assert_eq!(sequence(1, 2.0).end(0).next(), None);
+        assert!(sequence(5, 1.0).end(u64::MAX).take(2).eq([5, 6]));
     }
 
-    let fractional = format!("{:.*}", precision, number.fract());
-    format!("{}.{}", integral, &fractional[2..])
+    #[test]
+    fn test_parse_quantity() {
+        let scale = |unit: &str| match unit {
+            "" => Some(1.0),
+            "k" => Some(1e3),
+            "µs" => Some(1e-6),
+            "EiB" => Some((1u64 << 60) as f64),
+            _ => None,
+        };
+        assert_eq!(parse_quantity("2.5", scale), Some(2.5));
+        assert_eq!(parse_quantity(" 2 k ", scale), Some(2000.0));
+        assert_eq!(parse_quantity("1e3", scale), Some(1000.0));
+        assert_eq!(parse_quantity("1.5E-3", scale), Some(0.0015));
+        assert_eq!(parse_quantity("1e3k", scale), Some(1e6));
+        assert_eq!(parse_quantity("3µs", scale), Some(3e-6));
+        assert_eq!(parse_quantity("1EiB", scale), Some((1u64 << 60) as f64));
+        assert_eq!(parse_quantity("1e400", scale), None);
+        assert_eq!(parse_quantity("-1", scale), None);
+        assert_eq!(parse_quantity("1x", scale), None);
+        assert_eq!(parse_quantity("e3", scale), None);
+    }
 }
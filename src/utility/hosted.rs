@@ -0,0 +1,208 @@
+// Copyright 2016 Kyle Mayes
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The parts of this module that require the standard library.
+
+use super::{black_box};
+
+/// A small, fast, non-cryptographic pseudorandom number generator (xorshift64*).
+#[derive(Copy, Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Constructs a new `Random` with the supplied seed.
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed.max(1) }
+    }
+
+    /// Constructs a new `Random` seeded with the current system time.
+    pub fn from_time() -> Self {
+        Random::new(crate::time::since_epoch().as_nanos() as u64)
+    }
+
+    /// Returns the next pseudorandom number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Shuffles the supplied values in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            values.swap(i, j);
+        }
+    }
+}
+
+/// Evicts (most of) the contents of the CPU caches by writing to every cache
+/// line of the supplied buffer (which should be larger than the caches).
+pub fn flush(buffer: &mut [u8]) {
+    for byte in buffer.iter_mut().step_by(64) {
+        *byte = black_box(byte.wrapping_add(1));
+    }
+}
+
+/// Returns the supplied floating-point number formatted with three significant
+/// digits and an SI suffix (e.g., `9.41k` or `2.3M`).
+pub fn format_compact(number: f64) -> String {
+    const SUFFIXES: &[&str] = &["", "k", "M", "G", "T", "P", "E"];
+
+    if !number.is_finite() {
+        return number.to_string();
+    }
+
+    let (mut scaled, mut index) = (number, 0);
+    while scaled.abs() >= 999.5 && index + 1 < SUFFIXES.len() {
+        scaled /= 1000.0;
+        index += 1;
+    }
+
+    let precision = match scaled.abs() {
+        magnitude if magnitude >= 99.95 => 0,
+        magnitude if magnitude >= 9.995 => 1,
+        magnitude if magnitude >= 0.9995 => 2,
+        _ => 3,
+    };
+    let mut string = format!("{:.*}", precision, scaled);
+    if string.contains('.') {
+        string.truncate(string.trim_end_matches('0').trim_end_matches('.').len());
+    }
+    if string == "-0" {
+        string.remove(0);
+    }
+    string + SUFFIXES[index]
+}
+
+/// Returns the supplied floating-point number formatted with the supplied
+/// precision and thousands separator.
+pub fn format_number(number: f64, precision: usize, separator: char) -> String {
+    let mut integral = String::new();
+
+    let mut counter = 0;
+    for digit in (number as u64).to_string().chars().rev() {
+        if counter == 3 {
+            integral.insert(0, separator);
+            counter = 0;
+        }
+
+        counter += 1;
+        integral.insert(0, digit);
+    }
+
+    let fractional = format!("{:.*}", precision, number.fract());
+    format!("{}.{}", integral, &fractional[2..])
+}
+
+/// Returns whether the supplied text matches the supplied glob pattern (where
+/// `*` matches any sequence of characters and `?` matches any character).
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    backtrack = Some((bp, bt + 1));
+                    p = bp + 1;
+                    t = bt + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Returns the supplied string as a JSON string literal.
+pub fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Returns the supplied floating-point number as a JSON number (or `null` if
+/// the number is not finite).
+pub fn json_number(number: f64) -> String {
+    if number.is_finite() { number.to_string() } else { "null".into() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_compact() {
+        assert_eq!(format_compact(0.0), "0");
+        assert_eq!(format_compact(12.0), "12");
+        assert_eq!(format_compact(1234.0), "1.23k");
+        assert_eq!(format_compact(9_410.0), "9.41k");
+        assert_eq!(format_compact(2_300_000.0), "2.3M");
+
+        // Numbers are rounded to three significant digits (moving to the next
+        // suffix or precision when rounding would add a digit).
+        assert_eq!(format_compact(999.4), "999");
+        assert_eq!(format_compact(999.5), "1k");
+        assert_eq!(format_compact(999_499.0), "999k");
+        assert_eq!(format_compact(999_500.0), "1M");
+        assert_eq!(format_compact(99.94), "99.9");
+        assert_eq!(format_compact(99.95), "100");
+        assert_eq!(format_compact(9.994), "9.99");
+        assert_eq!(format_compact(9.995), "10");
+        assert_eq!(format_compact(0.9994), "0.999");
+        assert_eq!(format_compact(0.9995), "1");
+
+        // Trailing zeros (and decimal points) are trimmed.
+        assert_eq!(format_compact(1.5), "1.5");
+        assert_eq!(format_compact(10.1), "10.1");
+        assert_eq!(format_compact(100.0), "100");
+        assert_eq!(format_compact(2_000.0), "2k");
+
+        assert_eq!(format_compact(-1234.0), "-1.23k");
+        assert_eq!(format_compact(-999.5), "-1k");
+        assert_eq!(format_compact(-0.5), "-0.5");
+        assert_eq!(format_compact(-0.0004), "0");
+
+        assert_eq!(format_compact(f64::NAN), "NaN");
+        assert_eq!(format_compact(f64::INFINITY), "inf");
+        assert_eq!(format_compact(f64::NEG_INFINITY), "-inf");
+    }
+}